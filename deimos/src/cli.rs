@@ -1,17 +1,26 @@
+use deimos_codegen::{CodegenOptions, DumpFormat, Machine, Sources};
+use std::collections::HashSet;
 use std::env::Args;
 use std::error::Error;
 use std::fmt::Display;
 use std::fs;
 use std::path::Path;
 
-const DEFAULT_OUTNAME: &'static str = "out.asm";
-static LIB_SOURCE: &'static str = include_str!("lib.dei");
+const DEFAULT_OUTNAME: &str = "out.asm";
+const SOURCE_MAP_EXT: &str = ".map";
+const TEXT_DUMP_EXT: &str = "text";
+const DATA_DUMP_EXT: &str = "data";
+const ELF_EXT: &str = "elf";
+static LIB_SOURCE: &str = include_str!("lib.dei");
+static STRING_SOURCE: &str = include_str!("string.dei");
 
 #[derive(Debug)]
 pub enum CliArgError {
     MissingSourceFile,
     NonexistantSourceFile,
     MissingOutArg,
+    InvalidStackBudget,
+    InvalidEmitFormat,
 }
 
 impl Display for CliArgError {
@@ -23,6 +32,8 @@ impl Display for CliArgError {
                 Self::MissingSourceFile => "Missing source code file",
                 Self::NonexistantSourceFile => "Source code file doesn't exist",
                 Self::MissingOutArg => "Argument to '-o' is missing",
+                Self::InvalidStackBudget => "Argument to '-stack-budget' must be a byte count",
+                Self::InvalidEmitFormat => "Argument to '-emit' must be 'dump', 'hex' or 'elf'",
             }
         )
     }
@@ -36,10 +47,23 @@ pub enum DebugStage {
     Parse,
 }
 
+/// Machine code written next to the assembly
+#[derive(Clone, Copy)]
+pub enum Emit {
+    /// MARS memory dumps of the text and data segments
+    Dump(DumpFormat),
+    /// Big-endian MIPS32 executable. It still makes MARS syscalls, so
+    /// it's for disassemblers and emulators rather than MIPS Linux.
+    Elf,
+}
+
 pub struct CliArgs {
     source: String,
     out: Option<String>,
     debug_stage: Option<DebugStage>,
+    debug_info: bool,
+    emit: Option<Emit>,
+    options: CodegenOptions,
 }
 
 impl CliArgs {
@@ -48,6 +72,9 @@ impl CliArgs {
         let mut out = None;
         let mut args = args.skip(1);
         let mut debug_stage = None;
+        let mut debug_info = false;
+        let mut emit = None;
+        let mut options = CodegenOptions::default();
 
         while let Some(arg) = args.next() {
             match arg.as_str() {
@@ -64,6 +91,44 @@ impl CliArgs {
                 "-debug-stage=parse" => {
                     debug_stage = Some(DebugStage::Parse);
                 }
+                "-g" => {
+                    debug_info = true;
+                }
+                "-strict" => {
+                    options.strict_conditions = true;
+                }
+                "-lib" => {
+                    options.library = true;
+                }
+                "-export-prelude" => {
+                    options.export_shared = true;
+                }
+                "-readable-labels" => {
+                    options.readable_labels = true;
+                }
+                "-checks" => {
+                    options.runtime_checks = true;
+                }
+                "-stack-report" => {
+                    options.stack_report = true;
+                }
+                _ if arg.starts_with("-stack-budget=") => {
+                    let budget = &arg["-stack-budget=".len()..];
+                    match budget.parse() {
+                        Ok(budget) => options.stack_budget = Some(budget),
+                        Err(_) => return Err(CliArgError::InvalidStackBudget),
+                    }
+                }
+                _ if arg.starts_with("-emit=") => {
+                    let (format, machine) = match &arg["-emit=".len()..] {
+                        "dump" => (Emit::Dump(DumpFormat::Binary), Machine::Mars),
+                        "hex" => (Emit::Dump(DumpFormat::HexText), Machine::Mars),
+                        "elf" => (Emit::Elf, Machine::Mips32),
+                        _ => return Err(CliArgError::InvalidEmitFormat),
+                    };
+                    emit = Some(format);
+                    options.machine_code = Some(machine);
+                }
                 _ => {
                     if Path::new(arg.as_str()).exists() {
                         source = arg;
@@ -82,12 +147,22 @@ impl CliArgs {
             source,
             out,
             debug_stage,
+            debug_info,
+            emit,
+            options,
         })
     }
 
-    pub fn invoke(self) -> Result<(), Box<dyn Error>> {
-        let mut source = fs::read_to_string(self.source)?;
-        source.push_str(&LIB_SOURCE);
+    pub fn invoke(mut self) -> Result<(), Box<dyn Error>> {
+        self.options.shared = prelude_names()?;
+        let mut sources = Sources::default();
+        sources.add(self.source.as_str(), fs::read_to_string(&self.source)?);
+        sources.add("lib.dei", LIB_SOURCE);
+        sources.add("string.dei", STRING_SOURCE);
+        let source = sources.text();
+        if self.debug_info {
+            self.options.debug_info = Some(sources);
+        }
 
         let tokens = deimos_parser::lex(&source)?;
         if let Some(DebugStage::Lex) = self.debug_stage {
@@ -101,9 +176,40 @@ impl CliArgs {
             return Ok(());
         }
 
-        let codegen = deimos_codegen::codegen(&ast)?;
-        std::fs::write(self.out.as_deref().unwrap_or(DEFAULT_OUTNAME), codegen)?;
+        let codegen = deimos_codegen::codegen(&ast, &self.options)?;
+        if let Some(report) = codegen.stack_report {
+            print!("{}", report);
+        }
+        let out = self.out.as_deref().unwrap_or(DEFAULT_OUTNAME);
+        std::fs::write(out, codegen.asm)?;
+        if let Some(source_map) = codegen.source_map {
+            std::fs::write(format!("{}{}", out, SOURCE_MAP_EXT), source_map)?;
+        }
+        if let (Some(emit), Some(image)) = (self.emit, codegen.image) {
+            let out = Path::new(out);
+            match emit {
+                Emit::Dump(format) => {
+                    fs::write(out.with_extension(TEXT_DUMP_EXT), image.text_dump(format))?;
+                    fs::write(out.with_extension(DATA_DUMP_EXT), image.data_dump(format))?;
+                }
+                Emit::Elf => fs::write(out.with_extension(ELF_EXT), image.elf())?,
+            }
+        }
 
         Ok(())
     }
 }
+
+/// Names of the subs and statics the prelude defines. Every unit is
+/// compiled with the prelude, but only the main unit emits it, and it
+/// only exports it with `-export-prelude`.
+fn prelude_names() -> Result<HashSet<String>, Box<dyn Error>> {
+    let source = format!("{}{}", LIB_SOURCE, STRING_SOURCE);
+    let prelude = deimos_parser::parse(deimos_parser::lex(&source)?)?;
+    let fns = prelude.fns.iter().map(|f| f.name);
+    let statics = prelude.static_vars.iter().map(|s| s.name);
+    Ok(fns
+        .chain(statics)
+        .map(|name| prelude.bank.identifiers[name.data].clone())
+        .collect())
+}
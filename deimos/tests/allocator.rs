@@ -0,0 +1,71 @@
+mod common;
+
+#[test]
+fn malloc_returns_separate_aligned_blocks() {
+    let run = common::run(
+        "program {
+            let a: &u8, b: &u8, none: &u8;
+            call malloc(5u, &a);
+            call malloc(12u, &b);
+            call memset(a, 1 as u8, 5u);
+            call memset(b, 2 as u8, 12u);
+            print (a as u32) % 8u, \" \", (b as u32) % 8u, \" \";
+            print (b as u32) - (a as u32) >= 16u, \" \", *(a + 4) as u32, \" \", *b as u32;
+            call malloc(0u, &none);
+            print \" \", (none as u32) == 0u;
+            call free(none);
+            call free(a);
+            call free(b);
+        }",
+    );
+    assert_eq!(run.stdout, "0 0 true 1 2 true");
+}
+
+#[test]
+fn freed_blocks_are_reused() {
+    let run = common::run(
+        "program {
+            let a: &u8, b: &u8, c: &u8, d: &u8, e: &u8;
+            call malloc(16u, &a);
+            call malloc(16u, &b);
+            call free(a);
+            call malloc(8u, &c);
+            print (c as u32) == (a as u32), \" \";
+            # c took all of a's block, so d comes from a new sbrk
+            call malloc(16u, &d);
+            print (d as u32) > (b as u32), \" \";
+            call free(c);
+            call free(b);
+            # a and b merged back into one block
+            call malloc(40u, &e);
+            print (e as u32) == (a as u32);
+        }",
+    );
+    assert_eq!(run.stdout, "true true true");
+}
+
+#[test]
+fn realloc_keeps_contents() {
+    let run = common::run(
+        "program {
+            let a: &u8, b: &u8;
+            call malloc(4u, &a);
+            call strcpy(a, \"abc\");
+            call realloc(a, 100u, &b);
+            print b, \" \", (b as u32) != (a as u32);
+            call realloc(b, 10u, &a);
+            print \" \", (b as u32) == (a as u32);
+        }",
+    );
+    assert_eq!(run.stdout, "abc true true");
+}
+
+#[test]
+fn vector_sample_grows() {
+    let run = common::run_with_input(include_str!("../../samples/vector.dei"), "10\n");
+    let expected: String = (0..10).map(|i| format!("{}\n", i * i)).collect();
+    assert_eq!(
+        run.stdout,
+        format!("Enter the number of squares to store: {expected}")
+    );
+}
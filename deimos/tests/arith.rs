@@ -0,0 +1,41 @@
+use deimos_codegen::CodegenOptions;
+
+fn compile(src: &str) -> String {
+    let tokens = deimos_parser::lex(src).unwrap();
+    let ast = deimos_parser::parse(tokens).unwrap();
+    deimos_codegen::codegen(&ast, &CodegenOptions::default())
+        .unwrap()
+        .asm
+}
+
+/// Mnemonics of the instructions in `main`, up to the next label
+fn main_mnemonics(asm: &str) -> Vec<&str> {
+    asm.lines()
+        .skip_while(|line| *line != "main:")
+        .skip(1)
+        .take_while(|line| !line.ends_with(':'))
+        .filter_map(|line| line.split_whitespace().next())
+        .collect()
+}
+
+#[test]
+fn unsigned_division_uses_divu() {
+    let asm = compile(
+        "static a: u32 = 4000000000u; static b: u32 = 3u;
+         program { a = a / b; b = a % b; }",
+    );
+    let ops = main_mnemonics(&asm);
+    assert_eq!(ops.iter().filter(|op| **op == "divu").count(), 2);
+    assert!(!ops.contains(&"div"));
+}
+
+#[test]
+fn signed_division_uses_div() {
+    let asm = compile(
+        "static a: i32 = -7; static b: i32 = 2;
+         program { a = a / b; b = a % b; }",
+    );
+    let ops = main_mnemonics(&asm);
+    assert_eq!(ops.iter().filter(|op| **op == "div").count(), 2);
+    assert!(!ops.contains(&"divu"));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+#[test]
+fn asm_accepts_directives_and_comments() {
+    let run = common::run(
+        "program {
+            let x: i32 = 1;
+            asm {
+                \"# skip over a data word\"
+                \"b asm_test_skip\"
+                \".word 0xffffffff\"
+                \"asm_test_skip: addi {x}, {x}, 1  # x += 1\";
+            }
+            print x;
+        }",
+    );
+    assert_eq!(run.stdout, "2");
+}
+
+#[test]
+fn numbered_registers_are_not_given_to_operands() {
+    let run = common::run(
+        "program {
+            let x: i32 = 1, y: i32 = 2;
+            asm {
+                \"li $8, 5\"
+                \"li $9, 7\"
+                \"add {x}, {x}, $8\"
+                \"add {y}, {y}, $9\";
+            }
+            print x, \" \", y;
+        }",
+    );
+    assert_eq!(run.stdout, "6 9");
+}
+
+#[test]
+fn unknown_mnemonics_are_rejected() {
+    let err = common::compile(
+        "program { asm { \"frob $t0  # .word\"; } }",
+        &Default::default(),
+    )
+    .err();
+    assert!(
+        err.as_deref().is_some_and(|e| e.contains("frob")),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,93 @@
+mod common;
+
+#[test]
+fn mixed_int_and_float_arguments() {
+    let run = common::run(
+        "sub mix(a: f32, b: i32, c: f32, d: i32, e: f32, f: i32, o: &f32) {
+            *o = a * 100.0 + b as f32 * 10.0 + c + d as f32 * 0.5 + e * 0.25 + f as f32 * 1000.0;
+        }
+        sub floats(a: f32, b: f32, n: i32, o: &f32) {
+            *o = (a - b) * n as f32;
+        }
+        program {
+            let r: f32 = 0.0, s: f32 = 0.0;
+            call mix(1.0, 2, 3.0, 4, 8.0, 5, &r);
+            call floats(7.5, 2.5, 3, &s);
+            print r, \" \", s;
+        }",
+    );
+    assert_eq!(run.stdout, "5127.0 15.0");
+}
+
+#[test]
+fn stack_arguments_beyond_the_fourth() {
+    let run = common::run(
+        "sub digits(a: i32, b: i32, c: i32, d: i32, e: i32, f: i32, g: i32, o: &i32) {
+            *o = a * 1000000 + b * 100000 + c * 10000 + d * 1000 + e * 100 + f * 10 + g;
+        }
+        sub nested(a: i32, b: i32, c: i32, d: i32, e: i32, o: &i32) {
+            call digits(e, d, c, b, a, e, d, o);
+        }
+        program {
+            let r: i32 = 0, s: i32 = 0;
+            call digits(1, 2, 3, 4, 5, 6, 7, &r);
+            call nested(1, 2, 3, 4, 5, &s);
+            print r, \" \", s;
+        }",
+    );
+    assert_eq!(run.stdout, "1234567 5432154");
+}
+
+#[test]
+fn register_arguments_can_be_referenced() {
+    // Taking the address of an argument spills it into the home area
+    let run = common::run(
+        "sub bump(p: &i32) { *p = *p + 1; }
+        sub twice(a: i32, b: i32, o: &i32) {
+            call bump(&a);
+            call bump(&b);
+            call bump(&b);
+            *o = a * 10 + b;
+        }
+        program {
+            let r: i32 = 0;
+            call twice(1, 2, &r);
+            print r;
+        }",
+    );
+    assert_eq!(run.stdout, "24");
+}
+
+#[test]
+fn extern_subs_follow_o32() {
+    // The externs are implemented by hand in an asm block that's jumped over
+    let run = common::run(
+        "extern sub sum6(a: i32, b: i32, c: i32, d: i32, e: i32, o: &i32);
+        extern sub scale(x: f32, y: f32, n: i32, o: &f32);
+        program {
+            let r: i32 = 0, f: f32 = 0.0;
+            asm {
+                \"b calls_test_skip\"
+                \"sum6: add $t0, $a0, $a1\"
+                \"add $t0, $t0, $a2\"
+                \"add $t0, $t0, $a3\"
+                \"lw $t1, 16($sp)\"
+                \"add $t0, $t0, $t1\"
+                \"lw $t1, 20($sp)\"
+                \"sw $t0, 0($t1)\"
+                \"jr $ra\"
+                \"scale: mul.s $f0, $f12, $f14\"
+                \"mtc1 $a2, $f2\"
+                \"cvt.s.w $f2, $f2\"
+                \"mul.s $f0, $f0, $f2\"
+                \"s.s $f0, 0($a3)\"
+                \"jr $ra\"
+                \"calls_test_skip:\";
+            }
+            call sum6(1, 20, 300, 4000, 50000, &r);
+            call scale(1.5, 2.0, 3, &f);
+            print r, \" \", f;
+        }",
+    );
+    assert_eq!(run.stdout, "54321 9.0");
+}
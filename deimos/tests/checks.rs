@@ -0,0 +1,128 @@
+mod common;
+
+use deimos_codegen::{CodegenOptions, Machine};
+
+fn run_checked(src: &str) -> common::Run {
+    let options = CodegenOptions {
+        runtime_checks: true,
+        machine_code: Some(Machine::Mars),
+        ..Default::default()
+    };
+    common::run_with_options(src, "", &options)
+}
+
+#[test]
+fn index_past_the_end_fails() {
+    let run = run_checked(
+        "program {
+            let a: i32[4], i: i32 = 4;
+            a[3] = 1;
+            print \"before \";
+            a[i] = 2;
+            print \"after\";
+        }",
+    );
+    assert_eq!(run.exit_code, 1);
+    assert_eq!(
+        run.stdout,
+        "before Runtime error at 5:15: index out of bounds\n"
+    );
+}
+
+#[test]
+fn negative_index_fails() {
+    let run = run_checked(
+        "program {
+            let a: i32[4], i: i32 = -1, x: i32 = 0;
+            x = a[i];
+        }",
+    );
+    assert_eq!(run.exit_code, 1);
+    assert_eq!(run.stdout, "Runtime error at 3:19: index out of bounds\n");
+}
+
+#[test]
+fn every_dimension_is_checked() {
+    let src = |row: i32, col: i32| {
+        format!(
+            "program {{
+                let grid: i32[3][5], r: i32 = {row}, c: i32 = {col};
+                grid[r][c] = 7;
+                print grid[r][c];
+            }}"
+        )
+    };
+    let run = run_checked(&src(2, 4));
+    assert_eq!((run.exit_code, run.stdout.as_str()), (0, "7"));
+
+    // Column 5 would still be inside the array, in the next row
+    for (row, col, at) in [(1, 5, "3:25"), (3, 0, "3:22"), (0, -1, "3:25")] {
+        let run = run_checked(&src(row, col));
+        assert_eq!(run.exit_code, 1);
+        assert_eq!(
+            run.stdout,
+            format!("Runtime error at {at}: index out of bounds\n")
+        );
+    }
+}
+
+#[test]
+fn division_and_modulo_by_zero_fail() {
+    for op in ["/", "%"] {
+        let run = run_checked(&format!(
+            "program {{
+                let n: i32 = 7, d: i32 = 0, u: u32 = 7u;
+                n = n {op} d;
+            }}"
+        ));
+        assert_eq!(run.exit_code, 1, "{op}");
+        assert_eq!(run.stdout, "Runtime error at 3:23: division by zero\n");
+    }
+    let run = run_checked(
+        "program {
+            let u: u32 = 7u, d: u32 = 0u;
+            u = u % d;
+        }",
+    );
+    assert_eq!(run.exit_code, 1);
+    assert_eq!(run.stdout, "Runtime error at 3:19: division by zero\n");
+}
+
+#[test]
+fn null_dereference_fails() {
+    let run = run_checked(
+        "program {
+            let p: &i32, x: i32 = 0;
+            p = 0 as &i32;
+            x = *p;
+        }",
+    );
+    assert_eq!(run.exit_code, 1);
+    assert_eq!(
+        run.stdout,
+        "Runtime error at 4:17: null pointer dereference\n"
+    );
+
+    let run = run_checked(
+        "program {
+            let p: &i32;
+            p = 0 as &i32;
+            *p = 3;
+        }",
+    );
+    assert_eq!(run.exit_code, 1);
+    assert!(run.stdout.ends_with(": null pointer dereference\n"));
+}
+
+#[test]
+fn passing_checks_do_not_change_the_result() {
+    let run = run_checked(
+        "program {
+            let a: i32[3], p: &i32, n: i32 = 9, d: i32 = 2;
+            a[2] = n / d;
+            p = &a[2];
+            print *p, \" \", n % d;
+        }",
+    );
+    assert_eq!((run.exit_code, run.stdout.as_str()), (0, "4 1"));
+}
@@ -0,0 +1,396 @@
+//! Compiles programs together with the standard prelude and runs the
+//! machine code MARS would assemble for them on a small MIPS simulator.
+#![allow(dead_code)]
+
+use deimos_codegen::{CodegenOptions, CodegenOutput, Machine};
+use std::collections::HashMap;
+
+const LIB_SOURCE: &str = include_str!("../../src/lib.dei");
+const STRING_SOURCE: &str = include_str!("../../src/string.dei");
+
+/// Where MARS places the data segment, the stack and the heap
+const DATA_BASE: u32 = 0x1001_0000;
+const HEAP_BASE: u32 = 0x1004_0000;
+const STACK_TOP: u32 = 0x7fff_effc;
+const GLOBAL_POINTER: u32 = 0x1000_8000;
+
+const STEP_LIMIT: u64 = 50_000_000;
+
+/// Compiles a program after appending the prelude, like the CLI does
+pub fn compile(src: &str, options: &CodegenOptions) -> Result<CodegenOutput, String> {
+    let source = format!("{src}\n{LIB_SOURCE}{STRING_SOURCE}");
+    let tokens = deimos_parser::lex(&source).map_err(|e| e.to_string())?;
+    let ast = deimos_parser::parse(tokens).map_err(|e| e.to_string())?;
+    deimos_codegen::codegen(&ast, options).map_err(|e| e.to_string())
+}
+
+pub struct Run {
+    pub stdout: String,
+    pub exit_code: i32,
+    /// Lowest address the stack pointer reached
+    pub min_sp: u32,
+}
+
+/// Compiles and runs a program, feeding it `stdin`
+pub fn run_with_input(src: &str, stdin: &str) -> Run {
+    let options = CodegenOptions {
+        machine_code: Some(Machine::Mars),
+        ..Default::default()
+    };
+    run_with_options(src, stdin, &options)
+}
+
+pub fn run(src: &str) -> Run {
+    run_with_input(src, "")
+}
+
+pub fn run_with_options(src: &str, stdin: &str, options: &CodegenOptions) -> Run {
+    let output = compile(src, options).unwrap_or_else(|e| panic!("{e}"));
+    let image = output.image.expect("no machine code was requested");
+    let mut cpu = Cpu::new(image.text, &image.data, stdin);
+    let exit_code = cpu.run(image.entry);
+    Run {
+        stdout: String::from_utf8_lossy(&cpu.stdout).into_owned(),
+        exit_code,
+        min_sp: cpu.min_sp,
+    }
+}
+
+struct Cpu {
+    text: Vec<u32>,
+    pages: HashMap<u32, Box<[u8; 4096]>>,
+    r: [u32; 32],
+    f: [u32; 32],
+    hi: u32,
+    lo: u32,
+    cc: bool,
+    heap: u32,
+    stdin: Vec<u8>,
+    stdout: Vec<u8>,
+    min_sp: u32,
+}
+
+fn signed(x: u32) -> i32 {
+    x as i32
+}
+
+impl Cpu {
+    fn new(text: Vec<u32>, data: &[u8], stdin: &str) -> Cpu {
+        let mut cpu = Cpu {
+            text,
+            pages: HashMap::new(),
+            r: [0; 32],
+            f: [0; 32],
+            hi: 0,
+            lo: 0,
+            cc: false,
+            heap: HEAP_BASE,
+            stdin: stdin.as_bytes().to_vec(),
+            stdout: Vec::new(),
+            min_sp: STACK_TOP,
+        };
+        for (i, b) in data.iter().enumerate() {
+            cpu.store_byte(DATA_BASE + i as u32, *b);
+        }
+        cpu.r[28] = GLOBAL_POINTER;
+        cpu.r[29] = STACK_TOP;
+        cpu
+    }
+
+    fn load_byte(&self, addr: u32) -> u8 {
+        self.pages
+            .get(&(addr >> 12))
+            .map_or(0, |page| page[(addr & 0xfff) as usize])
+    }
+
+    fn store_byte(&mut self, addr: u32, value: u8) {
+        let page = self
+            .pages
+            .entry(addr >> 12)
+            .or_insert_with(|| Box::new([0; 4096]));
+        page[(addr & 0xfff) as usize] = value;
+    }
+
+    fn load(&self, addr: u32, size: u32) -> u32 {
+        assert_eq!(addr % size, 0, "unaligned load from {addr:#x}");
+        (0..size).fold(0, |word, i| {
+            word | (self.load_byte(addr + i) as u32) << (8 * i)
+        })
+    }
+
+    fn store(&mut self, addr: u32, size: u32, value: u32) {
+        assert_eq!(addr % size, 0, "unaligned store to {addr:#x}");
+        for i in 0..size {
+            self.store_byte(addr + i, (value >> (8 * i)) as u8);
+        }
+    }
+
+    fn float(&self, reg: usize) -> f32 {
+        f32::from_bits(self.f[reg])
+    }
+
+    fn set_float(&mut self, reg: usize, value: f32) {
+        self.f[reg] = value.to_bits();
+    }
+
+    fn read_line(&mut self) -> String {
+        let end = self
+            .stdin
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(self.stdin.len(), |i| i + 1);
+        let line: Vec<u8> = self.stdin.drain(..end).collect();
+        String::from_utf8_lossy(&line).trim_end().to_string()
+    }
+
+    /// Runs from `entry` until the program exits, returning its exit code
+    fn run(&mut self, entry: u32) -> i32 {
+        let mut pc = entry;
+        for _ in 0..STEP_LIMIT {
+            let index = (pc.wrapping_sub(0x0040_0000) / 4) as usize;
+            let Some(&word) = self.text.get(index) else {
+                return 0;
+            };
+            match self.step(pc, word) {
+                Step::Next(next) => pc = next,
+                Step::Exit(code) => return code,
+            }
+            self.r[0] = 0;
+            self.min_sp = self.min_sp.min(self.r[29]);
+        }
+        panic!("program didn't finish within {STEP_LIMIT} instructions");
+    }
+
+    fn step(&mut self, pc: u32, w: u32) -> Step {
+        let op = w >> 26;
+        let rs = ((w >> 21) & 31) as usize;
+        let rt = ((w >> 16) & 31) as usize;
+        let rd = ((w >> 11) & 31) as usize;
+        let shamt = (w >> 6) & 31;
+        let funct = w & 63;
+        let imm = w & 0xffff;
+        let simm = imm as u16 as i16 as i32 as u32;
+        let next = pc.wrapping_add(4);
+        let branch = next.wrapping_add(simm << 2);
+        let (a, b) = (self.r[rs], self.r[rt]);
+
+        match op {
+            0 => {
+                let value = match funct {
+                    0x00 => b << shamt,
+                    0x02 => b >> shamt,
+                    0x03 => (signed(b) >> shamt) as u32,
+                    0x04 => b << (a & 31),
+                    0x06 => b >> (a & 31),
+                    0x07 => (signed(b) >> (a & 31)) as u32,
+                    0x08 => return Step::Next(a),
+                    0x09 => {
+                        self.r[rd] = next;
+                        return Step::Next(a);
+                    }
+                    0x0c => return self.syscall(next),
+                    0x0d => panic!("break at {pc:#x}"),
+                    0x10 => self.hi,
+                    0x11 => {
+                        self.hi = a;
+                        return Step::Next(next);
+                    }
+                    0x12 => self.lo,
+                    0x13 => {
+                        self.lo = a;
+                        return Step::Next(next);
+                    }
+                    0x18 | 0x19 => {
+                        let product = if funct == 0x18 {
+                            (signed(a) as i64 * signed(b) as i64) as u64
+                        } else {
+                            a as u64 * b as u64
+                        };
+                        self.lo = product as u32;
+                        self.hi = (product >> 32) as u32;
+                        return Step::Next(next);
+                    }
+                    0x1a | 0x1b => {
+                        // MARS leaves hi and lo unchanged on division by zero
+                        if b != 0 {
+                            if funct == 0x1a {
+                                self.lo = signed(a).wrapping_div(signed(b)) as u32;
+                                self.hi = signed(a).wrapping_rem(signed(b)) as u32;
+                            } else {
+                                self.lo = a / b;
+                                self.hi = a % b;
+                            }
+                        }
+                        return Step::Next(next);
+                    }
+                    0x20 | 0x21 => a.wrapping_add(b),
+                    0x22 | 0x23 => a.wrapping_sub(b),
+                    0x24 => a & b,
+                    0x25 => a | b,
+                    0x26 => a ^ b,
+                    0x27 => !(a | b),
+                    0x2a => (signed(a) < signed(b)) as u32,
+                    0x2b => (a < b) as u32,
+                    _ => panic!("unknown instruction {w:#010x} at {pc:#x}"),
+                };
+                self.r[rd] = value;
+            }
+            0x1c if funct == 0x02 => self.r[rd] = a.wrapping_mul(b),
+            0x01 => {
+                let taken = match rt {
+                    0 => signed(a) < 0,
+                    1 => signed(a) >= 0,
+                    _ => panic!("unknown instruction {w:#010x} at {pc:#x}"),
+                };
+                if taken {
+                    return Step::Next(branch);
+                }
+            }
+            0x02 | 0x03 => {
+                if op == 0x03 {
+                    self.r[31] = next;
+                }
+                return Step::Next((next & 0xf000_0000) | ((w & 0x03ff_ffff) << 2));
+            }
+            0x04..=0x07 => {
+                let taken = match op {
+                    0x04 => a == b,
+                    0x05 => a != b,
+                    0x06 => signed(a) <= 0,
+                    _ => signed(a) > 0,
+                };
+                if taken {
+                    return Step::Next(branch);
+                }
+            }
+            0x08..=0x0f => {
+                self.r[rt] = match op {
+                    0x08 | 0x09 => a.wrapping_add(simm),
+                    0x0a => (signed(a) < signed(simm)) as u32,
+                    0x0b => (a < simm) as u32,
+                    0x0c => a & imm,
+                    0x0d => a | imm,
+                    0x0e => a ^ imm,
+                    _ => imm << 16,
+                };
+            }
+            0x20 | 0x21 | 0x23 | 0x24 | 0x25 | 0x31 => {
+                let addr = a.wrapping_add(simm);
+                let value = match op {
+                    0x20 => self.load(addr, 1) as u8 as i8 as i32 as u32,
+                    0x21 => self.load(addr, 2) as u16 as i16 as i32 as u32,
+                    0x24 => self.load(addr, 1),
+                    0x25 => self.load(addr, 2),
+                    _ => self.load(addr, 4),
+                };
+                if op == 0x31 {
+                    self.f[rt] = value;
+                } else {
+                    self.r[rt] = value;
+                }
+            }
+            0x28 => self.store(a.wrapping_add(simm), 1, b),
+            0x29 => self.store(a.wrapping_add(simm), 2, b),
+            0x2b => self.store(a.wrapping_add(simm), 4, b),
+            0x39 => self.store(a.wrapping_add(simm), 4, self.f[rt]),
+            0x11 => return self.cop1(pc, w, branch),
+            _ => panic!("unknown instruction {w:#010x} at {pc:#x}"),
+        }
+        Step::Next(next)
+    }
+
+    fn cop1(&mut self, pc: u32, w: u32, branch: u32) -> Step {
+        let fmt = (w >> 21) & 31;
+        let ft = ((w >> 16) & 31) as usize;
+        let fs = ((w >> 11) & 31) as usize;
+        let fd = ((w >> 6) & 31) as usize;
+        let funct = w & 63;
+        match (fmt, funct) {
+            (0x00, _) => self.r[ft] = self.f[fs],
+            (0x04, _) => self.f[fs] = self.r[ft],
+            (0x08, _) => {
+                if (ft & 1 == 1) == self.cc {
+                    return Step::Next(branch);
+                }
+            }
+            (0x14, 0x20) => self.set_float(fd, signed(self.f[fs]) as f32),
+            (0x10, _) => {
+                let (x, y) = (self.float(fs), self.float(ft));
+                match funct {
+                    0x00 => self.set_float(fd, x + y),
+                    0x01 => self.set_float(fd, x - y),
+                    0x02 => self.set_float(fd, x * y),
+                    0x03 => self.set_float(fd, x / y),
+                    0x04 => self.set_float(fd, x.sqrt()),
+                    0x05 => self.set_float(fd, x.abs()),
+                    0x06 => self.f[fd] = self.f[fs],
+                    0x07 => self.set_float(fd, -x),
+                    // Out of range conversions give the largest int, like MARS
+                    0x24 if x.is_nan() || x.abs() >= 2147483648.0 => self.f[fd] = i32::MAX as u32,
+                    0x24 => self.f[fd] = x as i32 as u32,
+                    0x32 => self.cc = x == y,
+                    0x3c => self.cc = x < y,
+                    0x3e => self.cc = x <= y,
+                    _ => panic!("unknown instruction {w:#010x} at {pc:#x}"),
+                }
+            }
+            _ => panic!("unknown instruction {w:#010x} at {pc:#x}"),
+        }
+        Step::Next(pc.wrapping_add(4))
+    }
+
+    fn syscall(&mut self, next: u32) -> Step {
+        let (a0, a1, a2) = (self.r[4], self.r[5], self.r[6]);
+        match self.r[2] {
+            1 => self.stdout.extend(signed(a0).to_string().bytes()),
+            2 => self.stdout.extend(format!("{:?}", self.float(12)).bytes()),
+            4 => {
+                let mut addr = a0;
+                while self.load_byte(addr) != 0 {
+                    self.stdout.push(self.load_byte(addr));
+                    addr += 1;
+                }
+            }
+            5 => self.r[2] = self.read_line().parse::<i32>().unwrap_or(0) as u32,
+            6 => {
+                let value = self.read_line().parse().unwrap_or(0.0);
+                self.set_float(0, value);
+            }
+            8 => {
+                let mut line = self.read_line().into_bytes();
+                line.push(b'\n');
+                line.truncate(a1.saturating_sub(1) as usize);
+                line.push(0);
+                for (i, b) in line.into_iter().enumerate() {
+                    self.store_byte(a0 + i as u32, b);
+                }
+            }
+            9 => {
+                self.r[2] = self.heap;
+                self.heap = (self.heap + a0 + 3) & !3;
+            }
+            10 => return Step::Exit(0),
+            11 => self.stdout.push(a0 as u8),
+            // Only the standard streams are available
+            13 => self.r[2] = u32::MAX,
+            15 if a0 == 1 || a0 == 2 => {
+                for i in 0..a2 {
+                    self.stdout.push(self.load_byte(a1 + i));
+                }
+                self.r[2] = a2;
+            }
+            14 | 15 => self.r[2] = u32::MAX,
+            16 | 31 | 32 | 33 => {}
+            17 => return Step::Exit(signed(a0)),
+            34 => self.stdout.extend(format!("{a0:#010x}").bytes()),
+            36 => self.stdout.extend(a0.to_string().bytes()),
+            code => panic!("unsupported syscall {code}"),
+        }
+        Step::Next(next)
+    }
+}
+
+enum Step {
+    Next(u32),
+    Exit(i32),
+}
@@ -0,0 +1,93 @@
+//! `-g` has to annotate each statement with its source line and map the
+//! lines of the generated assembly back to the statements they came from
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const SOURCE: &str = "program {
+    let x: i32 = 0;
+
+    x = 3;
+    asm {
+        \"addi {x}, {x}, 1\"
+        \"addi {x}, {x}, 2\";
+    }
+    print x, \"\\n\";
+}
+";
+
+/// Compiles `SOURCE` with debug info and returns the lines of the assembly
+/// and the source map
+fn compile(dir: &Path) -> (Vec<String>, String) {
+    let source = dir.join("debug.dei");
+    let out = dir.join("debug.asm");
+    fs::write(&source, SOURCE).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_deimos"))
+        .arg(&source)
+        .arg("-g")
+        .arg("-o")
+        .arg(&out)
+        .status()
+        .expect("Failed to run the compiler");
+    assert!(status.success(), "Failed to compile");
+
+    let asm = fs::read_to_string(&out).unwrap();
+    let map = fs::read_to_string(dir.join("debug.asm.map")).unwrap();
+    (asm.lines().map(str::to_owned).collect(), map)
+}
+
+/// Line of the assembly (counting from 1) that ends with `suffix`
+fn find_line(asm: &[String], suffix: &str) -> usize {
+    let index = asm.iter().position(|line| line.ends_with(suffix));
+    index.unwrap_or_else(|| panic!("No line ends with {:?}", suffix)) + 1
+}
+
+fn mapping(line: usize, row: usize, col: usize) -> String {
+    format!(
+        "{{\"line\": {}, \"file\": 0, \"row\": {}, \"col\": {}}}",
+        line, row, col
+    )
+}
+
+#[test]
+fn statements_map_to_their_source_lines() {
+    let dir = std::env::temp_dir().join(format!("deimos_debug_info_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (asm, map) = compile(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Each statement is preceded by a comment giving its location and source
+    let assign = find_line(&asm, "debug.dei:4:5  x = 3;");
+    let block = find_line(&asm, "debug.dei:5:5  asm {");
+    let print = find_line(&asm, "debug.dei:9:5  print x, \"\\n\";");
+    assert!(asm[assign - 1].starts_with("\t# "));
+    assert!(assign < block && block < print);
+    assert_eq!(asm[assign].trim(), "li $t0, 3");
+
+    assert!(map.contains("\"files\": [\""));
+    assert!(map.contains("debug.dei\", \"lib.dei\""));
+    // The comment and every instruction up to the next statement come from
+    // the statement
+    for line in assign..block {
+        assert!(map.contains(&mapping(line, 4, 5)), "line {}", line);
+    }
+    let first_addi = find_line(&asm, ", 1");
+    let second_addi = find_line(&asm, ", 2");
+    assert!(block < first_addi && first_addi < second_addi && second_addi < print);
+    for line in block..print {
+        assert!(map.contains(&mapping(line, 5, 5)), "line {}", line);
+    }
+    assert!(map.contains(&mapping(print, 9, 5)));
+    assert!(map.contains(&mapping(print + 1, 9, 5)));
+
+    // Labels and the stack setup before the first statement aren't mapped
+    let main = find_line(&asm, "main:");
+    for line in main..assign {
+        assert!(
+            !map.contains(&format!("{{\"line\": {},", line)),
+            "line {}",
+            line
+        );
+    }
+}
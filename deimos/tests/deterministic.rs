@@ -0,0 +1,48 @@
+//! Compiling the same program twice has to give byte-for-byte identical
+//! output, so generated assembly can be diffed and snapshotted
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Compiles a source file with debug info and returns the assembly
+/// followed by the source map
+fn compile(source: &Path, out: &Path) -> Vec<u8> {
+    let status = Command::new(env!("CARGO_BIN_EXE_deimos"))
+        .arg(source)
+        .arg("-g")
+        .arg("-o")
+        .arg(out)
+        .status()
+        .expect("Failed to run the compiler");
+    assert!(status.success(), "{} failed to compile", source.display());
+
+    let mut output = fs::read(out).unwrap();
+    output.extend(fs::read(out.with_extension("asm.map")).unwrap());
+    output
+}
+
+#[test]
+fn samples_compile_deterministically() {
+    let samples_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../samples");
+    let mut samples: Vec<PathBuf> = fs::read_dir(samples_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dei"))
+        .collect();
+    samples.sort();
+    assert!(!samples.is_empty(), "No samples found");
+
+    let out_dir = std::env::temp_dir().join(format!("deimos_deterministic_{}", std::process::id()));
+    fs::create_dir_all(&out_dir).unwrap();
+    for sample in &samples {
+        let first = compile(sample, &out_dir.join("first.asm"));
+        let second = compile(sample, &out_dir.join("second.asm"));
+        assert!(
+            first == second,
+            "{} compiled differently between runs",
+            sample.display()
+        );
+    }
+    fs::remove_dir_all(&out_dir).unwrap();
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use deimos_codegen::CodegenOptions;
+
+const ENUMS: &str = "
+enum State: u32 { IDLE = 1, RUNNING, DONE = 10, FAILED }
+enum Delta: i32 { BACK = -2, STAY, AHEAD }
+";
+
+fn compile_err(body: &str) -> String {
+    let src = format!("{ENUMS} program {{ {body} }}");
+    common::compile(&src, &CodegenOptions::default())
+        .err()
+        .unwrap_or_else(|| panic!("{body} compiled"))
+}
+
+#[test]
+fn variants_count_up_from_the_last_value() {
+    let run = common::run(&format!(
+        "{ENUMS}
+        program {{
+            print IDLE as u32, \" \", RUNNING as u32, \" \", DONE as u32, \" \", FAILED as u32, \" \";
+            print BACK as i32, \" \", STAY as i32, \" \", AHEAD as i32;
+        }}"
+    ));
+    assert_eq!(run.stdout, "1 2 10 11 -2 -1 0");
+}
+
+#[test]
+fn enums_compare_and_switch_by_variant() {
+    let run = common::run(&format!(
+        "{ENUMS}
+        sub next(s: State, o: &State) {{
+            switch (s) {{
+                case IDLE: {{ *o = RUNNING; }}
+                case RUNNING: {{ *o = DONE; }}
+                default: {{ *o = FAILED; }}
+            }}
+        }}
+        program {{
+            let s: State = IDLE, steps: i32 = 0;
+            while (s != DONE) {{
+                call next(s, &s);
+                steps = steps + 1;
+            }}
+            print steps, \" \", s == DONE, \" \", sizeof(State);
+        }}"
+    ));
+    assert_eq!(run.stdout, "2 true 4");
+}
+
+#[test]
+fn enums_are_distinct_types() {
+    for body in [
+        "let n: u32 = 0; n = IDLE;",
+        "let s: State = IDLE; s = 1u;",
+        "let s: State = IDLE; s = STAY;",
+        "let b: bool; b = IDLE == 1u;",
+        "let b: bool; b = IDLE == STAY;",
+        "let n: u32; n = IDLE + RUNNING;",
+    ] {
+        let err = compile_err(body);
+        assert!(
+            err.contains("ismatch") || err.starts_with("Invalid"),
+            "{body}: {err}"
+        );
+    }
+}
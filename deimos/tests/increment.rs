@@ -0,0 +1,36 @@
+mod common;
+
+#[test]
+fn double_minus_subtracts_a_negation() {
+    let run = common::run(
+        "program {
+            let a: i32 = 10, b: i32 = 3, x: i32 = 0;
+            print a--b, \" \", a---b, \" \";
+            x++;
+            ++x;
+            x--;
+            --x;
+            --x;
+            print x;
+        }",
+    );
+    assert_eq!(run.stdout, "13 7 -1");
+}
+
+#[test]
+fn bitwise_compound_assignments() {
+    let run = common::run(
+        "program {
+            let x: i32 = 5, y: i32 = 12, n: i32 = -64, u: u32 = 0x80000000u, f: bool = true;
+            x &= 6;
+            y |= 3;
+            y ^= 5;
+            n >>= 4;
+            u >>= 31;
+            x <<= 3;
+            f ^= true;
+            print x, \" \", y, \" \", n, \" \", u, \" \", f, \" \", 3 xor 6, \" \", 1 << 2 + 1;
+        }",
+    );
+    assert_eq!(run.stdout, "32 10 -4 1 false 5 8");
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use deimos_codegen::CodegenOptions;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Compiles a source file with the CLI and returns the assembly
+fn compile_file(name: &str, src: &str, args: &[&str]) -> String {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let source = dir.join(format!("{name}.dei"));
+    let out = dir.join(format!("{name}.asm"));
+    std::fs::write(&source, src).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_deimos"))
+        .arg(&source)
+        .args(args)
+        .arg("-o")
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    std::fs::read_to_string(out).unwrap()
+}
+
+fn defines(asm: &str, label: &str) -> bool {
+    asm.lines()
+        .any(|line| line.starts_with(&format!("{label}:")))
+}
+
+#[test]
+fn library_units_link_against_the_main_units_prelude() {
+    let lib = compile_file(
+        "library_unit",
+        "pub sub make_buf(n: u32, o: &&u8) { call malloc(n, o); }",
+        &["-lib"],
+    );
+    assert!(lib.contains("jal malloc"));
+    for label in ["malloc", "free", "strlen", "heap_free_list"] {
+        assert!(!defines(&lib, label), "library unit defines {label}");
+    }
+
+    let main = compile_file(
+        "main_unit",
+        "extern sub make_buf(n: u32, o: &&u8); program {}",
+        &["-export-prelude"],
+    );
+    for label in ["malloc", "free", "strlen", "heap_free_list"] {
+        assert!(defines(&main, label), "main unit doesn't define {label}");
+        assert!(main.contains(&format!(".globl {label}\n")));
+    }
+}
+
+#[test]
+fn prelude_is_only_exported_on_request() {
+    let src = "pub sub helper() {} program { call helper(); }";
+    let asm = compile_file("unexported_prelude", src, &[]);
+    assert!(asm.contains(".globl helper\n"));
+    for label in ["malloc", "free", "strlen", "heap_free_list"] {
+        assert!(defines(&asm, label), "main unit doesn't define {label}");
+        assert!(
+            !asm.contains(&format!(".globl {label}\n")),
+            "{label} is exported"
+        );
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let output = Command::new(env!("CARGO_BIN_EXE_deimos"))
+        .arg(dir.join("unexported_prelude.dei"))
+        .arg("-stack-report")
+        .arg("-o")
+        .arg(dir.join("unexported_prelude.asm"))
+        .output()
+        .unwrap();
+    let report = String::from_utf8(output.stdout).unwrap();
+    let entries: Vec<_> = report
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.trim().split(':').next())
+        .collect();
+    assert_eq!(entries, ["program", "helper"], "{report}");
+}
+
+#[test]
+fn public_names_cant_take_generated_labels() {
+    let options = CodegenOptions {
+        library: true,
+        ..Default::default()
+    };
+    for src in [
+        "pub sub main() {}",
+        "pub sub internal_panic() {}",
+        "pub static INTERNAL_FMT_BUF: u32 = 0u;",
+        "pub static ARGC_GLOBAL: u32 = 0u;",
+        "pub static USER_STATIC_count: u32 = 0u;",
+        "pub sub WHILE_BLOCK_0() {}",
+        "pub sub done() {} pub static done_END: u32 = 0u;",
+        "pub sub add() {}",
+        "pub static lw: u32 = 0u;",
+        "extern sub nop(); pub sub f() {}",
+        "extern sub internal_fmt_write(); pub sub f() {}",
+    ] {
+        let err = common::compile(src, &options).err();
+        assert!(
+            err.as_deref().is_some_and(|e| e.contains("reserved")),
+            "{src} compiled: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn readable_labels_are_checked_too() {
+    let options = CodegenOptions {
+        readable_labels: true,
+        ..Default::default()
+    };
+    for src in [
+        "pub sub main() {} program {}",
+        "pub sub beq() {} program {}",
+        "extern sub ARGV_GLOBAL(); program {}",
+    ] {
+        let err = common::compile(src, &options).err();
+        assert!(
+            err.as_deref().is_some_and(|e| e.contains("reserved")),
+            "{src} compiled: {err:?}"
+        );
+    }
+
+    // Private labels step around public and extern ones
+    let asm = common::compile(
+        "extern sub sub_a(); pub sub sub_b() {} sub a() {} sub b() {}
+        program { call a(); call b(); }",
+        &options,
+    )
+    .unwrap()
+    .asm;
+    assert!(defines(&asm, "sub_a_1"));
+    assert!(defines(&asm, "sub_b_1"));
+    assert!(asm.contains("jal sub_a_1") && asm.contains("jal sub_b_1"));
+}
+
+#[test]
+fn private_labels_avoid_sub_end_labels() {
+    let options = CodegenOptions::default();
+    let asm = common::compile(
+        "sub a() {} sub a_END() {} program { call a_END(); }",
+        &options,
+    )
+    .unwrap()
+    .asm;
+    assert!(defines(&asm, "USER_SUB_a_END"));
+    assert!(defines(&asm, "USER_SUB_a_END_1"));
+    assert!(asm.contains("jal USER_SUB_a_END_1"));
+}
@@ -0,0 +1,12 @@
+mod common;
+
+#[test]
+fn logical_not_is_true_for_zero() {
+    let run = common::run(
+        "program {
+            let zero: i32 = 0, five: i32 = -5, fzero: f32 = 0.0, half: f32 = 0.5, no: bool = false;
+            print !zero, \" \", !five, \" \", !fzero, \" \", !half, \" \", !no, \" \", !!five;
+        }",
+    );
+    assert_eq!(run.stdout, "true false true false true true");
+}
@@ -0,0 +1,66 @@
+mod common;
+
+#[test]
+fn signed_loads_sign_extend() {
+    let run = common::run(
+        "static si: i16 = -300;
+         program {
+            let b: i8 = -1 as i8, h: i16 = -2 as i16, wide: i32 = 0;
+            wide = b as i32;
+            print wide, \" \", h as i32, \" \", si as i32, \" \", b as i32 + 1;
+        }",
+    );
+    assert_eq!(run.stdout, "-1 -2 -300 0");
+}
+
+#[test]
+fn unsigned_loads_zero_extend() {
+    let run = common::run(
+        "static su: u16 = 65535 as u16;
+         program {
+            let c: u8 = 255 as u8, w: u16 = 65535 as u16;
+            print c as i32, \" \", w as i32, \" \", su as u32;
+        }",
+    );
+    assert_eq!(run.stdout, "255 65535 65535");
+}
+
+#[test]
+fn narrowing_casts_truncate() {
+    let run = common::run(
+        "program {
+            let big: i32 = 40000, neg: i32 = -129;
+            print big as i16 as i32, \" \", big as u16 as i32, \" \", neg as i8 as i32, \" \", neg as u8 as i32;
+        }",
+    );
+    assert_eq!(run.stdout, "-25536 40000 127 127");
+}
+
+#[test]
+fn stores_wrap_around() {
+    let run = common::run(
+        "program {
+            let w: u16 = 65535 as u16, b: i8 = 127 as i8;
+            w = w + 1 as u16;
+            b = b + 1 as i8;
+            print w as i32, \" \", b as i32;
+        }",
+    );
+    assert_eq!(run.stdout, "0 -128");
+}
+
+#[test]
+fn array_elements_keep_their_width() {
+    let run = common::run(
+        "program {
+            let bytes: i8[4], halves: u16[3];
+            bytes[0] = 1 as i8;
+            bytes[1] = -2 as i8;
+            bytes[2] = 3 as i8;
+            halves[0] = 65535 as u16;
+            halves[1] = 2 as u16;
+            print bytes[1] as i32, \" \", bytes[2] as i32, \" \", halves[0] as i32, \" \", halves[1] as i32;
+        }",
+    );
+    assert_eq!(run.stdout, "-2 3 65535 2");
+}
@@ -0,0 +1,35 @@
+mod common;
+
+#[test]
+fn syscall_wrappers_take_enums_or_integers() {
+    let run = common::run(
+        "program {
+            let fd: u32 = 0, other: u32 = 0, instrument: u32 = 3;
+            call open_file(\"missing\", OPEN_READ, 0u, &fd);
+            call open_file(\"missing\", 0u, 0u, &other);
+            call mars_midi_async(60u, 10u, instrument, 100u);
+            call mars_midi_sync(60u, 10u, MIDI_ORGAN, 100u);
+            print fd as i32, \" \", other as i32;
+        }",
+    );
+    assert_eq!(run.stdout, "-1 -1");
+}
+
+#[test]
+fn enum_parameters_reject_other_types() {
+    let options = Default::default();
+    let err = common::compile(
+        "program { let fd: u32; call open_file(\"f\", 0, 0u, &fd); }",
+        &options,
+    )
+    .err()
+    .unwrap();
+    assert!(err.starts_with("Invalid argument"), "{err}");
+    let err = common::compile(
+        "program { let fd: u32; call open_file(\"f\", MIDI_PIANO, 0u, &fd); }",
+        &options,
+    )
+    .err()
+    .unwrap();
+    assert!(err.starts_with("Invalid argument"), "{err}");
+}
@@ -0,0 +1,43 @@
+mod common;
+
+#[test]
+fn fprintf_writes_through_write_file() {
+    let output = common::compile(
+        "program { fprintf(1, \"%d apples\\n\", 3); }",
+        &Default::default(),
+    )
+    .unwrap();
+    let syscalls = output
+        .asm
+        .lines()
+        .filter(|line| line.trim() == "li $v0, 15")
+        .count();
+    assert_eq!(syscalls, 1, "only write_file should write to files");
+
+    let run = common::run("program { fprintf(1, \"%d apples, %s\\n\", 3, \"pears\"); }");
+    assert_eq!(run.stdout, "3 apples, pears\n");
+}
+
+#[test]
+fn float_fractions_are_rounded() {
+    let run =
+        common::run("program { printf(\"%f|%.2f|%.1f|%6.3f\", 1.5, -2.125, 8388607.5, 0.25); }");
+    assert_eq!(run.stdout, "1.500000|-2.13|8388607.5| 0.250");
+}
+
+#[test]
+fn floats_past_i32_print_every_digit() {
+    let run = common::run(
+        "program {
+            printf(\"%.1f|%14.0f|%f\\n\", 3000000000.0, -4294967296.0, 16777216.0);
+            printf(\"%.0f\\n\", 100000000000000000000.0);
+            printf(\"%.0f\\n\", 340282346638528859811704183484516925440.0);
+        }",
+    );
+    assert_eq!(
+        run.stdout,
+        "3000000000.0|   -4294967296|16777216.000000\n\
+         100000002004087734272\n\
+         340282346638528859811704183484516925440\n"
+    );
+}
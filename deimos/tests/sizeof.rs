@@ -0,0 +1,29 @@
+mod common;
+
+#[test]
+fn folded_and_generated_sizes_agree() {
+    let run = common::run(
+        "program {
+            let grid: u16[2][3], row: u8[sizeof(grid[1])], p: &u16;
+            print sizeof(grid); print \" \";
+            print sizeof(grid[1]); print \" \";
+            print len(row); print \" \";
+            print sizeof(grid[1][2]); print \" \";
+            print sizeof(&grid[1]); print \" \";
+            print sizeof(-p[0] as i32 < 3 ? 1.5 : 2.0);
+        }",
+    );
+    assert_eq!(run.stdout, "12 6 6 2 4 4");
+}
+
+#[test]
+fn sizeof_checks_its_operand() {
+    for src in [
+        "program { let p: &u16; print sizeof(-p); }",
+        "program { let a: u16[4]; print sizeof(a + 1.5); }",
+        "program { print sizeof(true ? 1 : 2u); }",
+    ] {
+        let result = common::compile(src, &Default::default());
+        assert!(result.is_err(), "{src} should not compile");
+    }
+}
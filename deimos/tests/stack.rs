@@ -0,0 +1,60 @@
+use deimos_codegen::CodegenOptions;
+
+mod common;
+
+/// Stack the simulated program actually used
+const STACK_TOP: u32 = 0x7fff_effc;
+
+fn program_depth(src: &str) -> u32 {
+    let options = CodegenOptions {
+        stack_report: true,
+        ..Default::default()
+    };
+    let output = common::compile(src, &options).unwrap();
+    let report = output.stack_report.unwrap();
+    let program = report.entries.iter().find(|e| e.name == "program");
+    program.unwrap().depth
+}
+
+#[test]
+fn printf_counts_the_helper_frames() {
+    let src = "sub show(n: i32) { printf(\"%5d\\n\", n); }
+        program { call show(2); }";
+    // Argument area for show (16), show's frame (8), the padding routine
+    // (8), the write routine (40) and write_file's frame (8)
+    assert_eq!(program_depth(src), 80);
+    let run = common::run(src);
+    assert_eq!(run.stdout, "    2\n");
+    assert_eq!(STACK_TOP - run.min_sp, 80);
+}
+
+#[test]
+fn clobbered_registers_are_counted() {
+    let src = "sub close(fd: u32) {
+            syscall (16) {
+                in: ($a0: fd);
+                clobber: ($a1, $t0, $t1);
+            }
+        }
+        program { call close(3u); }";
+    // Argument area (16), close's frame (8) and three saved clobbers (16)
+    assert_eq!(program_depth(src), 40);
+    assert_eq!(STACK_TOP - common::run(src).min_sp, 40);
+}
+
+#[test]
+fn spilled_temps_are_counted() {
+    let src = "sub deep(a: i32) {
+            let x: i32;
+            x = a + (a + (a + (a + (a + (a + (a + (a + (a + (a + a)))))))));
+            print x;
+        }
+        program { call deep(1); }";
+    // Argument area (16), deep's frame (8) and the three temps that
+    // don't fit in $t0-$t7 (12). Spills are stored below $sp without
+    // moving it, so the simulator only sees the first two.
+    assert_eq!(program_depth(src), 36);
+    let run = common::run(src);
+    assert_eq!(run.stdout, "11");
+    assert_eq!(STACK_TOP - run.min_sp, 24);
+}
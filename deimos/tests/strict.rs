@@ -0,0 +1,44 @@
+use deimos_codegen::CodegenOptions;
+
+fn compile(src: &str, strict: bool) -> Result<String, String> {
+    let tokens = deimos_parser::lex(src).unwrap();
+    let ast = deimos_parser::parse(tokens).unwrap();
+    let options = CodegenOptions {
+        strict_conditions: strict,
+        ..Default::default()
+    };
+    deimos_codegen::codegen(&ast, &options)
+        .map(|out| out.asm)
+        .map_err(|e| e.to_string())
+}
+
+const INT_CONDITIONS: [&str; 3] = [
+    "program { let n: i32 = 3; while (n) { n = n - 1; } }",
+    "program { let n: i32 = 3; if (n) { n = 0; } }",
+    "program { let n: i32 = 3; if (n == 1) { n = 0; } elif (n - 3) { n = 1; } }",
+];
+
+#[test]
+fn strict_mode_rejects_int_conditions() {
+    for src in INT_CONDITIONS {
+        let err = compile(src, true).unwrap_err();
+        assert!(err.contains("must be bool in strict mode"), "{src}: {err}");
+    }
+}
+
+#[test]
+fn int_conditions_are_accepted_by_default() {
+    for src in INT_CONDITIONS {
+        compile(src, false).unwrap_or_else(|e| panic!("{src}: {e}"));
+    }
+}
+
+#[test]
+fn strict_mode_accepts_bool_conditions() {
+    let src = "program {
+        let n: i32 = 3, done: bool = false;
+        while (!done) { n = n - 1; done = n == 0; }
+        if (n < 1 and true) { n = 5; } elif (done) { n = 6; }
+    }";
+    compile(src, true).unwrap();
+}
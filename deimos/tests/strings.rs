@@ -0,0 +1,8 @@
+mod common;
+
+#[test]
+fn strings_sample_passes() {
+    let run = common::run(include_str!("../../samples/strings.dei"));
+    assert_eq!(run.stdout, "All string tests passed\n");
+    assert_eq!(run.exit_code, 0);
+}
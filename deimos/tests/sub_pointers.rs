@@ -0,0 +1,31 @@
+mod common;
+
+#[test]
+fn calls_go_through_indexed_sub_pointers() {
+    let run = common::run(
+        "sub hello(n: i32) { print \"hello \", n, \"\\n\"; }
+        sub bye(n: i32) { print \"bye \", n, \"\\n\"; }
+        program {
+            let table: &sub(i32)[2], f: &sub(i32), i: i32;
+            table[0] = &hello;
+            table[1] = &bye;
+            f = &bye;
+            call table[0](1);
+            call table[1](2);
+            call f(3);
+        }",
+    );
+    assert_eq!(run.stdout, "hello 1\nbye 2\nbye 3\n");
+}
+
+#[test]
+fn only_sub_pointers_can_be_called() {
+    for src in [
+        "program { let i: i32; call i(1); }",
+        "sub f(n: i32) {} program { let table: &sub(i32)[2]; call table(1); }",
+        "sub f(n: i32) {} program { let p: &sub(i32); call p[0](1); }",
+    ] {
+        let result = common::compile(src, &Default::default());
+        assert!(result.is_err(), "{src} should not compile");
+    }
+}
@@ -0,0 +1,21 @@
+mod common;
+
+#[test]
+fn case_labels_fold_constant_expressions() {
+    let run = common::run(
+        "const BASE: i32 = 4;
+        program {
+            let i: i32 = 0;
+            while (i < 12) {
+                switch (i) {
+                    case BASE: { print \"a\"; }
+                    case BASE + 1, BASE * 2: { print \"b\"; }
+                    case -(1 - 3) * 5, sizeof(i32) as i32 - 1: { print \"c\"; }
+                    default: { print \".\"; }
+                }
+                i += 1;
+            }
+        }",
+    );
+    assert_eq!(run.stdout, "...cab..b.c.");
+}
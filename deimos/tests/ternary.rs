@@ -0,0 +1,43 @@
+mod common;
+
+use deimos_codegen::CodegenOptions;
+
+#[test]
+fn conditionals_pick_int_and_float_arms() {
+    let run = common::run(
+        "sub clamp(x: i32, lo: i32, hi: i32, res: &i32) {
+            *res = x < lo ? lo : x > hi ? hi : x;
+        }
+        program {
+            let a: i32 = 0, b: i32 = 0, c: i32 = 0, half: f32 = 0.5, f: f32 = 0.0;
+            call clamp(-5, 0, 10, &a);
+            call clamp(15, 0, 10, &b);
+            call clamp(7, 0, 10, &c);
+            f = half > 0.25 ? half * 4.0 : half;
+            print a, \" \", b, \" \", c, \" \", f, \" \", half < 0.25 ? 1.5 : -1.5;
+        }",
+    );
+    assert_eq!(run.stdout, "0 10 7 2.0 -1.5");
+}
+
+#[test]
+fn only_the_chosen_arm_is_evaluated() {
+    let run = common::run(
+        "program {
+            let zero: i32 = 0, n: i32 = 3;
+            print zero == 0 ? 1 : n / zero, \" \", zero != 0 ? n / zero : 2;
+        }",
+    );
+    assert_eq!(run.stdout, "1 2");
+}
+
+#[test]
+fn arms_must_have_the_same_type() {
+    let err = common::compile(
+        "program { let n: i32 = 1; n = n > 0 ? 1 : 2.0; }",
+        &CodegenOptions::default(),
+    )
+    .err()
+    .unwrap();
+    assert!(err.starts_with("Mismatched type"), "{err}");
+}
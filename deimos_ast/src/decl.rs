@@ -1,11 +1,11 @@
-use crate::{Block, DeclType, Identifier, Located, ParamType, PrimitiveValue};
+use crate::{Block, DeclType, Identifier, Located, ParamType, PrimitiveType, PrimitiveValue};
 
-pub type InitList = Vec<Located<PrimitiveValue>>;
+pub type InitList = Vec<Located<InitValue>>;
 
 #[derive(Debug, Clone)]
 pub enum InitValue {
     Primitive(PrimitiveValue),
-    List(Vec<Located<PrimitiveValue>>),
+    List(InitList),
 }
 
 #[derive(Debug, Clone)]
@@ -33,18 +33,53 @@ pub type FunctionArgs = Vec<TypedIdent>;
 pub struct Function {
     pub name: Identifier,
     pub args: FunctionArgs,
+    /// Index of the sub's signature in `Program::signatures`
+    pub signature: usize,
     pub block: FunctionBlock,
 }
 
+/// Sub implemented in a separate assembly file.
+/// Calls jump straight to the declared name.
+#[derive(Debug)]
+pub struct ExternFn {
+    pub name: Identifier,
+    pub args: FunctionArgs,
+    pub signature: usize,
+}
+
+/// Variable at a fixed address. Pointers hold the address itself,
+/// arrays are laid out starting at it.
 #[derive(Debug)]
 pub struct MemVar {
-    pub var: TypedIdent,
+    pub name: Identifier,
+    pub variable: DeclType,
     pub addr: Located<u32>,
 }
 
+/// Named compile-time constant. The value has already been evaluated
+/// and converted to the declared type.
+#[derive(Debug)]
+pub struct ConstDecl {
+    pub name: Identifier,
+    pub const_type: Located<ParamType>,
+    pub value: Located<PrimitiveValue>,
+}
+
+/// Enum declaration. Each variant is also declared as a constant
+/// of the enum type.
+#[derive(Debug)]
+pub struct EnumDecl {
+    pub name: Identifier,
+    pub repr: Located<PrimitiveType>,
+    pub variants: Vec<Identifier>,
+}
+
 #[derive(Debug)]
 pub enum Definition {
     Function(usize),
+    Extern(usize),
     Static(usize),
     MemVar(usize),
+    Const(usize),
+    Enum(usize),
 }
@@ -25,6 +25,9 @@ pub enum BinaryOp {
     Equal,
     And,
     Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
     IndexAccess,
 }
 
@@ -33,9 +36,20 @@ pub enum PrimitiveValue {
     Float(f32),
     Int(i32),
     Unsigned(u32),
+    Byte(u8),
+    Bool(bool),
     String(usize),
 }
 
+/// Operand of `sizeof`. A bare name is parsed as an expression and may
+/// also refer to an enum type.
+#[derive(Debug)]
+pub enum SizeOperand {
+    Type(Located<ParamType>),
+    /// The expression is only used for its type and is never evaluated
+    Expr(Box<Expression>),
+}
+
 #[derive(Debug)]
 pub enum Expression {
     Binary {
@@ -51,6 +65,28 @@ pub enum Expression {
         value: Box<Expression>,
         cast_type: ParamType,
     },
+    /// `condition ? then_value : else_value`.
+    /// Only the selected value is evaluated.
+    Ternary {
+        condition: Box<Expression>,
+        then_value: Box<Expression>,
+        else_value: Box<Expression>,
+    },
+    /// `len(array)`: length of the first dimension of a fixed-size array
+    Len {
+        array: Box<Expression>,
+        loc: Location,
+    },
+    /// `sizeof(type)` or `sizeof(expr)`: size in bytes as a `u32`
+    SizeOf {
+        operand: SizeOperand,
+        loc: Location,
+    },
+    /// `alignof(type)`: alignment in bytes as a `u32`
+    AlignOf {
+        typ: Located<ParamType>,
+        loc: Location,
+    },
     Identifier(Identifier),
     Primitive(Located<PrimitiveValue>),
 }
@@ -59,8 +95,13 @@ impl Expression {
     /// Extracts location from expression
     pub fn get_loc(&self) -> Location {
         match self {
-            Self::Cast { value, .. } | Self::Binary { left: value, .. } => value.get_loc(),
+            Self::Cast { value, .. }
+            | Self::Binary { left: value, .. }
+            | Self::Ternary {
+                condition: value, ..
+            } => value.get_loc(),
             Self::Unary { op, .. } => op.loc,
+            Self::Len { loc, .. } | Self::SizeOf { loc, .. } | Self::AlignOf { loc, .. } => *loc,
             Self::Identifier(ident) => ident.loc,
             Self::Primitive(p) => p.loc,
         }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 mod decl;
 mod expr;
@@ -6,6 +6,7 @@ mod loc;
 mod stmt;
 mod sys;
 mod types;
+mod typing;
 
 pub use decl::*;
 pub use expr::*;
@@ -13,11 +14,13 @@ pub use loc::*;
 pub use stmt::*;
 pub use sys::*;
 pub use types::*;
+pub use typing::*;
 
 #[derive(Debug, Default)]
 pub struct StringBank {
     pub identifiers: Vec<String>,
-    pub strings: Vec<String>,
+    /// String literals as raw bytes, without the null terminator
+    pub strings: Vec<Vec<u8>>,
 }
 
 pub type Definitions = HashMap<usize, Definition>;
@@ -26,8 +29,16 @@ pub type Definitions = HashMap<usize, Definition>;
 pub struct Program {
     pub bank: StringBank,
     pub fns: Vec<Function>,
+    pub extern_fns: Vec<ExternFn>,
     pub static_vars: Vec<VarDecl>,
     pub mem_vars: Vec<MemVar>,
+    pub consts: Vec<ConstDecl>,
+    pub enums: Vec<EnumDecl>,
+    /// Every sub signature used by a sub or a sub pointer type
+    pub signatures: Vec<Signature>,
     pub definitions: Definitions,
-    pub body: FunctionBlock,
+    /// Subs and statics exported with `pub`
+    pub public: HashSet<usize>,
+    /// Only a library unit can be missing a program body
+    pub body: Option<FunctionBlock>,
 }
@@ -1,4 +1,4 @@
-use crate::{AsmBlock, Expression, Identifier, Located, Syscall};
+use crate::{AsmBlock, BinaryOp, Expression, Identifier, Located, Syscall};
 
 #[derive(Debug)]
 pub enum RValue {
@@ -23,6 +23,21 @@ pub struct LogicChain {
     pub else_block: Option<Block>,
 }
 
+/// Case of a switch. Its values are constant expressions
+#[derive(Debug)]
+pub struct SwitchCase {
+    pub values: Vec<Expression>,
+    pub body: Block,
+}
+
+/// Switch over an integer value. Cases don't fall through
+#[derive(Debug)]
+pub struct Switch {
+    pub value: Expression,
+    pub cases: Vec<SwitchCase>,
+    pub default: Option<Block>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ControlBreak {
     Break,
@@ -34,6 +49,16 @@ pub enum ControlBreak {
 pub struct Assignment {
     pub rvalue: Located<RValue>,
     pub lvalue: Expression,
+    /// Operator of a compound assignment such as `+=`
+    pub op: Option<Located<BinaryOp>>,
+}
+
+/// `x++` or `x--`. Steps the value by one of its own type.
+#[derive(Debug)]
+pub struct Increment {
+    pub rvalue: Located<RValue>,
+    /// Add or Sub
+    pub op: Located<BinaryOp>,
 }
 
 #[derive(Debug)]
@@ -41,9 +66,20 @@ pub struct Print {
     pub args: Vec<Expression>,
 }
 
+/// Formatted print. The format string is checked against the
+/// argument types during codegen
+#[derive(Debug)]
+pub struct Printf {
+    /// File descriptor written to by `fprintf`. `printf` writes to stdout
+    pub fd: Option<Expression>,
+    pub format: Located<usize>,
+    pub args: Vec<Expression>,
+}
+
 #[derive(Debug)]
 pub struct Invocation {
-    pub function: Identifier,
+    /// Either a sub name or a sub pointer, optionally indexed
+    pub function: Expression,
     pub args: Vec<Expression>,
 }
 
@@ -51,11 +87,14 @@ pub struct Invocation {
 pub enum Statement {
     LogicChain(LogicChain),
     While(ConditionBody),
+    Switch(Switch),
     Call(Invocation),
     Assignment(Assignment),
+    Increment(Increment),
     Syscall(Syscall),
     ControlBreak(Located<ControlBreak>),
     Print(Print),
+    Printf(Printf),
     Asm(AsmBlock),
 }
 
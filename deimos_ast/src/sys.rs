@@ -1,44 +1,142 @@
 use super::{Identifier, Located};
-use std::collections::HashMap;
-
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
-pub enum Reg {
-    A0,
-    A1,
-    A2,
-    A3,
-    V0,
-    F0,
-    F12,
-}
 
-impl Reg {
-    pub fn str(&self) -> &'static str {
-        match self {
-            Self::A0 => "$a0",
-            Self::A1 => "$a1",
-            Self::A2 => "$a2",
-            Self::A3 => "$a3",
-            Self::V0 => "$v0",
-            Self::F0 => "$f0",
-            Self::F12 => "$f12",
+/// Declares the registers that can be bound in asm and syscall blocks
+macro_rules! register_map {
+    (
+        regular { $( $reg:ident -> $name:literal ),* $(,)? }
+        float { $( $freg:ident -> $fname:literal ),* $(,)? }
+    ) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+        pub enum Reg {
+            $( $reg, )*
+            $( $freg, )*
         }
-    }
 
-    pub fn is_float(&self) -> bool {
-        match self {
-            Self::F0 | Self::F12 => true,
-            _ => false,
+        impl Reg {
+            pub fn str(&self) -> &'static str {
+                match self {
+                    $( Self::$reg => concat!("$", $name), )*
+                    $( Self::$freg => concat!("$", $fname), )*
+                }
+            }
+
+            /// Looks up a register by its name without the `$`
+            pub fn from_name(s: &str) -> Option<Self> {
+                match s {
+                    $( $name => Some(Self::$reg), )*
+                    $( $fname => Some(Self::$freg), )*
+                    _ => None,
+                }
+            }
+
+            pub fn is_float(&self) -> bool {
+                matches!(self, $( Self::$freg )|*)
+            }
         }
+    };
+}
+
+register_map! {
+    regular {
+        V0 -> "v0",
+        V1 -> "v1",
+        A0 -> "a0",
+        A1 -> "a1",
+        A2 -> "a2",
+        A3 -> "a3",
+        T0 -> "t0",
+        T1 -> "t1",
+        T2 -> "t2",
+        T3 -> "t3",
+        T4 -> "t4",
+        T5 -> "t5",
+        T6 -> "t6",
+        T7 -> "t7",
+        T8 -> "t8",
+        T9 -> "t9",
+        S0 -> "s0",
+        S1 -> "s1",
+        S2 -> "s2",
+        S3 -> "s3",
+        S4 -> "s4",
+        S5 -> "s5",
+        S6 -> "s6",
+        S7 -> "s7",
+    }
+    float {
+        F0 -> "f0",
+        F1 -> "f1",
+        F2 -> "f2",
+        F3 -> "f3",
+        F4 -> "f4",
+        F5 -> "f5",
+        F6 -> "f6",
+        F7 -> "f7",
+        F8 -> "f8",
+        F9 -> "f9",
+        F10 -> "f10",
+        F11 -> "f11",
+        F12 -> "f12",
+        F13 -> "f13",
+        F14 -> "f14",
+        F15 -> "f15",
+        F16 -> "f16",
+        F17 -> "f17",
+        F18 -> "f18",
+        F19 -> "f19",
+        F20 -> "f20",
+        F21 -> "f21",
+        F22 -> "f22",
+        F23 -> "f23",
+        F24 -> "f24",
+        F25 -> "f25",
+        F26 -> "f26",
+        F27 -> "f27",
+        F28 -> "f28",
+        F29 -> "f29",
+        F30 -> "f30",
+        F31 -> "f31",
     }
 }
 
-pub type RegisterMap = HashMap<Reg, Identifier>;
+/// Registers bound to variables. Bindings are kept in source order so
+/// the loads and stores around a block are always emitted the same way.
+#[derive(Debug, Default)]
+pub struct RegisterMap {
+    bindings: Vec<(Reg, Identifier)>,
+}
+
+impl RegisterMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds a register, returning the variable it was bound to before
+    pub fn insert(&mut self, reg: Reg, ident: Identifier) -> Option<Identifier> {
+        match self.bindings.iter_mut().find(|(r, _)| *r == reg) {
+            Some((_, old)) => Some(std::mem::replace(old, ident)),
+            None => {
+                self.bindings.push((reg, ident));
+                None
+            }
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Reg> {
+        self.bindings.iter().map(|(reg, _)| reg)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Reg, &Identifier)> {
+        self.bindings.iter().map(|(reg, ident)| (reg, ident))
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct RegVars {
     pub in_values: RegisterMap,
     pub out_values: RegisterMap,
+    /// Registers the block overwrites that are restored after it
+    pub clobbers: Vec<Located<Reg>>,
 }
 
 #[derive(Debug)]
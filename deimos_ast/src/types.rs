@@ -1,4 +1,7 @@
-use super::Located;
+use super::{FunctionArgs, Located};
+
+/// Size of a pointer in bytes
+pub const PTR_SIZE: u32 = 4;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum PrimitiveType {
@@ -6,6 +9,43 @@ pub enum PrimitiveType {
     U32,
     F32,
     U8,
+    I8,
+    I16,
+    U16,
+    Bool,
+    /// Named enum type, identified by the enum's name.
+    /// Enum values are stored as words.
+    Enum(usize),
+    /// Sub with the signature at this index of `Program::signatures`.
+    /// Only pointers to subs can be declared.
+    Sub(usize),
+}
+
+impl PrimitiveType {
+    /// Size of a value of this type in bytes
+    pub fn size(&self) -> u32 {
+        match self {
+            Self::U8 | Self::I8 | Self::Bool => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::I32 | Self::U32 | Self::F32 | Self::Enum(_) | Self::Sub(_) => 4,
+        }
+    }
+
+    pub fn is_int(&self) -> bool {
+        !matches!(self, Self::F32 | Self::Bool | Self::Enum(_) | Self::Sub(_))
+    }
+
+    pub fn is_enum(&self) -> bool {
+        matches!(self, Self::Enum(_))
+    }
+
+    pub fn is_sub(&self) -> bool {
+        matches!(self, Self::Sub(_))
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::I8 | Self::I16 | Self::I32)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -18,17 +58,82 @@ impl ParamType {
     pub fn type_eq(&self, other: &ParamType) -> bool {
         other.param_type.data == self.param_type.data && self.indirection == other.indirection
     }
+
+    /// Size of a value of this type in bytes
+    pub fn size(&self) -> u32 {
+        match self.indirection {
+            0 => self.param_type.data.size(),
+            _ => PTR_SIZE,
+        }
+    }
+
+    /// Values are aligned to their size
+    pub fn align(&self) -> u32 {
+        self.size()
+    }
+}
+
+/// Argument types of a sub. Signatures are interned, so two sub types
+/// are the same when their indices are.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub args: Vec<ParamType>,
+}
+
+impl Signature {
+    pub fn from_args(args: &FunctionArgs) -> Self {
+        Self {
+            args: args.iter().map(|a| a.field_type.data.clone()).collect(),
+        }
+    }
+
+    pub fn type_eq(&self, other: &Signature) -> bool {
+        self.args.len() == other.args.len()
+            && self.args.iter().zip(&other.args).all(|(a, b)| a.type_eq(b))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum DeclType {
     Param(Located<ParamType>),
+    /// Array declaration. Multi-dimensional arrays are stored in
+    /// row-major order with `dims` in declaration order.
     Array {
         array_type: Located<ParamType>,
-        size: Located<u32>,
+        dims: Vec<Located<u32>>,
     },
 }
 
+impl DeclType {
+    /// Size of the declared value in bytes. Arrays have no padding
+    /// between elements. The parser rejects declarations too large
+    /// for [`DeclType::checked_size`].
+    pub fn size(&self) -> u32 {
+        self.checked_size().expect("declaration size overflows u32")
+    }
+
+    /// Size of the declared value in bytes, or `None` if it doesn't
+    /// fit in a u32
+    pub fn checked_size(&self) -> Option<u32> {
+        match self {
+            DeclType::Param(p) => Some(p.data.size()),
+            DeclType::Array { array_type, dims } => dims
+                .iter()
+                .try_fold(array_type.data.size(), |size, d| size.checked_mul(d.data)),
+        }
+    }
+
+    /// Arrays are aligned like their elements
+    pub fn align(&self) -> u32 {
+        match self {
+            DeclType::Param(typ)
+            | DeclType::Array {
+                array_type: typ, ..
+            } => typ.data.align(),
+        }
+    }
+}
+
 impl From<Located<ParamType>> for DeclType {
     fn from(value: Located<ParamType>) -> Self {
         DeclType::Param(value)
@@ -0,0 +1,216 @@
+use super::*;
+
+/// Names an expression can refer to while it's being typed
+pub trait TypeScope {
+    type Error: From<TypeError>;
+
+    /// Declared type of a variable or constant
+    fn name_type(&self, name: Identifier) -> Result<DeclType, Self::Error>;
+
+    /// Declared type of a variable that can be referenced
+    fn var_type(&self, name: Identifier) -> Result<DeclType, Self::Error>;
+
+    /// Signature index of a sub, for taking its address
+    fn sub_signature(&self, name: Identifier) -> Option<usize>;
+
+    /// Checks that the types named in a cast exist
+    fn check_type(&self, _typ: &ParamType) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Expression whose operands don't fit its operator
+#[derive(Debug, Clone, Copy)]
+pub enum TypeError {
+    InvalidUnary(UnaryOp, Location),
+    InvalidBinary(BinaryOp, Location),
+    Mismatched(Location),
+}
+
+impl DeclType {
+    /// Arrays used as values are pointers to their first element
+    pub fn decay(self) -> Located<ParamType> {
+        match self {
+            DeclType::Param(typ) => typ,
+            DeclType::Array { mut array_type, .. } => {
+                array_type.data.indirection += 1;
+                array_type
+            }
+        }
+    }
+}
+
+fn param(prim: PrimitiveType, indirection: usize, loc: Location) -> Located<ParamType> {
+    let typ = ParamType {
+        param_type: Located::new(prim, loc),
+        indirection,
+    };
+    Located::new(typ, loc)
+}
+
+fn tuple(typ: &ParamType) -> (PrimitiveType, usize) {
+    (typ.param_type.data, typ.indirection)
+}
+
+/// Declared type of an expression without evaluating it. Arrays keep
+/// their dimensions until they decay in arithmetic or a dereference.
+pub fn type_of<S: TypeScope>(expr: &Expression, s: &S) -> Result<DeclType, S::Error> {
+    let loc = expr.get_loc();
+    let typ = match expr {
+        Expression::Identifier(ident) => return s.name_type(*ident),
+        Expression::Primitive(p) => match p.data {
+            PrimitiveValue::Float(_) => param(PrimitiveType::F32, 0, loc),
+            PrimitiveValue::Int(_) => param(PrimitiveType::I32, 0, loc),
+            PrimitiveValue::Unsigned(_) => param(PrimitiveType::U32, 0, loc),
+            PrimitiveValue::Byte(_) => param(PrimitiveType::U8, 0, loc),
+            PrimitiveValue::Bool(_) => param(PrimitiveType::Bool, 0, loc),
+            PrimitiveValue::String(_) => param(PrimitiveType::U8, 1, loc),
+        },
+        Expression::Cast { cast_type, .. } => {
+            s.check_type(cast_type)?;
+            Located::new(cast_type.clone(), loc)
+        }
+        Expression::Len { .. } | Expression::SizeOf { .. } | Expression::AlignOf { .. } => {
+            param(PrimitiveType::U32, 0, loc)
+        }
+        Expression::Unary { operand, op } => return unary_type(operand, *op, s),
+        Expression::Binary { left, right, op } => return binary_type(left, right, *op, s),
+        Expression::Ternary {
+            then_value,
+            else_value,
+            ..
+        } => {
+            let then_type = type_of(then_value, s)?.decay();
+            let else_type = type_of(else_value, s)?.decay();
+            if !then_type.data.type_eq(&else_type.data) {
+                return Err(TypeError::Mismatched(else_value.get_loc()).into());
+            }
+            then_type
+        }
+    };
+    Ok(typ.into())
+}
+
+fn unary_type<S: TypeScope>(
+    operand: &Expression,
+    op: Located<UnaryOp>,
+    s: &S,
+) -> Result<DeclType, S::Error> {
+    let invalid = TypeError::InvalidUnary(op.data, op.loc);
+    if matches!(op.data, UnaryOp::Reference) {
+        return match operand {
+            Expression::Identifier(ident) => match s.var_type(*ident) {
+                // Referencing an array gives the same pointer as the array
+                Ok(array @ DeclType::Array { .. }) => Ok(array.decay().into()),
+                Ok(DeclType::Param(mut typ)) => {
+                    typ.data.indirection += 1;
+                    Ok(typ.into())
+                }
+                Err(e) => match s.sub_signature(*ident) {
+                    Some(sig) => Ok(param(PrimitiveType::Sub(sig), 1, op.loc).into()),
+                    None => Err(e),
+                },
+            },
+            Expression::Binary {
+                left,
+                right,
+                op:
+                    index_op @ Located {
+                        data: BinaryOp::IndexAccess,
+                        ..
+                    },
+            } => {
+                // A row of a multi-dimensional array is already a pointer
+                match binary_type(left, right, *index_op, s)? {
+                    row @ DeclType::Array { .. } => Ok(row.decay().into()),
+                    DeclType::Param(mut typ) => {
+                        typ.data.indirection += 1;
+                        Ok(typ.into())
+                    }
+                }
+            }
+            _ => Err(invalid.into()),
+        };
+    }
+
+    let mut typ = type_of(operand, s)?.decay();
+    match (op.data, tuple(&typ.data)) {
+        (UnaryOp::LogicNot, _) => typ = param(PrimitiveType::Bool, 0, op.loc),
+        (UnaryOp::Negation, (PrimitiveType::F32, 0)) => {}
+        (UnaryOp::Negation, (prim, 0)) if prim.is_int() && prim.is_signed() => {}
+        // Sub pointers can only be called
+        (UnaryOp::Deref, (PrimitiveType::Sub(_), 1)) => return Err(invalid.into()),
+        (UnaryOp::Deref, (_, 1..)) => typ.data.indirection -= 1,
+        _ => return Err(invalid.into()),
+    }
+    Ok(typ.into())
+}
+
+fn binary_type<S: TypeScope>(
+    left: &Expression,
+    right: &Expression,
+    op: Located<BinaryOp>,
+    s: &S,
+) -> Result<DeclType, S::Error> {
+    let invalid = TypeError::InvalidBinary(op.data, op.loc);
+    let left_type = type_of(left, s)?;
+    let right_type = type_of(right, s)?.decay();
+    let index_is_int = matches!(tuple(&right_type.data), (prim, 0) if prim.is_int());
+
+    if matches!(op.data, BinaryOp::IndexAccess) {
+        return match left_type {
+            DeclType::Array { .. } if !index_is_int => Err(invalid.into()),
+            // Indexing into a multi-dimensional array yields a row
+            DeclType::Array { array_type, dims } if dims.len() > 1 => Ok(DeclType::Array {
+                array_type,
+                dims: dims[1..].to_vec(),
+            }),
+            DeclType::Array { array_type, .. } => Ok(array_type.into()),
+            DeclType::Param(typ) => match tuple(&typ.data) {
+                (PrimitiveType::Sub(_), _) => Err(invalid.into()),
+                (_, 1..) if index_is_int => {
+                    let mut typ = typ;
+                    typ.data.indirection -= 1;
+                    Ok(typ.into())
+                }
+                _ => Err(invalid.into()),
+            },
+        };
+    }
+
+    let left_type = left_type.decay();
+    let result = match (op.data, tuple(&left_type.data), tuple(&right_type.data)) {
+        (BinaryOp::Add | BinaryOp::Sub, (_, 1..), _) if index_is_int => left_type,
+        (BinaryOp::Add | BinaryOp::Sub, (l, 0), (r, 0)) if l == r && l.is_int() => left_type,
+        (
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mult | BinaryOp::Div,
+            (PrimitiveType::F32, 0),
+            (PrimitiveType::F32, 0),
+        ) => left_type,
+        (BinaryOp::Mult | BinaryOp::Div, (l, 0), (r, 0)) if l == r => left_type,
+        (BinaryOp::Mod, (l, 0), (r, 0)) if l == r && l.is_int() => left_type,
+        (BinaryOp::And | BinaryOp::Or | BinaryOp::Xor, (l, 0), (r, 0))
+            if l == r && (l.is_int() || l == PrimitiveType::Bool) =>
+        {
+            left_type
+        }
+        // The shift amount can be any integer type
+        (BinaryOp::ShiftLeft | BinaryOp::ShiftRight, (l, 0), (r, 0))
+            if l.is_int() && r.is_int() =>
+        {
+            left_type
+        }
+        (
+            BinaryOp::Equal
+            | BinaryOp::NotEq
+            | BinaryOp::LessThan
+            | BinaryOp::LessThanEq
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanEq,
+            (l, 0),
+            (r, 0),
+        ) if l == r => param(PrimitiveType::Bool, 0, op.loc),
+        _ => return Err(invalid.into()),
+    };
+    Ok(result.into())
+}
@@ -0,0 +1,46 @@
+use deimos_ast::*;
+use mips_builder::{FloatRegister, GenericRegister, Register};
+
+/// Number of argument words that are passed in registers
+const ARG_REG_COUNT: usize = 4;
+
+static ARG_REGISTERS: [Register; ARG_REG_COUNT] =
+    [Register::A0, Register::A1, Register::A2, Register::A3];
+
+static FLOAT_ARG_REGISTERS: [FloatRegister; 2] = [FloatRegister::F12, FloatRegister::F14];
+
+/// Stack pointer alignment required between calls
+pub const STACK_ALIGN: u32 = 8;
+
+fn is_float(typ: &ParamType) -> bool {
+    typ.indirection == 0 && typ.param_type.data == PrimitiveType::F32
+}
+
+/// Gets the register each argument is passed in following o32.
+/// Floats at the start of the argument list use $f12/$f14, every
+/// other one of the first four words goes in $a0-$a3 (floats as raw bits).
+/// The remaining arguments are only passed on the stack.
+pub fn arg_registers<'a>(
+    args: impl IntoIterator<Item = &'a ParamType>,
+) -> Vec<Option<GenericRegister>> {
+    let mut leading_floats = true;
+    args.into_iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            leading_floats &= is_float(arg);
+            if leading_floats && index < FLOAT_ARG_REGISTERS.len() {
+                Some(FLOAT_ARG_REGISTERS[index].into())
+            } else {
+                ARG_REGISTERS.get(index).map(|&r| r.into())
+            }
+        })
+        .collect()
+}
+
+/// Size of the argument area a caller reserves. Every argument has a
+/// word slot and there's always room for the four register arguments
+/// so callees can spill them.
+pub fn arg_area_size(arg_count: usize) -> u32 {
+    let words = arg_count.max(ARG_REG_COUNT) as u32;
+    (words * 4).div_ceil(STACK_ALIGN) * STACK_ALIGN
+}
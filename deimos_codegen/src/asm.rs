@@ -0,0 +1,284 @@
+use deimos_ast::*;
+use mips_builder::{
+    is_mnemonic, register_number, FloatRegister, GenericRegister, MipsAddress, MipsBuilder,
+    Register,
+};
+
+use crate::error::{ValidationError, ValidationResult};
+use crate::expr::temp::ExprType;
+use crate::expr::value::{load_int, save_int};
+use crate::scope::{LocatedValue, Scope, ValLocation};
+
+/// Registers handed out to `{name}` operands
+static OPERAND_REGISTERS: [Register; 10] = [
+    Register::T0,
+    Register::T1,
+    Register::T2,
+    Register::T3,
+    Register::T4,
+    Register::T5,
+    Register::T6,
+    Register::T7,
+    Register::T8,
+    Register::T9,
+];
+
+static OPERAND_FLOAT_REGISTERS: [FloatRegister; 12] = [
+    FloatRegister::F4,
+    FloatRegister::F5,
+    FloatRegister::F6,
+    FloatRegister::F7,
+    FloatRegister::F8,
+    FloatRegister::F9,
+    FloatRegister::F10,
+    FloatRegister::F11,
+    FloatRegister::F16,
+    FloatRegister::F17,
+    FloatRegister::F18,
+    FloatRegister::F19,
+];
+
+enum AsmPiece<'a> {
+    Text(&'a str),
+    /// `{name}`, the value of a variable in a register picked by the compiler
+    Value(Identifier),
+    /// `{&name}`, the address of a variable
+    Address(Identifier),
+}
+
+/// Asm string split into literal text and placeholders
+pub struct AsmLine<'a> {
+    pieces: Vec<AsmPiece<'a>>,
+    loc: Location,
+}
+
+/// Checks the instruction name of an asm string, skipping a leading label.
+/// Directives and comments are left for the assembler
+fn validate_mnemonic(text: &str, loc: Location) -> ValidationResult<()> {
+    let code = text.split_once('#').map_or(text, |(code, _)| code);
+    let mut instr = code.trim_start();
+    if let Some((label, rest)) = instr.split_once(':') {
+        if label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            instr = rest.trim_start();
+        }
+    }
+    match instr.split_whitespace().next() {
+        Some(name) if !name.starts_with('.') && !is_mnemonic(name) => {
+            Err(ValidationError::UnknownMnemonic(loc, name.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Splits an asm string into text and `{name}`/`{&name}` placeholders
+pub fn parse_asm_line<'a>(
+    text: &'a str,
+    loc: Location,
+    bank: &StringBank,
+) -> ValidationResult<AsmLine<'a>> {
+    validate_mnemonic(text, loc)?;
+
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        pieces.push(AsmPiece::Text(&rest[..start]));
+        let len = rest[start..]
+            .find('}')
+            .ok_or(ValidationError::InvalidAsmOperand(loc))?;
+        let placeholder = rest[start + 1..start + len].trim();
+        let (name, is_addr) = match placeholder.strip_prefix('&') {
+            Some(name) => (name.trim(), true),
+            None => (placeholder, false),
+        };
+        let ident = bank
+            .identifiers
+            .iter()
+            .position(|i| i == name)
+            .map(|i| Located::new(i, loc))
+            .ok_or(ValidationError::UndefinedIdent(loc))?;
+        pieces.push(if is_addr {
+            AsmPiece::Address(ident)
+        } else {
+            AsmPiece::Value(ident)
+        });
+        rest = &rest[start + len + 1..];
+    }
+    pieces.push(AsmPiece::Text(rest));
+    Ok(AsmLine { pieces, loc })
+}
+
+/// Checks if a `$name` or `$N` word of an asm string refers to a register
+fn names_register(word: &str, reg: GenericRegister) -> bool {
+    match reg {
+        GenericRegister::Regular(_) => {
+            register_number(word).is_some() && register_number(word) == register_number(reg.str())
+        }
+        GenericRegister::Float(_) => word == reg.str(),
+    }
+}
+
+enum OperandValue {
+    Immediate(String),
+    Word {
+        register: Register,
+        addr: ValLocation,
+        typ: ExprType,
+    },
+    Float {
+        register: FloatRegister,
+        addr: ValLocation,
+    },
+    /// Arrays and mem variables are passed as their address
+    Address {
+        register: Register,
+        addr: ValLocation,
+    },
+}
+
+/// Registers and values assigned to the `{name}` operands of an asm block
+pub struct AsmOperands {
+    values: Vec<(usize, OperandValue)>,
+}
+
+impl AsmOperands {
+    /// Picks a register for every operand, avoiding the ones the block
+    /// names explicitly in its strings or register maps
+    pub fn allocate(
+        lines: &[AsmLine],
+        reserved: &[GenericRegister],
+        s: &Scope,
+    ) -> ValidationResult<Self> {
+        let mut named_regs = Vec::new();
+        for piece in lines.iter().flat_map(|l| &l.pieces) {
+            if let AsmPiece::Text(text) = piece {
+                let words = text.split(|c: char| !c.is_ascii_alphanumeric() && c != '$');
+                named_regs.extend(words.filter(|word| word.starts_with('$')));
+            }
+        }
+        let is_free = |reg: GenericRegister| {
+            !reserved.contains(&reg) && !named_regs.iter().any(|name| names_register(name, reg))
+        };
+        let mut int_regs = OPERAND_REGISTERS.iter().filter(|r| is_free((**r).into()));
+        let mut float_regs = OPERAND_FLOAT_REGISTERS
+            .iter()
+            .filter(|f| is_free((**f).into()));
+
+        let mut values = Vec::new();
+        for line in lines {
+            for piece in &line.pieces {
+                let AsmPiece::Value(ident) = piece else {
+                    continue;
+                };
+                if values.iter().any(|(i, _)| *i == ident.data) {
+                    continue;
+                }
+                let limit = ValidationError::AsmOperandLimit(line.loc);
+                let value = if let Some((val, _)) = s.get_const(*ident) {
+                    OperandValue::Immediate(match val {
+                        PrimitiveValue::Int(i) => i.to_string(),
+                        PrimitiveValue::Unsigned(u) => u.to_string(),
+                        PrimitiveValue::Byte(b) => b.to_string(),
+                        PrimitiveValue::Bool(b) => (b as u32).to_string(),
+                        _ => return Err(ValidationError::InvalidAsmOperand(line.loc)),
+                    })
+                } else {
+                    let LocatedValue { loc: addr, val } = s.get_var(*ident)?;
+                    match (&addr, val) {
+                        (ValLocation::RawAddr(_), _) | (_, DeclType::Array { .. }) => {
+                            OperandValue::Address {
+                                register: *int_regs.next().ok_or(limit)?,
+                                addr,
+                            }
+                        }
+                        (_, DeclType::Param(p)) => {
+                            let typ = ExprType::from(p.data);
+                            if typ.indirection == 0 && typ.base == PrimitiveType::F32 {
+                                OperandValue::Float {
+                                    register: *float_regs.next().ok_or(limit)?,
+                                    addr,
+                                }
+                            } else {
+                                OperandValue::Word {
+                                    register: *int_regs.next().ok_or(limit)?,
+                                    addr,
+                                    typ,
+                                }
+                            }
+                        }
+                    }
+                };
+                values.push((ident.data, value));
+            }
+        }
+        Ok(AsmOperands { values })
+    }
+
+    /// Loads every operand into its register
+    pub fn load(&self, b: &mut MipsBuilder) {
+        for (_, value) in &self.values {
+            match value {
+                OperandValue::Immediate(_) => {}
+                OperandValue::Word {
+                    register,
+                    addr,
+                    typ,
+                } => load_int(b, *typ, *register, addr.clone()),
+                OperandValue::Float { register, addr } => b.load_f32(*register, addr.clone()),
+                OperandValue::Address { register, addr } => b.load_addr(*register, addr.clone()),
+            }
+        }
+    }
+
+    /// Writes operand registers back to their variables
+    pub fn store(&self, b: &mut MipsBuilder) {
+        for (_, value) in &self.values {
+            match value {
+                OperandValue::Word {
+                    register,
+                    addr,
+                    typ,
+                } => save_int(b, *typ, *register, addr.clone()),
+                OperandValue::Float { register, addr } => b.save_f32(*register, addr.clone()),
+                OperandValue::Immediate(_) | OperandValue::Address { .. } => {}
+            }
+        }
+    }
+
+    /// Substitutes the placeholders of an asm string
+    pub fn expand(&self, line: &AsmLine, s: &Scope) -> ValidationResult<String> {
+        let mut instr = String::new();
+        for piece in &line.pieces {
+            match piece {
+                AsmPiece::Text(t) => instr.push_str(t),
+                AsmPiece::Value(ident) => {
+                    let (_, value) = self
+                        .values
+                        .iter()
+                        .find(|(i, _)| *i == ident.data)
+                        .expect("Operand allocated for every placeholder");
+                    match value {
+                        OperandValue::Immediate(imm) => instr.push_str(imm),
+                        OperandValue::Word { register, .. }
+                        | OperandValue::Address { register, .. } => {
+                            instr.push_str(&register.to_string())
+                        }
+                        OperandValue::Float { register, .. } => {
+                            instr.push_str(&register.to_string())
+                        }
+                    }
+                }
+                AsmPiece::Address(ident) => {
+                    if s.get_const(*ident).is_some() {
+                        return Err(ValidationError::ConstReference(line.loc));
+                    }
+                    let addr = s.get_var(*ident)?.loc;
+                    if let ValLocation::RawAddr(_) = addr {
+                        return Err(ValidationError::MemReference(line.loc));
+                    }
+                    instr.push_str(&MipsAddress::from(addr).to_string());
+                }
+            }
+        }
+        Ok(instr)
+    }
+}
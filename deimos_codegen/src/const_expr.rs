@@ -1,72 +1,50 @@
 /// Module responsible for codegen for static expressions (local variable and
 /// static var declaration)
 use crate::error::*;
-use crate::names::{get_static_name, get_str_name};
+use crate::expr::{temp::ExprType, value::save_int};
+use crate::names::get_str_name;
 use deimos_ast::*;
 use mips_builder::*;
 
+/// Create directive for a single integer of the given type
+fn int_directive(typ: PrimitiveType, val: u32) -> DataDirective {
+    match typ.size() {
+        1 => DataDirective::from(val as u8),
+        2 => DataDirective::from(val as u16),
+        _ => DataDirective::from(val),
+    }
+}
+
 /// Create directive for scalar static variable
 fn init_static_param(
     bank: &StringBank,
     param_type: &ParamType,
     init_val: &Option<Located<InitValue>>,
 ) -> ValidationResult<DataDirective> {
-    match (param_type.param_type.data, param_type.indirection, init_val) {
-        (PrimitiveType::F32, 0, None) => Ok(DataDirective::from(0.0)),
+    let typ = (param_type.param_type.data, param_type.indirection);
+    let init = match (typ, init_val) {
+        ((PrimitiveType::F32, 0), None) => return Ok(DataDirective::from(0.0)),
+        ((prim, 0), None) => return Ok(int_directive(prim, 0)),
+        (_, None) => return Ok(DataDirective::from(0i32)),
         (
-            PrimitiveType::F32,
-            0,
+            _,
             Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::Float(f)),
-                ..
+                data: InitValue::Primitive(p),
+                loc,
             }),
-        ) => Ok(DataDirective::from(*f)),
-        (PrimitiveType::I32 | PrimitiveType::U32, 0, None) | (_, 1.., None) => {
-            Ok(DataDirective::from(0i32))
+        ) => Located::new(*p, *loc),
+        (_, Some(Located { loc, .. })) => return Err(ValidationError::InvalidStaticVar(*loc)),
+    };
+    let loc = init.loc;
+    let directive = match typ {
+        (PrimitiveType::F32, 0) => expect_f32(init).map(DataDirective::from),
+        (prim, 0) => expect_int(init, prim).map(|val| int_directive(prim, val)),
+        (PrimitiveType::U8, 1) => {
+            expect_string(init).map(|s| DataDirective::Asciiz(bank.strings[s].clone()))
         }
-        (PrimitiveType::U8, 0, None) => Ok(DataDirective::from(0u8)),
-        (
-            PrimitiveType::I32 | PrimitiveType::U32,
-            0,
-            Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::Int(i)),
-                ..
-            }),
-        ) => Ok(DataDirective::from(*i)),
-        (
-            PrimitiveType::I32 | PrimitiveType::U32,
-            0,
-            Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::Unsigned(i)),
-                ..
-            }),
-        ) => Ok(DataDirective::from(*i)),
-        (
-            PrimitiveType::U8,
-            0,
-            Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::Unsigned(i @ 0..=255)),
-                ..
-            }),
-        ) => Ok(DataDirective::from(*i as u8)),
-        (
-            PrimitiveType::U8,
-            0,
-            Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::Int(i @ 0..=255)),
-                ..
-            }),
-        ) => Ok(DataDirective::from(*i as u8)),
-        (
-            PrimitiveType::U8,
-            1,
-            Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::String(s)),
-                ..
-            }),
-        ) => Ok(DataDirective::Asciiz(bank.strings[*s].clone())),
-        (_, _, Some(Located { loc, .. })) => Err(ValidationError::InvalidStaticVar(*loc)),
-    }
+        _ => Err(ValidationError::InvalidStaticVar(loc)),
+    };
+    directive.map_err(|_| ValidationError::InvalidStaticVar(loc))
 }
 
 fn expect_f32(val: Located<PrimitiveValue>) -> ValidationResult<f32> {
@@ -74,102 +52,153 @@ fn expect_f32(val: Located<PrimitiveValue>) -> ValidationResult<f32> {
         PrimitiveValue::Float(f) => Ok(f),
         PrimitiveValue::Int(i) => Ok(i as f32),
         PrimitiveValue::Unsigned(i) => Ok(i as f32),
+        PrimitiveValue::Byte(i) => Ok(i as f32),
         _ => Err(ValidationError::MismatchedType(val.loc)),
     }
 }
 
-fn expect_word(val: Located<PrimitiveValue>) -> ValidationResult<u32> {
-    match val.data {
-        PrimitiveValue::Int(i) => Ok(i as u32),
-        PrimitiveValue::Unsigned(i) => Ok(i),
-        _ => Err(ValidationError::MismatchedType(val.loc)),
+/// Gets the value of an integer or bool constant as a word, checking that
+/// it fits in the given type. Word-sized types accept any integer.
+fn expect_int(val: Located<PrimitiveValue>, typ: PrimitiveType) -> ValidationResult<u32> {
+    let wide = match (val.data, typ) {
+        (PrimitiveValue::Bool(b), PrimitiveType::Bool) => return Ok(b as u32),
+        (_, PrimitiveType::Bool) => return Err(ValidationError::MismatchedType(val.loc)),
+        (PrimitiveValue::Int(i), _) => i as i64,
+        (PrimitiveValue::Unsigned(i), _) => i as i64,
+        (PrimitiveValue::Byte(i), _) => i as i64,
+        _ => return Err(ValidationError::MismatchedType(val.loc)),
+    };
+    let fits = match typ {
+        PrimitiveType::I8 => i8::try_from(wide).is_ok(),
+        PrimitiveType::U8 => u8::try_from(wide).is_ok(),
+        PrimitiveType::I16 => i16::try_from(wide).is_ok(),
+        PrimitiveType::U16 => u16::try_from(wide).is_ok(),
+        PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::Enum(_) => true,
+        PrimitiveType::F32 | PrimitiveType::Bool | PrimitiveType::Sub(_) => false,
+    };
+    if fits {
+        Ok(wide as u32)
+    } else {
+        Err(ValidationError::MismatchedType(val.loc))
     }
 }
 
-fn expect_byte(val: Located<PrimitiveValue>) -> ValidationResult<u8> {
+fn expect_string(val: Located<PrimitiveValue>) -> ValidationResult<usize> {
     match val.data {
-        PrimitiveValue::Int(i) if i >= 0 && i < 256 => Ok(i as u8),
-        PrimitiveValue::Unsigned(i) if i < 256 => Ok(i as u8),
+        PrimitiveValue::String(s) => Ok(s),
         _ => Err(ValidationError::MismatchedType(val.loc)),
     }
 }
 
-fn expect_string(val: Located<PrimitiveValue>) -> ValidationResult<usize> {
-    match val.data {
-        PrimitiveValue::String(s) => Ok(s),
-        _ => Err(ValidationError::MismatchedType(val.loc)),
+/// Flattens a (possibly nested) array initializer list into row-major
+/// order. Each nesting level must match the corresponding dimension.
+fn flatten_init_list(
+    dims: &[Located<u32>],
+    list: &InitList,
+    out: &mut Vec<Located<PrimitiveValue>>,
+) -> Option<()> {
+    let (dim, inner_dims) = dims.split_first()?;
+    if list.len() != dim.data as usize {
+        return None;
+    }
+    for val in list {
+        match &val.data {
+            InitValue::Primitive(p) if inner_dims.is_empty() => {
+                out.push(Located::new(*p, val.loc));
+            }
+            InitValue::List(l) if !inner_dims.is_empty() => {
+                flatten_init_list(inner_dims, l, out)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+/// Bytes of a string literal used to initialize a u8 array, padded with
+/// zeros to the length of the array. The string must leave room for its
+/// null terminator.
+fn get_string_init(bank: &StringBank, str_id: usize, len: usize) -> Option<Vec<u8>> {
+    let mut bytes = bank.strings[str_id].clone();
+    if bytes.len() >= len {
+        return None;
     }
+    bytes.resize(len, 0);
+    Some(bytes)
+}
+
+fn get_flat_init(dims: &[Located<u32>], list: &InitList) -> Option<Vec<Located<PrimitiveValue>>> {
+    let mut flat = Vec::new();
+    flatten_init_list(dims, list, &mut flat).map(|_| flat)
 }
 
 /// Create directive for array static variable
 fn init_static_array(
     bank: &StringBank,
     array_type: &ParamType,
-    array_size: u32,
+    dims: &[Located<u32>],
     init_val: &Option<Located<InitValue>>,
 ) -> ValidationResult<DataDirective> {
-    let array_size = array_size as usize;
+    let array_size = dims.iter().map(|d| d.data as usize).product();
     let directive = match (array_type.param_type.data, array_type.indirection, init_val) {
-        (PrimitiveType::U8, 0, None) => DataDirective::ByteLen {
+        (PrimitiveType::F32, 0, None) | (_, 1.., None) => DataDirective::WordLen {
             len: array_size,
             default: 0,
         },
-        (PrimitiveType::U32 | PrimitiveType::I32 | PrimitiveType::F32, 0, None)
-        | (_, 1.., None) => DataDirective::WordLen {
-            len: array_size,
-            default: 0,
+        (prim, 0, None) => match prim.size() {
+            1 => DataDirective::ByteLen {
+                len: array_size,
+                default: 0,
+            },
+            2 => DataDirective::HalfLen {
+                len: array_size,
+                default: 0,
+            },
+            _ => DataDirective::WordLen {
+                len: array_size,
+                default: 0,
+            },
         },
         (
-            PrimitiveType::F32,
-            0,
-            Some(Located {
-                data: InitValue::List(list),
-                ..
-            }),
-        ) if list.len() == array_size => {
-            let mut float_vals = Vec::with_capacity(list.len());
-            for &val in list {
-                float_vals.push(expect_f32(val)?);
-            }
-            DataDirective::from(float_vals)
-        }
-        (
-            PrimitiveType::I32 | PrimitiveType::U32,
+            PrimitiveType::U8,
             0,
             Some(Located {
-                data: InitValue::List(list),
-                ..
+                data: InitValue::Primitive(PrimitiveValue::String(s)),
+                loc,
             }),
-        ) if list.len() == array_size => {
-            let mut int_vals = Vec::with_capacity(list.len());
-            for &val in list {
-                int_vals.push(expect_word(val)?);
-            }
-            DataDirective::from(int_vals)
-        }
+        ) if dims.len() == 1 => get_string_init(bank, *s, array_size)
+            .map(DataDirective::from)
+            .ok_or(ValidationError::InvalidStaticVar(*loc))?,
         (
-            PrimitiveType::U8,
+            prim,
             0,
             Some(Located {
                 data: InitValue::List(list),
-                ..
+                loc,
             }),
-        ) if list.len() == array_size => {
-            let mut byte_vals = Vec::with_capacity(list.len());
-            for &val in list {
-                byte_vals.push(expect_byte(val)?);
+        ) => {
+            let list = get_flat_init(dims, list).ok_or(ValidationError::InvalidStaticVar(*loc))?;
+            if prim == PrimitiveType::F32 {
+                let mut float_vals = Vec::with_capacity(list.len());
+                for val in list {
+                    float_vals.push(expect_f32(val)?);
+                }
+                DataDirective::from(float_vals)
+            } else {
+                let mut int_vals = Vec::with_capacity(list.len());
+                for val in list {
+                    int_vals.push(expect_int(val, prim)?);
+                }
+                match prim.size() {
+                    1 => DataDirective::from(
+                        int_vals.into_iter().map(|v| v as u8).collect::<Vec<_>>(),
+                    ),
+                    2 => DataDirective::from(
+                        int_vals.into_iter().map(|v| v as u16).collect::<Vec<_>>(),
+                    ),
+                    _ => DataDirective::from(int_vals),
+                }
             }
-            DataDirective::from(byte_vals)
-        }
-        (
-            PrimitiveType::U8,
-            0,
-            Some(Located {
-                data: InitValue::Primitive(PrimitiveValue::String(s)),
-                ..
-            }),
-        ) if bank.strings[*s].len() + 1 == array_size => {
-            DataDirective::Asciiz(bank.strings[*s].clone())
         }
         (_, _, Some(Located { loc, .. })) => {
             return Err(ValidationError::InvalidStaticVar(*loc));
@@ -183,12 +212,13 @@ pub fn codegen_init_static(
     b: &mut MipsBuilder,
     bank: &StringBank,
     static_var: &VarDecl,
+    label: String,
 ) -> ValidationResult<()> {
-    let mut static_def = DataDef::new(get_static_name(static_var.name.data));
+    let mut static_def = DataDef::new(label);
     let directive = match &static_var.variable {
         DeclType::Param(p) => init_static_param(bank, &p.data, &static_var.init)?,
-        DeclType::Array { array_type, size } => {
-            init_static_array(bank, &array_type.data, size.data, &static_var.init)?
+        DeclType::Array { array_type, dims } => {
+            init_static_array(bank, &array_type.data, dims, &static_var.init)?
         }
     };
     static_def.add_dir(directive);
@@ -198,6 +228,7 @@ pub fn codegen_init_static(
 
 pub fn codegen_init_var(
     b: &mut MipsBuilder,
+    bank: &StringBank,
     var_type: DeclType,
     init: &Located<InitValue>,
     stack_offset: i32,
@@ -206,13 +237,33 @@ pub fn codegen_init_var(
         (DeclType::Param(p), InitValue::Primitive(init_val)) => {
             stack_init_param(b, &p.data, Located::new(*init_val, init.loc), stack_offset)
         }
-        (DeclType::Array { array_type, size }, InitValue::List(list)) => stack_init_array(
-            b,
-            &array_type.data,
-            size.data,
-            Some(Located::new(list, init.loc)),
-            stack_offset,
-        ),
+        (DeclType::Array { array_type, dims }, InitValue::List(list)) => {
+            let list =
+                get_flat_init(&dims, list).ok_or(ValidationError::InvalidLocalInit(init.loc))?;
+            stack_init_array(
+                b,
+                &array_type.data,
+                Located::new(&list, init.loc),
+                stack_offset,
+            )
+        }
+        (DeclType::Array { array_type, dims }, InitValue::Primitive(PrimitiveValue::String(s)))
+            if dims.len() == 1
+                && array_type.data.param_type.data == PrimitiveType::U8
+                && array_type.data.indirection == 0 =>
+        {
+            let list = get_string_init(bank, *s, dims[0].data as usize)
+                .ok_or(ValidationError::InvalidLocalInit(init.loc))?
+                .into_iter()
+                .map(|byte| Located::new(PrimitiveValue::Byte(byte), init.loc))
+                .collect();
+            stack_init_array(
+                b,
+                &array_type.data,
+                Located::new(&list, init.loc),
+                stack_offset,
+            )
+        }
         _ => Err(ValidationError::InvalidLocalInit(init.loc)),
     }
 }
@@ -233,15 +284,10 @@ fn stack_init_param(
             b.const_f32(val, FloatRegister::F4);
             b.save_f32(FloatRegister::F4, address);
         }
-        PrimitiveType::I32 | PrimitiveType::U32 if var_type.indirection == 0 => {
-            let val = expect_word(init)?;
+        prim if var_type.indirection == 0 => {
+            let val = expect_int(init, prim)?;
             b.const_word(val, Register::T0);
-            b.save_word(Register::T0, address);
-        }
-        PrimitiveType::U8 if var_type.indirection == 0 => {
-            let val = expect_byte(init)?;
-            b.const_word(val as u32, Register::T0);
-            b.save_byte(Register::T0, address);
+            save_int(b, ExprType::from(prim), Register::T0, address);
         }
         PrimitiveType::U8 if var_type.indirection == 1 => {
             let str_id = expect_string(init)?;
@@ -260,13 +306,13 @@ fn init_array_const(
     b: &mut MipsBuilder,
     mut offset: i32,
     slot_size: i32,
-    data: &InitList,
+    data: &[Located<PrimitiveValue>],
     f: impl Fn(&mut MipsBuilder, MipsAddress, &Located<PrimitiveValue>) -> ValidationResult<()>,
 ) -> ValidationResult<()> {
     for val in data {
         let address = MipsAddress::RegisterOffset {
             register: Register::StackPtr,
-            offset: offset,
+            offset,
         };
         f(b, address, val)?;
         offset += slot_size;
@@ -274,43 +320,16 @@ fn init_array_const(
     Ok(())
 }
 
+/// Initializes a stack array from an already flattened initializer list
 fn stack_init_array(
     b: &mut MipsBuilder,
     arr_type: &ParamType,
-    arr_size: u32,
-    init: Option<Located<&InitList>>,
+    init: Located<&Vec<Located<PrimitiveValue>>>,
     stack_offset: i32,
 ) -> ValidationResult<()> {
-    let arr_size = arr_size as usize;
-    match (arr_type.param_type.data, arr_type.indirection, init) {
-        (_, _, None) => {}
-        (PrimitiveType::I32 | PrimitiveType::U32, 0, Some(Located { data, .. }))
-            if data.len() == arr_size =>
-        {
-            init_array_const(b, stack_offset, 4, data, |b, addr, val| {
-                expect_word(*val).map(|word| {
-                    b.const_word(word, Register::T0);
-                    b.save_word(Register::T0, addr);
-                })
-            })?;
-        }
-        (PrimitiveType::U8, 0, Some(Located { data, .. })) if data.len() == arr_size => {
-            init_array_const(b, stack_offset, 1, data, |b, addr, val| {
-                expect_byte(*val).map(|byte| {
-                    b.const_word(byte as u32, Register::T0);
-                    b.save_byte(Register::T0, addr);
-                })
-            })?;
-        }
-        (PrimitiveType::U8, 0, Some(Located { data, .. })) if data.len() == arr_size => {
-            init_array_const(b, stack_offset, 1, data, |b, addr, val| {
-                expect_byte(*val).map(|byte| {
-                    b.const_word(byte as u32, Register::T0);
-                    b.save_byte(Register::T0, addr);
-                })
-            })?;
-        }
-        (PrimitiveType::F32, 0, Some(Located { data, .. })) if data.len() == arr_size => {
+    let data = init.data;
+    match (arr_type.param_type.data, arr_type.indirection) {
+        (PrimitiveType::F32, 0) => {
             init_array_const(b, stack_offset, 4, data, |b, addr, val| {
                 expect_f32(*val).map(|float| {
                     b.const_f32(float, FloatRegister::F4);
@@ -318,7 +337,7 @@ fn stack_init_array(
                 })
             })?;
         }
-        (PrimitiveType::U8, 1, Some(Located { data, .. })) if data.len() == arr_size => {
+        (PrimitiveType::U8, 1) => {
             init_array_const(b, stack_offset, 4, data, |b, addr, val| {
                 expect_string(*val).map(|str_id| {
                     let str_name = get_str_name(str_id);
@@ -327,8 +346,17 @@ fn stack_init_array(
                 })
             })?;
         }
-        (_, _, Some(Located { loc, .. })) => {
-            return Err(ValidationError::InvalidLocalInit(loc));
+        (prim, 0) => {
+            let slot_size = prim.size() as i32;
+            init_array_const(b, stack_offset, slot_size, data, |b, addr, val| {
+                expect_int(*val, prim).map(|word| {
+                    b.const_word(word, Register::T0);
+                    save_int(b, ExprType::from(prim), Register::T0, addr);
+                })
+            })?;
+        }
+        _ => {
+            return Err(ValidationError::InvalidLocalInit(init.loc));
         }
     }
     Ok(())
@@ -0,0 +1,103 @@
+use deimos_ast::Location;
+use mips_builder::Origin;
+use std::fmt::Write;
+
+/// Source file that is compiled as part of a program
+#[derive(Debug, Clone)]
+struct SourceFile {
+    name: String,
+    text: String,
+    first_row: usize,
+}
+
+/// Files that make up a program in the order they're joined together.
+/// Used to trace locations in the joined source back to their files.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    files: Vec<SourceFile>,
+}
+
+impl Sources {
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) {
+        let first_row = match self.files.last() {
+            Some(file) => file.first_row + file.text.lines().count(),
+            None => 1,
+        };
+        let mut text = text.into();
+        // Every file starts on a new row
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        self.files.push(SourceFile {
+            name: name.into(),
+            text,
+            first_row,
+        });
+    }
+
+    /// Source of the whole program
+    pub fn text(&self) -> String {
+        self.files.iter().map(|f| f.text.as_str()).collect()
+    }
+
+    /// Index of the file a row of the joined source is in, and the row
+    /// within that file
+    fn find(&self, row: usize) -> Option<(usize, usize)> {
+        let index = self.files.iter().rposition(|f| f.first_row <= row)?;
+        Some((index, row - self.files[index].first_row + 1))
+    }
+
+    /// Comment placed before the instructions of a statement, giving
+    /// its location and source line
+    pub fn annotation(&self, loc: Location) -> String {
+        match self.find(loc.row) {
+            Some((index, row)) => {
+                let file = &self.files[index];
+                let line = file.text.lines().nth(row - 1).unwrap_or("");
+                format!("{}:{}:{}  {}", file.name, row, loc.col, line.trim())
+            }
+            None => loc.to_string(),
+        }
+    }
+
+    /// JSON map from lines of the generated assembly (counting from 1)
+    /// to locations in the source files
+    pub fn source_map(&self, origins: &[Option<Origin>]) -> String {
+        let names: Vec<_> = self.files.iter().map(|f| json_string(&f.name)).collect();
+        let mut json = String::new();
+        json.push_str("{\n  \"version\": 1,\n");
+        let _ = writeln!(json, "  \"files\": [{}],", names.join(", "));
+        json.push_str("  \"mappings\": [");
+        let mappings = origins.iter().enumerate().filter_map(|(line, origin)| {
+            let (row, col) = (*origin)?;
+            let (file, file_row) = self.find(row)?;
+            Some(format!(
+                "\n    {{\"line\": {}, \"file\": {}, \"row\": {}, \"col\": {}}}",
+                line + 1,
+                file,
+                file_row,
+                col
+            ))
+        });
+        json.push_str(&mappings.collect::<Vec<_>>().join(","));
+        json.push_str("\n  ]\n}\n");
+        json
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -1,5 +1,5 @@
-use deimos_ast::{BinaryOp, ControlBreak, Identifier, Location, Reg, UnaryOp};
-use mips_builder::{FloatRegister, Register};
+use deimos_ast::{BinaryOp, ControlBreak, Identifier, Location, Reg, TypeError, UnaryOp};
+use mips_builder::{EncodeError, FloatRegister, Register};
 use std::error::Error;
 use std::fmt::Display;
 
@@ -11,7 +11,6 @@ pub enum ValidationError {
     Redefinition(Location),
     UndefinedIdent(Location),
     NotAFunc(Location),
-    ShadowedFuncCall(Location),
     FuncInExpr(Location),
     InvalidMemVarType(Location),
     InvalidStaticVar(Location),
@@ -20,7 +19,6 @@ pub enum ValidationError {
     InternalIntReg(Register),
     InvalidUnary(UnaryOp, Location),
     InvalidBinary(BinaryOp, Location),
-    ArrayReference(Location),
     MemReference(Location),
     InvalidRValType(Location),
     InvalidLValType(Location),
@@ -29,6 +27,25 @@ pub enum ValidationError {
     InvalidArgType(Location, usize, ExprType),
     FloatInCondition(Location),
     InvalidControlFlow(Location, ControlBreak),
+    ConstReference(Location),
+    NonBoolCondition(Location),
+    ReservedLabel(Location, String),
+    NoBody,
+    BodyInLibrary,
+    InvalidFormat(Location),
+    FormatArgCount(Location),
+    FormatArgType(Location, usize),
+    UnknownMnemonic(Location, String),
+    InvalidAsmText(Location),
+    InvalidAsmOperand(Location),
+    AsmOperandLimit(Location),
+    InvalidSwitchValue(Location),
+    NonConstCase(Location),
+    DuplicateCase(Location),
+    UnknownType(Location),
+    InvalidLen(Location),
+    StackBudget(String, u32),
+    Encode(EncodeError),
 }
 impl Display for ValidationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -37,12 +54,9 @@ impl Display for ValidationError {
             Self::Redefinition(loc) => write!(f, "Redefined variable {}", loc),
             Self::UndefinedIdent(loc) => write!(f, "Undefined identifier {}", loc),
             Self::NotAFunc(loc) => write!(f, "Value is not a function {}", loc),
-            Self::ShadowedFuncCall(loc) => {
-                write!(f, "Call to function shadowed by local variable {}", loc)
-            }
             Self::FuncInExpr(loc) => write!(
                 f,
-                "Functions cannot be referenced inside expressions {}",
+                "Subs can only be used in expressions through their address {}",
                 loc
             ),
             Self::InvalidMemVarType(loc) => write!(f, "MemVar must have pointer type {}", loc),
@@ -76,16 +90,6 @@ impl Display for ValidationError {
             Self::InvalidBinary(bin_op, loc) => {
                 write!(f, "Invalid binary operation {:?} at {}", bin_op, loc)
             }
-            Self::ArrayReference(loc) => {
-                write!(
-                    f,
-                    concat![
-                        "Can't reference array at {}, array ident is pointer",
-                        "to area on stack. No reference value is stored during execution"
-                    ],
-                    loc
-                )
-            }
             Self::MemReference(loc) => {
                 write!(f, "Can't reference mem addr at {}", loc)
             }
@@ -124,9 +128,67 @@ impl Display for ValidationError {
             Self::InvalidControlFlow(loc, b) => {
                 write!(f, "Invalid {:?} at {}", b, loc)
             }
+            Self::NonBoolCondition(loc) => {
+                write!(f, "Condition at {} must be bool in strict mode", loc)
+            }
+            Self::ConstReference(loc) => {
+                write!(f, "Constant at {} can't be assigned to or referenced", loc)
+            }
+            Self::ReservedLabel(loc, name) => {
+                write!(
+                    f,
+                    "Public name '{}' at {} is reserved for a label",
+                    name, loc
+                )
+            }
+            Self::NoBody => write!(f, "No program body"),
+            Self::BodyInLibrary => write!(f, "Library units can't have a program body"),
+            Self::InvalidFormat(loc) => write!(f, "Invalid format string at {}", loc),
+            Self::FormatArgCount(loc) => write!(
+                f,
+                "Format string at {} doesn't match the number of arguments",
+                loc
+            ),
+            Self::FormatArgType(loc, index) => write!(
+                f,
+                "Argument {} at {} doesn't match its format specifier",
+                index, loc
+            ),
+            Self::UnknownMnemonic(loc, name) => {
+                write!(f, "Unknown instruction '{}' in asm at {}", name, loc)
+            }
+            Self::InvalidAsmText(loc) => write!(f, "Asm at {} isn't valid UTF-8", loc),
+            Self::InvalidAsmOperand(loc) => write!(f, "Invalid asm operand at {}", loc),
+            Self::AsmOperandLimit(loc) => {
+                write!(f, "No free register left for asm operand at {}", loc)
+            }
+            Self::InvalidSwitchValue(loc) => {
+                write!(f, "Switch value at {} must be an integer", loc)
+            }
+            Self::NonConstCase(loc) => write!(f, "Case value at {} isn't constant", loc),
+            Self::DuplicateCase(loc) => write!(f, "Duplicate case value at {}", loc),
+            Self::UnknownType(loc) => write!(f, "Unknown type at {}", loc),
+            Self::StackBudget(entry, budget) => write!(
+                f,
+                "Stack depth of {} can exceed the budget of {} bytes",
+                entry, budget
+            ),
+            Self::InvalidLen(loc) => write!(f, "len() at {} requires a fixed-size array", loc),
+            Self::Encode(err) => write!(f, "Can't encode program: {}", err),
         }
     }
 }
 
 impl Error for ValidationError {}
+
+impl From<TypeError> for ValidationError {
+    fn from(value: TypeError) -> Self {
+        match value {
+            TypeError::InvalidUnary(op, loc) => Self::InvalidUnary(op, loc),
+            TypeError::InvalidBinary(op, loc) => Self::InvalidBinary(op, loc),
+            TypeError::Mismatched(loc) => Self::MismatchedType(loc),
+        }
+    }
+}
+
 pub type ValidationResult<T> = Result<T, ValidationError>;
@@ -1,14 +1,17 @@
 use deimos_ast::*;
 use mips_builder::{FloatRegister, MipsBuilder, Register};
 
+use super::check::check_divisor;
 use super::temp::{AccessMode, ExprTemp, RegisterBank};
-use super::value::codegen_index_ref;
+use super::value::{codegen_index_ref, truncate_int};
 use crate::error::{ValidationError, ValidationResult};
 use crate::expr::unary::codegen_deref;
+use crate::scope::{ConstructCounter, Scope};
 
 /// Scaffold function for + and -
 /// Operators that take number operands but can also
 /// do pointer manipulation
+#[allow(clippy::too_many_arguments)]
 fn arith_ptr_num_expr(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
@@ -20,36 +23,34 @@ fn arith_ptr_num_expr(
     u32_fnc: impl FnOnce(&mut MipsBuilder, Register, Register),
     f32_fnc: impl FnOnce(&mut MipsBuilder, FloatRegister, FloatRegister),
 ) -> ValidationResult<ExprTemp> {
+    let result_type = left.computed_type;
     match (left.type_tuple(), right.type_tuple()) {
-        // word ptr + int
-        (
-            (PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::F32, 1..),
-            (PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::U8, 0),
-        ) => {
+        // ptr + int
+        ((ptr_type, indirection @ 1..), (index_type, 0)) if index_type.is_int() => {
+            // Pointers to pointers always step by a word
+            let elem_size = if indirection == 1 { ptr_type.size() } else { 4 };
             let ptr_reg = right.register.get_word()?;
             let int_reg = left.register.get_word()?;
             ptr_reg.use_reg(b, 0, AccessMode::Read, |b, r2| {
-                // Multiply right value by 4
-                b.shift_logical_left(r2, r2, 2);
+                // Multiply right value by the element size
+                if elem_size > 1 {
+                    b.shift_logical_left(r2, r2, elem_size.trailing_zeros());
+                }
                 // Regular addition
                 int_reg.use_reg(b, 1, AccessMode::ReadWrite, |b, r1| {
                     u32_fnc(b, r1, r2);
                 })
             });
         }
-        // Unsigned + unsigned or u8 ptr + int
-        (
-            (PrimitiveType::U8, 1..),
-            (PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::U8, 0),
-        )
-        | ((PrimitiveType::U32, 0), (PrimitiveType::U32, 0))
-        | ((PrimitiveType::U8, 0), (PrimitiveType::U8, 0)) => {
+        // Unsigned + unsigned
+        ((typ1, 0), (typ2, 0)) if typ1 == typ2 && typ1.is_int() && !typ1.is_signed() => {
             let left_reg = left.register.get_word()?;
             let right_reg = right.register.get_word()?;
             left_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r1| {
                 right_reg.use_reg(b, 1, AccessMode::Read, |b, r2| {
                     u32_fnc(b, r1, r2);
-                })
+                });
+                truncate_int(b, result_type, r1);
             });
         }
         // Floating addition
@@ -62,14 +63,15 @@ fn arith_ptr_num_expr(
                 })
             });
         }
-        // Addition
-        ((PrimitiveType::I32, 0), (PrimitiveType::I32, 0)) => {
+        // Signed addition
+        ((typ1, 0), (typ2, 0)) if typ1 == typ2 && typ1.is_signed() => {
             let left_reg = left.register.get_word()?;
             let right_reg = right.register.get_word()?;
             left_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r1| {
                 right_reg.use_reg(b, 1, AccessMode::Read, |b, r2| {
                     i32_fnc(b, r1, r2);
-                })
+                });
+                truncate_int(b, result_type, r1);
             });
         }
         _ => {
@@ -134,6 +136,7 @@ pub fn codegen_sub(
 
 /// Scaffold for * and /
 /// Operators that take numeric operands
+#[allow(clippy::too_many_arguments)]
 fn arith_num_expr(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
@@ -155,12 +158,14 @@ fn arith_num_expr(
             });
         }
         ((ty1, 0), (ty2, 0)) if ty1 == ty2 => {
+            let result_type = left.computed_type;
             let left_reg = left.register.get_word()?;
             let right_reg = right.register.get_word()?;
             left_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r1| {
                 right_reg.use_reg(b, 1, AccessMode::Read, |b, r2| {
                     word_fnc(b, r1, r2);
-                })
+                });
+                truncate_int(b, result_type, r1);
             });
         }
         _ => {
@@ -197,10 +202,13 @@ pub fn codegen_mult(
 pub fn codegen_div(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
     left: ExprTemp,
     right: ExprTemp,
     loc: Location,
 ) -> ValidationResult<ExprTemp> {
+    let unsigned = !left.type_tuple().0.is_signed();
     arith_num_expr(
         b,
         reg_bank,
@@ -209,7 +217,12 @@ pub fn codegen_div(
         BinaryOp::Div,
         loc,
         |b, r1, r2| {
-            b.div_i32(r1, r1, r2);
+            check_divisor(b, s, c, r2, loc);
+            if unsigned {
+                b.div_u32(r1, r1, r2);
+            } else {
+                b.div_i32(r1, r1, r2);
+            }
         },
         |b, f1, f2| {
             b.div_f32(f1, f1, f2);
@@ -217,8 +230,9 @@ pub fn codegen_div(
     )
 }
 
-/// Scaffold for %, 'and', and 'or'
-/// Operators that take exclusively int operands
+/// Scaffold for %, 'and', 'or' and 'xor'
+/// Operators that take exclusively int operands ('and', 'or' and 'xor'
+/// also take bools)
 fn arith_int_expr(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
@@ -229,9 +243,12 @@ fn arith_int_expr(
     word_fnc: impl FnOnce(&mut MipsBuilder, Register, Register),
 ) -> ValidationResult<ExprTemp> {
     match (left.type_tuple(), right.type_tuple()) {
-        ((PrimitiveType::I32, 0), (PrimitiveType::I32, 0))
-        | ((PrimitiveType::U32, 0), (PrimitiveType::U32, 0))
-        | ((PrimitiveType::U8, 0), (PrimitiveType::U8, 0)) => {
+        ((typ1, 0), (typ2, 0))
+            if typ1 == typ2
+                && (typ1.is_int()
+                    || typ1 == PrimitiveType::Bool
+                        && matches!(bin_op, BinaryOp::And | BinaryOp::Or | BinaryOp::Xor)) =>
+        {
             let left_reg = left.register.get_word()?;
             let right_reg = right.register.get_word()?;
             left_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r1| {
@@ -270,32 +287,83 @@ pub fn codgen_logic_or(
     })
 }
 
+pub fn codegen_xor(
+    b: &mut MipsBuilder,
+    reg_bank: &mut RegisterBank,
+    left: ExprTemp,
+    right: ExprTemp,
+    loc: Location,
+) -> ValidationResult<ExprTemp> {
+    arith_int_expr(b, reg_bank, left, right, BinaryOp::Xor, loc, |b, r1, r2| {
+        b.xor_i32(r1, r1, r2);
+    })
+}
+
+/// Shifts an int by a variable amount of any int type. Right shifts
+/// are arithmetic for signed values and logical for unsigned ones.
+pub fn codegen_shift(
+    b: &mut MipsBuilder,
+    reg_bank: &mut RegisterBank,
+    left: ExprTemp,
+    right: ExprTemp,
+    op: Located<BinaryOp>,
+) -> ValidationResult<ExprTemp> {
+    match (left.type_tuple(), right.type_tuple()) {
+        ((typ1, 0), (typ2, 0)) if typ1.is_int() && typ2.is_int() => {
+            let left_reg = left.register.get_word()?;
+            let right_reg = right.register.get_word()?;
+            left_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r1| {
+                right_reg.use_reg(b, 1, AccessMode::Read, |b, r2| match op.data {
+                    BinaryOp::ShiftLeft => b.shift_left(r1, r1, r2),
+                    _ if typ1.is_signed() => b.shift_right_i32(r1, r1, r2),
+                    _ => b.shift_right_u32(r1, r1, r2),
+                })
+            });
+            reg_bank.free_reg(right.register);
+            Ok(left)
+        }
+        _ => Err(ValidationError::InvalidBinary(op.data, op.loc)),
+    }
+}
+
 pub fn codegen_mod(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
     left: ExprTemp,
     right: ExprTemp,
     loc: Location,
 ) -> ValidationResult<ExprTemp> {
+    let unsigned = !left.type_tuple().0.is_signed();
     arith_int_expr(b, reg_bank, left, right, BinaryOp::Mod, loc, |b, r1, r2| {
-        b.mod_i32(r1, r1, r2);
+        check_divisor(b, s, c, r2, loc);
+        if unsigned {
+            b.mod_u32(r1, r1, r2);
+        } else {
+            b.mod_i32(r1, r1, r2);
+        }
     })
 }
 
 pub fn codegen_index_access(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
     left: ExprTemp,
     right: ExprTemp,
     loc: Location,
 ) -> ValidationResult<ExprTemp> {
     let reference_expr = codegen_index_ref(b, reg_bank, left, right, loc)?;
-    codegen_deref(b, reg_bank, reference_expr, loc)
+    codegen_deref(b, reg_bank, s, c, reference_expr, loc)
 }
 
 pub fn codegen_binary(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
     left: ExprTemp,
     right: ExprTemp,
     op: Located<BinaryOp>,
@@ -305,16 +373,18 @@ pub fn codegen_binary(
         BinaryOp::Add => codegen_add(b, reg_bank, left, right, op.loc),
         BinaryOp::Sub => codegen_sub(b, reg_bank, left, right, op.loc),
         BinaryOp::Mult => codegen_mult(b, reg_bank, left, right, op.loc),
-        BinaryOp::Div => codegen_div(b, reg_bank, left, right, op.loc),
-        BinaryOp::Mod => codegen_mod(b, reg_bank, left, right, op.loc),
+        BinaryOp::Div => codegen_div(b, reg_bank, s, c, left, right, op.loc),
+        BinaryOp::Mod => codegen_mod(b, reg_bank, s, c, left, right, op.loc),
         BinaryOp::And => codgen_logic_and(b, reg_bank, left, right, op.loc),
         BinaryOp::Or => codgen_logic_or(b, reg_bank, left, right, op.loc),
+        BinaryOp::Xor => codegen_xor(b, reg_bank, left, right, op.loc),
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => codegen_shift(b, reg_bank, left, right, op),
         BinaryOp::Equal => codgen_logic_eq(b, reg_bank, left, right, op.loc),
         BinaryOp::NotEq => codgen_logic_not_eq(b, reg_bank, left, right, op.loc),
         BinaryOp::LessThan => codgen_logic_less_than(b, reg_bank, left, right, op.loc),
         BinaryOp::LessThanEq => codgen_logic_less_than_eq(b, reg_bank, left, right, op.loc),
         BinaryOp::GreaterThan => codgen_logic_greater_than(b, reg_bank, left, right, op.loc),
         BinaryOp::GreaterThanEq => codgen_logic_greater_than_eq(b, reg_bank, left, right, op.loc),
-        BinaryOp::IndexAccess => codegen_index_access(b, reg_bank, left, right, op.loc),
+        BinaryOp::IndexAccess => codegen_index_access(b, reg_bank, s, c, left, right, op.loc),
     }
 }
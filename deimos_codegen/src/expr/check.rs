@@ -0,0 +1,89 @@
+use deimos_ast::*;
+use mips_builder::{MipsBuilder, Register};
+
+use super::temp::EXPR_TEMP;
+use crate::names::{get_check_pass, PANIC_BOUNDS, PANIC_DIV_ZERO, PANIC_NULL};
+use crate::scope::{ConstructCounter, Scope};
+
+/// Failures caught when runtime checks are on
+#[derive(Debug, Clone, Copy)]
+enum RuntimeCheck {
+    Bounds,
+    DivZero,
+    Null,
+}
+
+impl RuntimeCheck {
+    fn panic_label(self) -> &'static str {
+        match self {
+            Self::Bounds => PANIC_BOUNDS,
+            Self::DivZero => PANIC_DIV_ZERO,
+            Self::Null => PANIC_NULL,
+        }
+    }
+}
+
+/// Calls the panic helper for a check unless `pass_branch` branches
+/// to the label it's given
+fn codegen_check(
+    b: &mut MipsBuilder,
+    c: &mut ConstructCounter,
+    check: RuntimeCheck,
+    loc: Location,
+    pass_branch: impl FnOnce(&mut MipsBuilder, &str),
+) {
+    let pass_lbl = get_check_pass(&c.new_check());
+    pass_branch(b, &pass_lbl);
+    b.const_word(loc.row as u32, Register::A0);
+    b.const_word(loc.col as u32, Register::A1);
+    b.jump_and_link(check.panic_label());
+    b.new_block(pass_lbl);
+}
+
+/// Fails if the index in `r` isn't below `len`. Negative indices are
+/// caught by the unsigned comparison.
+pub fn check_bounds(
+    b: &mut MipsBuilder,
+    s: &Scope,
+    c: &mut ConstructCounter,
+    r: Register,
+    len: u32,
+    loc: Location,
+) {
+    if s.options().runtime_checks {
+        codegen_check(b, c, RuntimeCheck::Bounds, loc, |b, pass_lbl| {
+            b.const_word(len, EXPR_TEMP[1]);
+            b.branch_lt_u32(r, EXPR_TEMP[1], pass_lbl);
+        });
+    }
+}
+
+/// Fails if the divisor in `r` is zero
+pub fn check_divisor(
+    b: &mut MipsBuilder,
+    s: &Scope,
+    c: &mut ConstructCounter,
+    r: Register,
+    loc: Location,
+) {
+    if s.options().runtime_checks {
+        codegen_check(b, c, RuntimeCheck::DivZero, loc, |b, pass_lbl| {
+            b.branch_not_eq_zero(r, pass_lbl);
+        });
+    }
+}
+
+/// Fails if the pointer in `r` is null
+pub fn check_null(
+    b: &mut MipsBuilder,
+    s: &Scope,
+    c: &mut ConstructCounter,
+    r: Register,
+    loc: Location,
+) {
+    if s.options().runtime_checks {
+        codegen_check(b, c, RuntimeCheck::Null, loc, |b, pass_lbl| {
+            b.branch_not_eq_zero(r, pass_lbl);
+        });
+    }
+}
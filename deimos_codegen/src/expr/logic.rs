@@ -29,7 +29,7 @@ pub fn codegen_logic_not(
             });
 
             let cond_reg = reg_bank.get_register();
-            get_condition_bit_inv(b, cond_reg);
+            get_condition_bit(b, cond_reg);
 
             reg_bank.free_reg(expr.register);
             cond_reg
@@ -37,15 +37,16 @@ pub fn codegen_logic_not(
         _ => {
             let reg = expr.register.get_word()?;
             reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r| {
-                b.set_neq(r, r, Register::Zero);
+                b.set_eq(r, r, Register::Zero);
             });
             reg
         }
     };
-    Ok(ExprTemp::new(reg, PrimitiveType::I32))
+    Ok(ExprTemp::new(reg, PrimitiveType::Bool))
 }
 
 /// Scaffold function for logic binary ops
+#[allow(clippy::too_many_arguments)]
 fn codegen_logic(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
@@ -87,7 +88,7 @@ fn codegen_logic(
             return Err(ValidationError::InvalidBinary(op, loc));
         }
     };
-    return Ok(ExprTemp::new(reg, PrimitiveType::I32));
+    Ok(ExprTemp::new(reg, PrimitiveType::Bool))
 }
 
 pub fn codgen_logic_eq(
@@ -1,15 +1,19 @@
 use crate::error::*;
-use crate::{expr::binary::codegen_binary, scope::Scope};
+use crate::expr::binary::codegen_binary;
+use crate::scope::{ConstructCounter, Scope};
 use deimos_ast::*;
 use mips_builder::MipsBuilder;
 
 mod binary;
+mod check;
 mod logic;
 pub mod print;
+pub mod printf;
 pub mod rvalue;
 pub mod temp;
+mod ternary;
 mod unary;
-mod value;
+pub mod value;
 
 pub use self::temp::{ExprTemp, RegisterBank};
 
@@ -18,19 +22,67 @@ pub fn codegen_expr(
     expr: &Expression,
     s: &Scope,
     reg_bank: &mut RegisterBank,
+    c: &mut ConstructCounter,
 ) -> ValidationResult<ExprTemp> {
-    match expr {
-        Expression::Unary { operand, op } => unary::codegen_unary(b, operand, s, reg_bank, *op),
+    let temp = match expr {
+        Expression::Unary { operand, op } => unary::codegen_unary(b, operand, s, reg_bank, c, *op),
+        Expression::Binary {
+            left,
+            right,
+            op:
+                Located {
+                    data: BinaryOp::IndexAccess,
+                    loc,
+                },
+        } => value::codegen_index(b, reg_bank, s, c, left, right, *loc),
         Expression::Binary { left, right, op } => {
-            let left_expr = codegen_expr(b, left, s, reg_bank)?;
-            let right_expr = codegen_expr(b, right, s, reg_bank)?;
-            codegen_binary(b, reg_bank, left_expr, right_expr, *op)
+            let left_expr = codegen_expr(b, left, s, reg_bank, c)?;
+            let right_expr = codegen_expr(b, right, s, reg_bank, c)?;
+            codegen_binary(b, reg_bank, s, c, left_expr, right_expr, *op)
         }
         Expression::Identifier(ident) => value::codegen_ident(b, reg_bank, s, *ident),
+        Expression::Len { array, loc } => {
+            let len = match type_of(array, s)? {
+                DeclType::Array { dims, .. } => dims[0].data,
+                DeclType::Param(_) => return Err(ValidationError::InvalidLen(*loc)),
+            };
+            Ok(value::codegen_const(
+                b,
+                reg_bank,
+                PrimitiveValue::Unsigned(len),
+            ))
+        }
+        Expression::SizeOf { operand, .. } => {
+            let size = match operand {
+                SizeOperand::Type(typ) => {
+                    s.check_type(&typ.data)?;
+                    typ.data.size()
+                }
+                SizeOperand::Expr(expr) => value::get_expr_size(s, expr)?,
+            };
+            Ok(value::codegen_const(
+                b,
+                reg_bank,
+                PrimitiveValue::Unsigned(size),
+            ))
+        }
+        Expression::AlignOf { typ, .. } => {
+            s.check_type(&typ.data)?;
+            let align = PrimitiveValue::Unsigned(typ.data.align());
+            Ok(value::codegen_const(b, reg_bank, align))
+        }
         Expression::Cast { value, cast_type } => {
-            let expr_val: ExprTemp = codegen_expr(b, &value, s, reg_bank)?;
+            s.check_type(cast_type)?;
+            let expr_val: ExprTemp = codegen_expr(b, value, s, reg_bank, c)?;
             value::codegen_cast(b, reg_bank, expr_val, cast_type.clone().into())
         }
+        Expression::Ternary {
+            condition,
+            then_value,
+            else_value,
+        } => ternary::codegen_ternary(b, s, reg_bank, c, condition, then_value, else_value),
         Expression::Primitive(p) => Ok(value::codegen_const(b, reg_bank, p.data)),
-    }
+    };
+    c.note_stack_below(s.stack_shift() + reg_bank.spill_size());
+    temp
 }
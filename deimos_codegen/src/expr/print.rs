@@ -1,20 +1,21 @@
 use super::ExprTemp;
 use crate::error::*;
+use crate::names::PRINT_BOOL;
 
 use deimos_ast::*;
 use mips_builder::{FloatRegister, MipsBuilder, Register};
 
-pub fn codegen_print_val(b: &mut MipsBuilder, expr_val: ExprTemp) -> ValidationResult<()> {
+pub fn codegen_print_val(
+    b: &mut MipsBuilder,
+    expr_val: ExprTemp,
+    loc: Location,
+) -> ValidationResult<()> {
     // Load value into correct register
     match expr_val.type_tuple() {
         (PrimitiveType::F32, 0) => {
             let expr_reg = expr_val.register.get_float()?;
             expr_reg.load_to(b, FloatRegister::F12);
         }
-        (PrimitiveType::U8, 0) => {
-            let expr_reg = expr_val.register.get_word()?;
-            expr_reg.load_byte_to(b, Register::A0);
-        }
         _ => {
             let expr_reg = expr_val.register.get_word()?;
             expr_reg.load_to(b, Register::A0);
@@ -25,10 +26,21 @@ pub fn codegen_print_val(b: &mut MipsBuilder, expr_val: ExprTemp) -> ValidationR
     let opcode = match expr_val.type_tuple() {
         (PrimitiveType::F32, 0) => 2,
         (PrimitiveType::U8, 0) => 11,
+        (PrimitiveType::Bool, 0) => {
+            // Bools are printed by name
+            b.jump_and_link(PRINT_BOOL);
+            return Ok(());
+        }
         (PrimitiveType::U8, 1) => 4,
-        (PrimitiveType::U32, 0) => 36,
-        (PrimitiveType::I32, 0) => 1,
+        (PrimitiveType::U32 | PrimitiveType::U16, 0) => 36,
+        // Enums print their value
+        (
+            PrimitiveType::I32 | PrimitiveType::I16 | PrimitiveType::I8 | PrimitiveType::Enum(_),
+            0,
+        ) => 1,
         (_, 1..) => 34,
+        // Subs only have values through pointers
+        (PrimitiveType::Sub(_), 0) => return Err(ValidationError::MismatchedType(loc)),
     };
     b.add_syscall(opcode);
 
@@ -0,0 +1,241 @@
+use super::{codegen_expr, ExprTemp, RegisterBank};
+use crate::error::*;
+use crate::internal::*;
+use crate::names::*;
+use crate::scope::{ConstructCounter, Scope};
+
+use deimos_ast::*;
+use mips_builder::{FloatRegister, MipsAddress, MipsBuilder, Register};
+
+use super::temp::{AccessMode, EXPR_TEMP};
+
+/// Value conversion requested by a format specifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Conversion {
+    Signed,
+    Unsigned,
+    Hex,
+    Float,
+    Char,
+    Str,
+}
+
+#[derive(Debug)]
+struct FormatSpec {
+    conversion: Conversion,
+    width: u32,
+    precision: Option<u32>,
+    flags: u16,
+}
+
+/// Piece of a format string. Text is referenced by its byte range in
+/// the format string so it can be written straight from the string bank
+#[derive(Debug)]
+enum FormatPiece {
+    Text { offset: usize, len: usize },
+    Spec(FormatSpec),
+}
+
+/// Reads a run of digits as a number
+fn parse_digits(bytes: &[u8], index: &mut usize) -> Option<u32> {
+    let start = *index;
+    let mut val = 0u32;
+    while let Some(d) = bytes.get(*index).filter(|c| c.is_ascii_digit()) {
+        val = val.saturating_mul(10).saturating_add((d - b'0') as u32);
+        *index += 1;
+    }
+    (*index > start).then_some(val)
+}
+
+/// Splits a format string into text and `%[-0][width][.precision]conv` specifiers
+fn parse_format(bytes: &[u8], loc: Location) -> ValidationResult<Vec<FormatPiece>> {
+    let invalid = ValidationError::InvalidFormat(loc);
+    let mut pieces = Vec::new();
+    let mut text_start = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] != b'%' {
+            index += 1;
+            continue;
+        }
+        if index > text_start {
+            pieces.push(FormatPiece::Text {
+                offset: text_start,
+                len: index - text_start,
+            });
+        }
+        index += 1;
+
+        if bytes.get(index) == Some(&b'%') {
+            text_start = index;
+            index += 1;
+            continue;
+        }
+
+        let mut flags = 0;
+        loop {
+            match bytes.get(index) {
+                Some(b'-') => flags |= FMT_LEFT,
+                Some(b'0') => flags |= FMT_ZERO_PAD,
+                _ => break,
+            }
+            index += 1;
+        }
+        let width = parse_digits(bytes, &mut index).unwrap_or(0);
+        let precision = if bytes.get(index) == Some(&b'.') {
+            index += 1;
+            Some(parse_digits(bytes, &mut index).unwrap_or(0))
+        } else {
+            None
+        };
+
+        let conversion = match bytes.get(index) {
+            Some(b'd' | b'i') => Conversion::Signed,
+            Some(b'u') => Conversion::Unsigned,
+            Some(b'x') => Conversion::Hex,
+            Some(b'X') => {
+                flags |= FMT_UPPER;
+                Conversion::Hex
+            }
+            Some(b'f') => Conversion::Float,
+            Some(b'c') => Conversion::Char,
+            Some(b's') => Conversion::Str,
+            _ => return Err(invalid),
+        };
+        index += 1;
+        text_start = index;
+
+        // Zero padding only makes sense for right aligned numbers
+        if flags & FMT_LEFT != 0 {
+            flags &= !FMT_ZERO_PAD;
+        }
+        let is_text = matches!(conversion, Conversion::Char | Conversion::Str);
+        if width > FMT_MAX_WIDTH
+            || (is_text && flags & FMT_ZERO_PAD != 0)
+            || (precision.is_some() && conversion != Conversion::Float)
+            || precision.is_some_and(|p| p > FMT_MAX_PRECISION)
+        {
+            return Err(invalid);
+        }
+
+        pieces.push(FormatPiece::Spec(FormatSpec {
+            conversion,
+            width,
+            precision,
+            flags,
+        }));
+    }
+
+    if bytes.len() > text_start {
+        pieces.push(FormatPiece::Text {
+            offset: text_start,
+            len: bytes.len() - text_start,
+        });
+    }
+    Ok(pieces)
+}
+
+/// Passes a value to the runtime helper for its specifier
+fn codegen_format_arg(
+    b: &mut MipsBuilder,
+    spec: &FormatSpec,
+    expr: ExprTemp,
+    loc: Location,
+    index: usize,
+) -> ValidationResult<()> {
+    let helper = match (spec.conversion, expr.type_tuple()) {
+        (Conversion::Signed, (t, 0)) if t.is_int() && t.is_signed() => FMT_INT,
+        (Conversion::Unsigned, (t, 0)) if t.is_int() && !t.is_signed() => FMT_UINT,
+        (Conversion::Hex, (t, 0)) if t.is_int() => FMT_UINT,
+        (Conversion::Hex, (_, 1..)) => FMT_UINT,
+        (Conversion::Float, (PrimitiveType::F32, 0)) => FMT_FLOAT,
+        (Conversion::Char, (PrimitiveType::U8, 0)) => FMT_CHAR,
+        (Conversion::Str, (PrimitiveType::U8, 1)) => FMT_STR,
+        _ => return Err(ValidationError::FormatArgType(loc, index)),
+    };
+
+    let arg = match spec.conversion {
+        Conversion::Float => {
+            expr.register.get_float()?.load_to(b, FloatRegister::F12);
+            spec.precision.unwrap_or(6)
+        }
+        Conversion::Hex => {
+            expr.register.get_word()?.load_to(b, Register::A0);
+            16
+        }
+        _ => {
+            expr.register.get_word()?.load_to(b, Register::A0);
+            10
+        }
+    };
+    b.const_word(arg, Register::A1);
+    b.const_word(spec.width, Register::A2);
+    b.const_word(spec.flags as u32, Register::A3);
+    b.jump_and_link(helper);
+    Ok(())
+}
+
+/// Codegen for printf and fprintf. Literal text and each formatted
+/// specifier are written through the prelude's write_file
+pub fn codegen_printf(
+    b: &mut MipsBuilder,
+    printf: &Printf,
+    s: &Scope,
+    strs: &StringBank,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
+    let fmt_loc = printf.format.loc;
+    let pieces = parse_format(&strs.strings[printf.format.data], fmt_loc)?;
+    let spec_count = pieces
+        .iter()
+        .filter(|p| matches!(p, FormatPiece::Spec(_)))
+        .count();
+    if spec_count != printf.args.len() {
+        return Err(ValidationError::FormatArgCount(fmt_loc));
+    }
+
+    let mut bank = RegisterBank::default();
+    if let Some(fd) = &printf.fd {
+        let expr = codegen_expr(b, fd, s, &mut bank, c)?;
+        if !matches!(
+            expr.type_tuple(),
+            (PrimitiveType::U32 | PrimitiveType::I32, 0)
+        ) {
+            return Err(ValidationError::MismatchedType(fd.get_loc()));
+        }
+        let reg = expr.register.get_word()?;
+        reg.use_reg(b, 0, AccessMode::Read, |b, r| {
+            b.save_word(r, FMT_FD);
+        });
+        bank.clear();
+    } else {
+        b.const_word(1, EXPR_TEMP[0]);
+        b.save_word(EXPR_TEMP[0], FMT_FD);
+    }
+
+    let str_name = get_str_name(printf.format.data);
+    let mut args = printf.args.iter().enumerate();
+    for piece in &pieces {
+        match piece {
+            FormatPiece::Text { offset, len } => {
+                b.load_addr(
+                    Register::A1,
+                    MipsAddress::LabelOffset {
+                        label: str_name.as_str().into(),
+                        offset: *offset as i32,
+                    },
+                );
+                b.const_word(*len as u32, Register::A2);
+                b.jump_and_link(FMT_WRITE);
+            }
+            FormatPiece::Spec(spec) => {
+                let (index, arg) = args.next().expect("Format arg count already checked");
+                let expr = codegen_expr(b, arg, s, &mut bank, c)?;
+                codegen_format_arg(b, spec, expr, arg.get_loc(), index)?;
+                bank.clear();
+            }
+        }
+    }
+    Ok(())
+}
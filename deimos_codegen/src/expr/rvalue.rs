@@ -1,45 +1,86 @@
+use super::binary::codegen_binary;
+use super::check::check_null;
 use super::temp::{AccessMode, RegisterBank};
-use super::value::{codegen_ident_ref, codegen_index_ref};
+use super::unary::codegen_deref;
+use super::value::{
+    codegen_array_index_ref, codegen_const, codegen_ident_ref, get_array_dims, save_int,
+};
 use super::{codegen_expr, ExprTemp};
 use crate::error::*;
-use crate::scope::Scope;
+use crate::scope::{ConstructCounter, Scope};
 use deimos_ast::*;
 use mips_builder::MipsBuilder;
 
+/// Gets the address an assignment stores to
 fn codegen_rval(
     b: &mut MipsBuilder,
     scope: &Scope,
     reg_bank: &mut RegisterBank,
+    c: &mut ConstructCounter,
     rval: &Located<RValue>,
 ) -> ValidationResult<ExprTemp> {
-    match &rval.data {
+    let addr = match &rval.data {
         RValue::Identifier(ident) => codegen_ident_ref(b, reg_bank, scope, *ident),
-        RValue::Deref(expr) => codegen_expr(b, expr, scope, reg_bank),
+        RValue::Deref(expr) => {
+            let addr = codegen_expr(b, expr, scope, reg_bank, c)?;
+            if addr.computed_type.indirection > 0 {
+                addr.register
+                    .get_word()?
+                    .use_reg(b, 0, AccessMode::Read, |b, r| {
+                        check_null(b, scope, c, r, rval.loc);
+                    });
+            }
+            Ok(addr)
+        }
         RValue::Index { array, value } => {
-            let value_expr = codegen_expr(b, array, scope, reg_bank)?;
-            let index_expr = codegen_expr(b, value, scope, reg_bank)?;
-            codegen_index_ref(b, reg_bank, value_expr, index_expr, rval.loc)
+            // Whole rows of a multi-dimensional array can't be assigned to
+            if get_array_dims(scope, array).len() > 1 {
+                return Err(ValidationError::InvalidRValType(rval.loc));
+            }
+            codegen_array_index_ref(b, reg_bank, scope, c, array, value, rval.loc)
         }
+    }?;
+    if addr.computed_type.indirection == 0 {
+        return Err(ValidationError::InvalidRValType(rval.loc));
     }
+    Ok(addr)
 }
 
-pub fn codegen_assignment(
+/// Loads the value stored at the address of an rvalue, keeping the
+/// address for the store that follows
+fn codegen_load_rval(
     b: &mut MipsBuilder,
+    reg_bank: &mut RegisterBank,
     scope: &Scope,
-    assignment: &Assignment,
-) -> ValidationResult<()> {
-    let mut bank = RegisterBank::default();
-    let expr_val = codegen_expr(b, &assignment.lvalue, scope, &mut bank)?;
-    let rval = codegen_rval(b, scope, &mut bank, &assignment.rvalue)?;
-
-    let rtype = rval.computed_type;
-    if rtype.indirection == 0 {
-        return Err(ValidationError::InvalidRValType(assignment.rvalue.loc));
-    }
+    c: &mut ConstructCounter,
+    rval: &ExprTemp,
+    loc: Location,
+) -> ValidationResult<ExprTemp> {
+    let addr_reg = rval.register.get_word()?;
+    let copy_reg = reg_bank.get_register();
+    copy_reg.use_reg(b, 0, AccessMode::Write, |b, r| {
+        addr_reg.load_to(b, r);
+    });
+    codegen_deref(
+        b,
+        reg_bank,
+        scope,
+        c,
+        ExprTemp::new(copy_reg, rval.computed_type),
+        loc,
+    )
+}
 
+/// Stores a value at the address of an rvalue
+fn codegen_store(
+    b: &mut MipsBuilder,
+    expr_val: ExprTemp,
+    rval: ExprTemp,
+    loc: Location,
+) -> ValidationResult<()> {
     let ltype = expr_val.computed_type;
-    if rtype.deref_type() != ltype {
-        return Err(ValidationError::InvalidLValType(assignment.rvalue.loc));
+    if rval.computed_type.deref_type() != ltype {
+        return Err(ValidationError::InvalidLValType(loc));
     }
 
     match rval.type_tuple() {
@@ -52,21 +93,12 @@ pub fn codegen_assignment(
                 })
             });
         }
-        (PrimitiveType::U8, 1) => {
-            let rval_reg = rval.register.get_word()?;
-            let expr_reg = expr_val.register.get_word()?;
-            rval_reg.use_reg(b, 0, AccessMode::Read, |b, fr| {
-                expr_reg.use_reg_byte(b, 1, AccessMode::Read, |b, fl| {
-                    b.save_byte(fl, fr);
-                })
-            });
-        }
         _ => {
             let rval_reg = rval.register.get_word()?;
             let expr_reg = expr_val.register.get_word()?;
             rval_reg.use_reg(b, 0, AccessMode::Read, |b, fr| {
                 expr_reg.use_reg(b, 1, AccessMode::Read, |b, fl| {
-                    b.save_word(fl, fr);
+                    save_int(b, ltype, fl, fr);
                 })
             });
         }
@@ -74,3 +106,55 @@ pub fn codegen_assignment(
 
     Ok(())
 }
+
+/// Codegen for assignments. Compound assignments compute the address
+/// of the rvalue once and use it for both the load and the store.
+pub fn codegen_assignment(
+    b: &mut MipsBuilder,
+    scope: &Scope,
+    assignment: &Assignment,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
+    let mut bank = RegisterBank::default();
+    let loc = assignment.rvalue.loc;
+    let (expr_val, rval) = match assignment.op {
+        None => {
+            let expr_val = codegen_expr(b, &assignment.lvalue, scope, &mut bank, c)?;
+            let rval = codegen_rval(b, scope, &mut bank, c, &assignment.rvalue)?;
+            (expr_val, rval)
+        }
+        Some(op) => {
+            let rval = codegen_rval(b, scope, &mut bank, c, &assignment.rvalue)?;
+            let current = codegen_load_rval(b, &mut bank, scope, c, &rval, loc)?;
+            let operand = codegen_expr(b, &assignment.lvalue, scope, &mut bank, c)?;
+            let expr_val = codegen_binary(b, &mut bank, scope, c, current, operand, op)?;
+            (expr_val, rval)
+        }
+    };
+    c.note_stack_below(scope.stack_shift() + bank.spill_size());
+    codegen_store(b, expr_val, rval, loc)
+}
+
+/// Codegen for `x++` and `x--`. Pointers step by one element.
+pub fn codegen_increment(
+    b: &mut MipsBuilder,
+    scope: &Scope,
+    increment: &Increment,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
+    let mut bank = RegisterBank::default();
+    let loc = increment.rvalue.loc;
+    let rval = codegen_rval(b, scope, &mut bank, c, &increment.rvalue)?;
+    let current = codegen_load_rval(b, &mut bank, scope, c, &rval, loc)?;
+    let one = match current.type_tuple() {
+        (PrimitiveType::F32, 0) => codegen_const(b, &mut bank, PrimitiveValue::Float(1.0)),
+        (typ, 0) if typ.is_int() => {
+            let one = codegen_const(b, &mut bank, PrimitiveValue::Unsigned(1));
+            ExprTemp::new(one.register, current.computed_type)
+        }
+        _ => codegen_const(b, &mut bank, PrimitiveValue::Int(1)),
+    };
+    let expr_val = codegen_binary(b, &mut bank, scope, c, current, one, increment.op)?;
+    c.note_stack_below(scope.stack_shift() + bank.spill_size());
+    codegen_store(b, expr_val, rval, loc)
+}
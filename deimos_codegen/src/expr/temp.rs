@@ -121,27 +121,6 @@ impl ExprRegularRegister {
         )
     }
 
-    pub fn use_reg_byte<R>(
-        self,
-        b: &mut MipsBuilder,
-        temp_index: usize,
-        access_mode: AccessMode,
-        fnc: impl FnOnce(&mut MipsBuilder, Register) -> R,
-    ) -> R {
-        self.use_val(
-            b,
-            EXPR_TEMP[temp_index],
-            access_mode,
-            |b, temp_reg, v| {
-                b.load_byte(temp_reg, v);
-            },
-            fnc,
-            |b, temp_reg, v| {
-                b.save_byte(temp_reg, v);
-            },
-        )
-    }
-
     pub fn load_to(&self, b: &mut MipsBuilder, r: Register) {
         match self {
             OrVirtual::Register(old_r) => b.mov(r, *old_r),
@@ -155,13 +134,6 @@ impl ExprRegularRegister {
             OrVirtual::Virtual(v) => b.save_word(old_r, *v),
         }
     }
-
-    pub fn load_byte_to(&self, b: &mut MipsBuilder, r: Register) {
-        match self {
-            OrVirtual::Register(old_r) => b.mov(r, *old_r),
-            OrVirtual::Virtual(v) => b.load_byte(r, *v),
-        }
-    }
 }
 
 impl ExprFloatRegister {
@@ -250,6 +222,14 @@ impl ExprType {
             indirection: self.indirection - 1,
         }
     }
+
+    /// Size of a value of this type in bytes
+    pub fn size(self) -> u32 {
+        match self.indirection {
+            0 => self.base.size(),
+            _ => PTR_SIZE,
+        }
+    }
 }
 
 impl From<ParamType> for ExprType {
@@ -265,10 +245,7 @@ impl From<DeclType> for ExprType {
     fn from(value: DeclType) -> Self {
         match value {
             DeclType::Param(p) => ExprType::from(p.data),
-            DeclType::Array {
-                array_type,
-                size: _,
-            } => {
+            DeclType::Array { array_type, .. } => {
                 let mut param_type = array_type.data.clone();
                 param_type.indirection += 1;
                 ExprType::from(param_type)
@@ -341,6 +318,8 @@ pub struct RegisterBank {
     registers: HashSet<Register>,
     float_regs: HashSet<FloatRegister>,
     virtual_reg: HashSet<usize>,
+    /// Most bytes of virtual registers in use below the stack pointer
+    spilled: u32,
 }
 
 impl RegisterBank {
@@ -356,9 +335,15 @@ impl RegisterBank {
             offset += 1;
         }
         self.virtual_reg.insert(offset);
+        self.spilled = self.spilled.max((offset as u32 + 1) * 4);
         VirtualRegister { offset }
     }
 
+    /// Stack space the virtual registers have needed so far
+    pub fn spill_size(&self) -> u32 {
+        self.spilled
+    }
+
     pub fn get_register(&mut self) -> ExprRegularRegister {
         EXPR_REGISTERS
             .iter()
@@ -0,0 +1,55 @@
+use deimos_ast::*;
+use mips_builder::MipsBuilder;
+
+use super::codegen_expr;
+use super::temp::{AccessMode, ExprTemp, RegisterBank};
+use crate::error::{ValidationError, ValidationResult};
+use crate::names::{get_ternary_else, get_ternary_end};
+use crate::scope::{ConstructCounter, Scope};
+use crate::stmt::codegen_condition;
+
+/// Codegen for conditional expressions. Only the selected value is
+/// evaluated, and both values end up in the register of the first one.
+pub fn codegen_ternary(
+    b: &mut MipsBuilder,
+    s: &Scope,
+    reg_bank: &mut RegisterBank,
+    c: &mut ConstructCounter,
+    condition: &Expression,
+    then_value: &Expression,
+    else_value: &Expression,
+) -> ValidationResult<ExprTemp> {
+    let ternary_id = c.new_ternary();
+    let else_lbl = get_ternary_else(&ternary_id);
+    let end_lbl = get_ternary_end(&ternary_id);
+
+    codegen_condition(b, condition, s, reg_bank, c, &else_lbl)?;
+    let then_expr = codegen_expr(b, then_value, s, reg_bank, c)?;
+    b.branch(&end_lbl);
+
+    b.new_block(else_lbl);
+    let else_expr = codegen_expr(b, else_value, s, reg_bank, c)?;
+    if else_expr.computed_type != then_expr.computed_type {
+        return Err(ValidationError::MismatchedType(else_value.get_loc()));
+    }
+    match else_expr.type_tuple() {
+        (PrimitiveType::F32, 0) => {
+            let else_reg = else_expr.register.get_float()?;
+            let result_reg = then_expr.register.get_float()?;
+            result_reg.use_reg(b, 0, AccessMode::Write, |b, f| {
+                else_reg.load_to(b, f);
+            });
+        }
+        _ => {
+            let else_reg = else_expr.register.get_word()?;
+            let result_reg = then_expr.register.get_word()?;
+            result_reg.use_reg(b, 0, AccessMode::Write, |b, r| {
+                else_reg.load_to(b, r);
+            });
+        }
+    }
+    reg_bank.free_reg(else_expr.register);
+
+    b.new_block(end_lbl);
+    Ok(then_expr)
+}
@@ -1,11 +1,12 @@
 use deimos_ast::*;
 use mips_builder::{MipsBuilder, Register};
 
+use super::check::check_null;
 use super::logic::codegen_logic_not;
 use super::temp::{AccessMode, ExprTemp, RegisterBank};
-use super::value::{codegen_ident_ref, codegen_index_ref};
+use super::value::{codegen_array_index_ref, codegen_ident_ref, load_int, truncate_int};
 use crate::error::{ValidationError, ValidationResult};
-use crate::scope::Scope;
+use crate::scope::{ConstructCounter, Scope};
 
 pub fn codegen_negation(
     b: &mut MipsBuilder,
@@ -20,11 +21,12 @@ pub fn codegen_negation(
                     b.neg_f32(f, f);
                 });
         }
-        (PrimitiveType::I32, 0) => {
+        (typ, 0) if typ.is_int() && typ.is_signed() => {
             expr.register
                 .get_word()?
                 .use_reg(b, 0, AccessMode::ReadWrite, |b, r| {
                     b.sub_i32(r, Register::Zero, r);
+                    truncate_int(b, expr.computed_type, r);
                 });
         }
         _ => {
@@ -37,6 +39,8 @@ pub fn codegen_negation(
 pub fn codegen_deref(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
     expr: ExprTemp,
     loc: Location,
 ) -> ValidationResult<ExprTemp> {
@@ -45,6 +49,7 @@ pub fn codegen_deref(
             let float_reg = reg_bank.get_float_reg();
             let ptr_reg = expr.register.get_word()?;
             ptr_reg.use_reg(b, 0, AccessMode::Read, |b, r| {
+                check_null(b, s, c, r, loc);
                 float_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, f| {
                     b.load_f32(f, r);
                 });
@@ -52,19 +57,15 @@ pub fn codegen_deref(
             reg_bank.free_reg(expr.register);
             Ok(ExprTemp::new(float_reg, PrimitiveType::F32))
         }
-        (PrimitiveType::U8, 1) => {
-            expr.register
-                .get_word()?
-                .use_reg_byte(b, 0, AccessMode::ReadWrite, |b, r| {
-                    b.load_byte(r, r);
-                });
-            Ok(ExprTemp::new(expr.register, PrimitiveType::U8))
-        }
+        // Sub pointers can only be called
+        (PrimitiveType::Sub(_), 1) => Err(ValidationError::InvalidUnary(UnaryOp::Deref, loc)),
         (_, 1..) => {
+            let deref_type = expr.computed_type.deref_type();
             expr.register
                 .get_word()?
                 .use_reg(b, 0, AccessMode::ReadWrite, |b, r| {
-                    b.load_word(r, r);
+                    check_null(b, s, c, r, loc);
+                    load_int(b, deref_type, r, r);
                 });
             Ok(ExprTemp::new(
                 expr.register,
@@ -79,6 +80,7 @@ pub fn codegen_ref(
     b: &mut MipsBuilder,
     reg_bank: &mut RegisterBank,
     s: &Scope,
+    c: &mut ConstructCounter,
     expr: &Expression,
     loc: Location,
 ) -> ValidationResult<ExprTemp> {
@@ -92,11 +94,7 @@ pub fn codegen_ref(
                     data: BinaryOp::IndexAccess,
                     loc,
                 },
-        } => {
-            let left_expr = super::codegen_expr(b, left, s, reg_bank)?;
-            let right_expr = super::codegen_expr(b, right, s, reg_bank)?;
-            codegen_index_ref(b, reg_bank, left_expr, right_expr, *loc)
-        }
+        } => codegen_array_index_ref(b, reg_bank, s, c, left, right, *loc),
         _ => Err(ValidationError::InvalidUnary(UnaryOp::Reference, loc)),
     }
 }
@@ -106,20 +104,21 @@ pub fn codegen_unary(
     expr: &Expression,
     s: &Scope,
     reg_bank: &mut RegisterBank,
+    c: &mut ConstructCounter,
     op: Located<UnaryOp>,
 ) -> ValidationResult<ExprTemp> {
     match op.data {
-        UnaryOp::Reference => codegen_ref(b, reg_bank, s, expr, op.loc),
+        UnaryOp::Reference => codegen_ref(b, reg_bank, s, c, expr, op.loc),
         UnaryOp::LogicNot => {
-            let expr_val = super::codegen_expr(b, expr, s, reg_bank)?;
+            let expr_val = super::codegen_expr(b, expr, s, reg_bank, c)?;
             codegen_logic_not(b, reg_bank, expr_val)
         }
         UnaryOp::Deref => {
-            let expr_val = super::codegen_expr(b, expr, s, reg_bank)?;
-            codegen_deref(b, reg_bank, expr_val, op.loc)
+            let expr_val = super::codegen_expr(b, expr, s, reg_bank, c)?;
+            codegen_deref(b, reg_bank, s, c, expr_val, op.loc)
         }
         UnaryOp::Negation => {
-            let expr_val = super::codegen_expr(b, expr, s, reg_bank)?;
+            let expr_val = super::codegen_expr(b, expr, s, reg_bank, c)?;
             codegen_negation(b, expr_val, op.loc)
         }
     }
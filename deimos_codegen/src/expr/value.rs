@@ -1,11 +1,65 @@
 use deimos_ast::*;
-use mips_builder::{MipsBuilder, Register};
+use mips_builder::{MipsAddress, MipsBuilder, Register};
 
 use super::binary::codegen_add;
-use super::temp::{AccessMode, ExprRegister, ExprTemp, ExprType, OrVirtual, RegisterBank};
+use super::check::check_bounds;
+use super::codegen_expr;
+use super::temp::{
+    AccessMode, ExprRegister, ExprTemp, ExprType, OrVirtual, RegisterBank, EXPR_TEMP, FLOAT_TEMP,
+};
+use super::unary::codegen_deref;
 use crate::error::{ValidationError, ValidationResult};
-use crate::names::get_str_name;
-use crate::scope::{Scope, ValLocation};
+use crate::names::{get_str_name, GET_FLOAT_BOOL_INV};
+use crate::scope::{ConstructCounter, LocatedValue, Scope, ValLocation};
+
+/// Loads an integer or pointer value. Values narrower than a word are
+/// sign or zero extended depending on their type
+pub fn load_int<'a>(
+    b: &mut MipsBuilder,
+    typ: ExprType,
+    dest: Register,
+    addr: impl Into<MipsAddress<'a>>,
+) {
+    match (typ.base, typ.indirection) {
+        (PrimitiveType::I8, 0) => b.load_byte(dest, addr),
+        (PrimitiveType::U8 | PrimitiveType::Bool, 0) => b.load_byte_unsigned(dest, addr),
+        (PrimitiveType::I16, 0) => b.load_half(dest, addr),
+        (PrimitiveType::U16, 0) => b.load_half_unsigned(dest, addr),
+        _ => b.load_word(dest, addr),
+    }
+}
+
+/// Stores an integer or pointer value, truncating it to the size of its type
+pub fn save_int<'a>(
+    b: &mut MipsBuilder,
+    typ: ExprType,
+    source: Register,
+    addr: impl Into<MipsAddress<'a>>,
+) {
+    match (typ.base.size(), typ.indirection) {
+        (1, 0) => b.save_byte(source, addr),
+        (2, 0) => b.save_half(source, addr),
+        _ => b.save_word(source, addr),
+    }
+}
+
+/// Wraps a register value to the range of its type so that values
+/// narrower than a word stay properly extended after arithmetic
+pub fn truncate_int(b: &mut MipsBuilder, typ: ExprType, r: Register) {
+    match (typ.base, typ.indirection) {
+        (PrimitiveType::U8, 0) => b.and_const(r, r, 0xFF),
+        (PrimitiveType::U16, 0) => b.and_const(r, r, 0xFFFF),
+        (PrimitiveType::I8, 0) => {
+            b.shift_logical_left(r, r, 24);
+            b.shift_arith_right(r, r, 24);
+        }
+        (PrimitiveType::I16, 0) => {
+            b.shift_logical_left(r, r, 16);
+            b.shift_arith_right(r, r, 16);
+        }
+        _ => {}
+    }
+}
 
 /// Codegen for references to identifiers
 pub fn codegen_ident(
@@ -14,6 +68,12 @@ pub fn codegen_ident(
     s: &Scope,
     ident: Identifier,
 ) -> ValidationResult<ExprTemp> {
+    if let Some((val, typ)) = s.get_const(ident) {
+        // The value already fits the declared type, so only the type changes
+        let mut temp = codegen_const(b, reg_bank, val);
+        temp.computed_type = typ.into();
+        return Ok(temp);
+    }
     let addr = s.get_var(ident)?;
     let expr_type = ExprType::from(addr.val.clone());
     if let ValLocation::RawAddr(addr) = &addr.loc {
@@ -39,17 +99,10 @@ pub fn codegen_ident(
                 });
                 register.into()
             }
-            (PrimitiveType::U8, 0) => {
-                let register = reg_bank.get_register();
-                register.use_reg_byte(b, 0, AccessMode::Write, |b, r| {
-                    b.load_byte(r, addr.loc);
-                });
-                register.into()
-            }
             _ => {
                 let register = reg_bank.get_register();
                 register.use_reg(b, 0, AccessMode::Write, |b, r| {
-                    b.load_word(r, addr.loc);
+                    load_int(b, expr_type, r, addr.loc);
                 });
                 register.into()
             }
@@ -86,6 +139,12 @@ pub fn codegen_const(
         PrimitiveValue::Unsigned(i) => {
             ExprTemp::new(const_word(b, reg_bank, i), PrimitiveType::U32)
         }
+        PrimitiveValue::Byte(i) => {
+            ExprTemp::new(const_word(b, reg_bank, i as u32), PrimitiveType::U8)
+        }
+        PrimitiveValue::Bool(v) => {
+            ExprTemp::new(const_word(b, reg_bank, v as u32), PrimitiveType::Bool)
+        }
         PrimitiveValue::String(str_id) => {
             let register = reg_bank.get_register();
             register.use_reg(b, 0, AccessMode::Write, |b, r| {
@@ -109,34 +168,57 @@ pub fn codegen_cast(
     expr: ExprTemp,
     typ: ExprType,
 ) -> ValidationResult<ExprTemp> {
-    // We'll treat u8 as a word because the registers are words
+    // Integers narrower than a word are kept extended to a full word in
+    // registers, so they only need to be wrapped when narrowing
     match (
         (expr.computed_type.base, expr.computed_type.indirection),
         (typ.base, typ.indirection),
     ) {
-        // Float to int/ptr
-        (
-            (PrimitiveType::F32, 0),
-            (PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::U8, _)
-            | (PrimitiveType::F32, 1..),
-        ) => {
+        // Float to bool
+        ((PrimitiveType::F32, 0), (PrimitiveType::Bool, 0)) => {
+            let register = reg_bank.get_register();
+            let f_reg = expr.register.get_float()?;
+            b.const_f32(0.0, FLOAT_TEMP[0]);
+            f_reg.use_reg(b, 1, AccessMode::Read, |b, f| {
+                b.equals_f32(f, FLOAT_TEMP[0]);
+            });
+            b.jump_and_link(GET_FLOAT_BOOL_INV);
+            register.store_val(b, Register::V0);
+            reg_bank.free_reg(expr.register);
+            Ok(ExprTemp::new(register, typ))
+        }
+        // Int/enum/ptr to bool
+        ((from, from_ind), (PrimitiveType::Bool, 0))
+            if from.is_int() || from.is_enum() || from_ind > 0 =>
+        {
+            expr.register
+                .get_word()?
+                .use_reg(b, 0, AccessMode::ReadWrite, |b, r| {
+                    b.set_neq(r, r, Register::Zero);
+                });
+            Ok(ExprTemp {
+                register: expr.register,
+                computed_type: typ,
+            })
+        }
+        // Float to int/enum/ptr
+        ((PrimitiveType::F32, 0), (to, to_ind)) if to.is_int() || to.is_enum() || to_ind > 0 => {
             let register = reg_bank.get_register();
             let dest_reg = expr.register.get_float()?;
             dest_reg.use_reg(b, 0, AccessMode::Read, |b, float_reg| {
                 b.cast_from_f32(float_reg, float_reg);
                 register.use_reg(b, 0, AccessMode::Write, |b, r| {
                     b.mov_from_f32(r, float_reg);
+                    truncate_int(b, typ, r);
                 });
             });
             reg_bank.free_reg(expr.register);
             Ok(ExprTemp::new(register, typ))
         }
-        // Int/ptr to float
-        (
-            (PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::U8, _)
-            | (PrimitiveType::F32, 1..),
-            (PrimitiveType::F32, 0),
-        ) => {
+        // Int/enum/bool/ptr to float
+        ((from, from_ind), (PrimitiveType::F32, 0))
+            if from.is_int() || from.is_enum() || from == PrimitiveType::Bool || from_ind > 0 =>
+        {
             let register = reg_bank.get_float_reg();
             let int_reg = expr.register.get_word()?;
             int_reg.use_reg(b, 0, AccessMode::Read, |b, reg| {
@@ -151,6 +233,20 @@ pub fn codegen_cast(
                 computed_type: PrimitiveType::F32.into(),
             })
         }
+        // Conversion to a narrower or differently signed int
+        ((from, 0), (to, 0))
+            if (from.is_int() || from.is_enum()) && to.is_int() && from != to && to.size() < 4 =>
+        {
+            expr.register
+                .get_word()?
+                .use_reg(b, 0, AccessMode::ReadWrite, |b, r| {
+                    truncate_int(b, typ, r);
+                });
+            Ok(ExprTemp {
+                register: expr.register,
+                computed_type: typ,
+            })
+        }
         // No actual conversion required
         _ => Ok(ExprTemp {
             register: expr.register,
@@ -159,6 +255,25 @@ pub fn codegen_cast(
     }
 }
 
+/// Gets the address of a sub
+fn codegen_sub_ref(
+    b: &mut MipsBuilder,
+    reg_bank: &mut RegisterBank,
+    s: &Scope,
+    ident: Identifier,
+) -> ValidationResult<ExprTemp> {
+    let fnc = s.get_fn(ident)?;
+    let reg = reg_bank.get_register();
+    reg.use_reg(b, 0, AccessMode::Write, |b, r| {
+        b.load_addr(r, fnc.label.as_str());
+    });
+    let expr_type = ExprType {
+        base: PrimitiveType::Sub(fnc.signature),
+        indirection: 1,
+    };
+    Ok(ExprTemp::new(reg, expr_type))
+}
+
 /// Gets reference to identifier
 pub fn codegen_ident_ref(
     b: &mut MipsBuilder,
@@ -166,14 +281,16 @@ pub fn codegen_ident_ref(
     s: &Scope,
     ident: Identifier,
 ) -> ValidationResult<ExprTemp> {
-    let reg = reg_bank.get_register();
-    let local_var = s.get_var(ident)?;
+    let local_var = match s.get_var(ident) {
+        Err(ValidationError::FuncInExpr(_)) => return codegen_sub_ref(b, reg_bank, s, ident),
+        var => var?,
+    };
     match local_var.val {
-        DeclType::Array {
-            array_type: Located { loc, .. },
-            ..
-        } => Err(ValidationError::ArrayReference(loc)),
+        // Referencing an array gives the same pointer to its first
+        // element as the array itself
+        DeclType::Array { .. } => codegen_ident(b, reg_bank, s, ident),
         DeclType::Param(p) => {
+            let reg = reg_bank.get_register();
             if let ValLocation::RawAddr(_) = local_var.loc {
                 Err(ValidationError::MemReference(p.loc))
             } else {
@@ -197,9 +314,112 @@ pub fn codegen_index_ref(
 ) -> ValidationResult<ExprTemp> {
     match (value.type_tuple(), index.type_tuple()) {
         // value must be ptr and index must be int
-        ((_, 1..), (PrimitiveType::U8 | PrimitiveType::I32 | PrimitiveType::U32, 0)) => {
+        ((_, 1..), (index_type, 0)) if index_type.is_int() => {
             codegen_add(b, reg_bank, value, index, loc)
         }
         _ => Err(ValidationError::InvalidBinary(BinaryOp::IndexAccess, loc)),
     }
 }
+
+/// Gets the dimensions left to index in an array expression. Indexing into
+/// an array with more than one remaining dimension yields a pointer to the
+/// start of a row instead of an element.
+pub fn get_array_dims(s: &Scope, expr: &Expression) -> Vec<u32> {
+    match expr {
+        Expression::Identifier(ident) => match s.get_var(*ident) {
+            Ok(LocatedValue {
+                val: DeclType::Array { dims, .. },
+                ..
+            }) => dims.iter().map(|d| d.data).collect(),
+            _ => Vec::new(),
+        },
+        Expression::Binary {
+            left,
+            op:
+                Located {
+                    data: BinaryOp::IndexAccess,
+                    ..
+                },
+            ..
+        } => get_array_dims(s, left).into_iter().skip(1).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Type of the value of an expression, with arrays decayed to pointers
+pub fn get_expr_type(s: &Scope, expr: &Expression) -> ValidationResult<ExprType> {
+    Ok(type_of(expr, s)?.into())
+}
+
+/// Size of the value of an expression in bytes. A bare enum name gives
+/// the size of the enum.
+pub fn get_expr_size(s: &Scope, expr: &Expression) -> ValidationResult<u32> {
+    if let Expression::Identifier(ident) = expr {
+        let enum_type = ParamType {
+            param_type: Located::new(PrimitiveType::Enum(ident.data), ident.loc),
+            indirection: 0,
+        };
+        let is_value = s.get_const(*ident).is_some() || s.get_var(*ident).is_ok();
+        if !is_value && s.check_type(&enum_type).is_ok() {
+            return Ok(enum_type.size());
+        }
+    }
+    Ok(type_of(expr, s)?.size())
+}
+
+/// Calculate pointer for index access on an array expression. Arrays are
+/// stored row-major, so the index is scaled by the length of a row when
+/// indexing into a multi-dimensional array
+pub fn codegen_array_index_ref(
+    b: &mut MipsBuilder,
+    reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
+    array: &Expression,
+    index: &Expression,
+    loc: Location,
+) -> ValidationResult<ExprTemp> {
+    let dims = get_array_dims(s, array);
+    let row_len: u32 = dims.iter().skip(1).product();
+    // Failed bounds checks point at the index that's out of range
+    let index_loc = index.get_loc();
+    let array_expr = codegen_expr(b, array, s, reg_bank, c)?;
+    let index_expr = codegen_expr(b, index, s, reg_bank, c)?;
+
+    if let (index_type, 0) = index_expr.type_tuple() {
+        if index_type.is_int() {
+            let index_reg = index_expr.register.get_word()?;
+            index_reg.use_reg(b, 0, AccessMode::ReadWrite, |b, r| {
+                if let Some(&len) = dims.first() {
+                    check_bounds(b, s, c, r, len, index_loc);
+                }
+                if row_len > 1 {
+                    b.const_word(row_len, EXPR_TEMP[1]);
+                    b.mul_i32(r, r, EXPR_TEMP[1]);
+                }
+            });
+        }
+    }
+
+    codegen_index_ref(b, reg_bank, array_expr, index_expr, loc)
+}
+
+/// Codegen for index access. Partially indexing a multi-dimensional array
+/// gives a pointer to the selected row rather than loading a value
+pub fn codegen_index(
+    b: &mut MipsBuilder,
+    reg_bank: &mut RegisterBank,
+    s: &Scope,
+    c: &mut ConstructCounter,
+    array: &Expression,
+    index: &Expression,
+    loc: Location,
+) -> ValidationResult<ExprTemp> {
+    let is_row = get_array_dims(s, array).len() > 1;
+    let reference = codegen_array_index_ref(b, reg_bank, s, c, array, index, loc)?;
+    if is_row {
+        Ok(reference)
+    } else {
+        codegen_deref(b, reg_bank, s, c, reference, loc)
+    }
+}
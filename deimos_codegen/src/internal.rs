@@ -1,5 +1,34 @@
 use super::names::*;
-use mips_builder::{DataDef, MipsBuilder, Register};
+use mips_builder::{DataDef, DataDirective, FloatRegister, MipsAddress, MipsBuilder, Register};
+
+/// Flags passed to the printf helpers in $a3
+pub const FMT_NEG: u16 = 1;
+pub const FMT_ZERO_PAD: u16 = 2;
+pub const FMT_LEFT: u16 = 4;
+pub const FMT_UPPER: u16 = 8;
+
+/// Widest field printf can pad to
+pub const FMT_MAX_WIDTH: u32 = 40;
+/// Most digits printf prints after the decimal point
+pub const FMT_MAX_PRECISION: u32 = 9;
+
+/// Formatted text is built backwards from the end of the buffer
+const FMT_BUF_SIZE: usize = 64;
+/// Words holding the integer part of a float while it's printed, enough
+/// for the largest f32 (and infinity) as 16 bit halves
+const FMT_BIG_WORDS: usize = 5;
+/// Stack frame of the write routine: the argument area of write_file,
+/// the saved registers and the count write_file reports back
+pub const FMT_WRITE_FRAME: i32 = 40;
+/// Stack frame of the field padding routine, holding its return address
+pub const FMT_FIELD_FRAME: i32 = 8;
+
+fn fmt_text_end() -> MipsAddress<'static> {
+    MipsAddress::LabelOffset {
+        label: FMT_BUF.into(),
+        offset: FMT_BUF_SIZE as i32,
+    }
+}
 
 pub fn setup_main(b: &mut MipsBuilder) {
     // Save CLI arguments
@@ -11,7 +40,77 @@ pub fn setup_main(b: &mut MipsBuilder) {
     argv.add_dir(0);
     b.add_def(argv);
 
-    b.new_block("main");
+    // Names printed for bool values
+    let mut true_str = DataDef::new(TRUE_STR);
+    true_str.add_dir(String::from("true"));
+    b.add_def(true_str);
+
+    let mut false_str = DataDef::new(FALSE_STR);
+    false_str.add_dir(String::from("false"));
+    b.add_def(false_str);
+
+    // Messages for failed runtime checks
+    let mut panic_str = DataDef::new(PANIC_STR);
+    panic_str.add_dir(String::from("Runtime error at "));
+    b.add_def(panic_str);
+
+    let mut bounds_str = DataDef::new(BOUNDS_STR);
+    bounds_str.add_dir(String::from(": index out of bounds\n"));
+    b.add_def(bounds_str);
+
+    let mut div_zero_str = DataDef::new(DIV_ZERO_STR);
+    div_zero_str.add_dir(String::from(": division by zero\n"));
+    b.add_def(div_zero_str);
+
+    let mut null_str = DataDef::new(NULL_STR);
+    null_str.add_dir(String::from(": null pointer dereference\n"));
+    b.add_def(null_str);
+
+    // Routines used by library units
+    b.add_global(ARGC_GLOBAL);
+    b.add_global(ARGV_GLOBAL);
+    b.add_global(GET_FLOAT_BOOL);
+    b.add_global(GET_FLOAT_BOOL_INV);
+    b.add_global(PRINT_BOOL);
+    b.add_global(FMT_FD);
+    b.add_global(FMT_WRITE);
+    b.add_global(FMT_INT);
+    b.add_global(FMT_UINT);
+    b.add_global(FMT_FLOAT);
+    b.add_global(FMT_CHAR);
+    b.add_global(FMT_STR);
+    b.add_global(PANIC_BOUNDS);
+    b.add_global(PANIC_DIV_ZERO);
+    b.add_global(PANIC_NULL);
+
+    b.add_global(MAIN_LABEL);
+    // Output state for printf
+    let mut fmt_fd = DataDef::new(FMT_FD);
+    fmt_fd.add_dir(1);
+    b.add_def(fmt_fd);
+
+    let mut fmt_buf = DataDef::new(FMT_BUF);
+    fmt_buf.add_dir(DataDirective::ByteLen {
+        len: FMT_BUF_SIZE,
+        default: 0,
+    });
+    b.add_def(fmt_buf);
+
+    let mut fmt_spaces = DataDef::new(FMT_SPACES);
+    fmt_spaces.add_dir(DataDirective::ByteLen {
+        len: FMT_MAX_WIDTH as usize,
+        default: b' ',
+    });
+    b.add_def(fmt_spaces);
+
+    let mut fmt_big = DataDef::new(FMT_BIG);
+    fmt_big.add_dir(DataDirective::WordLen {
+        len: FMT_BIG_WORDS,
+        default: 0,
+    });
+    b.add_def(fmt_big);
+
+    b.new_block(MAIN_LABEL);
     b.save_word(Register::A0, ARGC_GLOBAL);
     b.save_word(Register::A1, ARGV_GLOBAL);
 }
@@ -36,4 +135,295 @@ pub fn teardown_main(b: &mut MipsBuilder) {
     b.new_block(GET_FLOAT_BOOL_INV_FALSE);
     b.const_word(1, Register::V0);
     b.jump_register(Register::ReturnAddr);
+
+    // Print bool in $a0 as "true" or "false"
+    b.new_block(PRINT_BOOL);
+    b.branch_not_eq_zero(Register::A0, PRINT_BOOL_TRUE);
+    b.load_addr(Register::A0, FALSE_STR);
+    b.add_syscall(4);
+    b.jump_register(Register::ReturnAddr);
+    b.new_block(PRINT_BOOL_TRUE);
+    b.load_addr(Register::A0, TRUE_STR);
+    b.add_syscall(4);
+    b.jump_register(Register::ReturnAddr);
+
+    fmt_helpers(b);
+    panic_helpers(b);
+}
+
+/// Reports a failed runtime check at the source location in $a0
+/// (row) and $a1 (column), then exits with code 1
+fn panic_helpers(b: &mut MipsBuilder) {
+    b.new_block(PANIC_BOUNDS);
+    b.load_addr(Register::A2, BOUNDS_STR);
+    b.branch(PANIC);
+    b.new_block(PANIC_DIV_ZERO);
+    b.load_addr(Register::A2, DIV_ZERO_STR);
+    b.branch(PANIC);
+    b.new_block(PANIC_NULL);
+    b.load_addr(Register::A2, NULL_STR);
+
+    b.new_block(PANIC);
+    b.mov(Register::T0, Register::A0);
+    b.mov(Register::T1, Register::A1);
+    b.load_addr(Register::A0, PANIC_STR);
+    b.add_syscall(4);
+    b.mov(Register::A0, Register::T0);
+    b.add_syscall(1);
+    b.const_word(b':' as u32, Register::A0);
+    b.add_syscall(11);
+    b.mov(Register::A0, Register::T1);
+    b.add_syscall(1);
+    b.mov(Register::A0, Register::A2);
+    b.add_syscall(4);
+    b.const_word(1, Register::A0);
+    b.add_syscall(17);
+}
+
+/// Appends a character to the front of the text being formatted
+fn fmt_push_char(b: &mut MipsBuilder, c: Register) {
+    b.add_const_i32(Register::T0, Register::T0, -1);
+    b.save_byte(c, Register::T0);
+}
+
+/// Runtime routines used by printf. Each one takes the field width in $a2
+/// and flags in $a3, puts the text in $t0..$t2 and continues at the
+/// shared field routine which handles sign, padding and output.
+fn fmt_helpers(b: &mut MipsBuilder) {
+    // Signed int in $a0, continues as unsigned after taking the sign
+    b.new_block(FMT_INT);
+    b.set_lt(Register::T1, Register::A0, Register::Zero);
+    b.branch_eq_zero(Register::T1, FMT_UINT);
+    b.sub_u32(Register::A0, Register::Zero, Register::A0);
+    b.or_const(Register::A3, Register::A3, FMT_NEG);
+
+    // Unsigned int in $a0 with base in $a1
+    b.new_block(FMT_UINT);
+    b.load_addr(Register::T0, fmt_text_end());
+    b.mov(Register::T2, Register::T0);
+    b.new_block(FMT_UINT_DIGIT);
+    b.mod_u32(Register::T1, Register::A0, Register::A1);
+    b.div_u32(Register::A0, Register::A0, Register::A1);
+    b.const_word(10, Register::T3);
+    b.set_lt(Register::T3, Register::T1, Register::T3);
+    b.branch_not_eq_zero(Register::T3, FMT_UINT_DEC);
+    b.add_const_i32(Register::T1, Register::T1, ('a' as i32) - ('0' as i32) - 10);
+    b.and_const(Register::T3, Register::A3, FMT_UPPER);
+    b.branch_eq_zero(Register::T3, FMT_UINT_DEC);
+    b.add_const_i32(Register::T1, Register::T1, ('A' as i32) - ('a' as i32));
+    b.new_block(FMT_UINT_DEC);
+    b.add_const_i32(Register::T1, Register::T1, '0' as i32);
+    fmt_push_char(b, Register::T1);
+    b.branch_not_eq_zero(Register::A0, FMT_UINT_DIGIT);
+    b.branch(FMT_FIELD);
+
+    // Float in $f12 with precision in $a1. The integer part is printed from
+    // a 160 bit number at FMT_BIG so every f32 fits, not just 32 bit ones
+    b.new_block(FMT_FLOAT);
+    b.mov_to_f32(FloatRegister::F4, Register::Zero);
+    b.less_than_f32(FloatRegister::F12, FloatRegister::F4);
+    b.branch_float_false(FMT_FLOAT_POS);
+    b.neg_f32(FloatRegister::F12, FloatRegister::F12);
+    b.or_const(Register::A3, Register::A3, FMT_NEG);
+    b.new_block(FMT_FLOAT_POS);
+    // Floats from 2^23 up have no fraction
+    b.const_f32(8388608.0, FloatRegister::F5);
+    b.less_than_f32(FloatRegister::F12, FloatRegister::F5);
+    b.branch_float_false(FMT_FLOAT_BIG);
+    b.mov(Register::T8, Register::Zero);
+    // Split into integer part ($t4) and fraction ($f6)
+    b.cast_from_f32(FloatRegister::F12, FloatRegister::F5);
+    b.mov_from_f32(Register::T4, FloatRegister::F5);
+    b.cast_to_f32(FloatRegister::F5, FloatRegister::F5);
+    b.sub_f32(FloatRegister::F6, FloatRegister::F12, FloatRegister::F5);
+    // $t5 = 10^precision
+    b.const_word(1, Register::T5);
+    b.mov(Register::T6, Register::A1);
+    b.const_word(10, Register::T7);
+    b.new_block(FMT_FLOAT_SCALE);
+    b.branch_eq_zero(Register::T6, FMT_FLOAT_ROUND);
+    b.mul_i32(Register::T5, Register::T5, Register::T7);
+    b.add_const_i32(Register::T6, Register::T6, -1);
+    b.branch(FMT_FLOAT_SCALE);
+    // Round the scaled fraction, carrying into the integer part
+    b.new_block(FMT_FLOAT_ROUND);
+    b.mov_to_f32(FloatRegister::F7, Register::T5);
+    b.cast_to_f32(FloatRegister::F7, FloatRegister::F7);
+    b.mul_f32(FloatRegister::F6, FloatRegister::F6, FloatRegister::F7);
+    b.const_f32(0.5, FloatRegister::F7);
+    b.add_f32(FloatRegister::F6, FloatRegister::F6, FloatRegister::F7);
+    b.cast_from_f32(FloatRegister::F6, FloatRegister::F6);
+    b.mov_from_f32(Register::T6, FloatRegister::F6);
+    b.set_lt(Register::T1, Register::T6, Register::T5);
+    b.branch_not_eq_zero(Register::T1, FMT_FLOAT_WIDE);
+    b.sub_u32(Register::T6, Register::T6, Register::T5);
+    b.add_const_i32(Register::T4, Register::T4, 1);
+    b.branch(FMT_FLOAT_WIDE);
+    // The integer part is the mantissa ($t4) shifted left by the exponent ($t8)
+    b.new_block(FMT_FLOAT_BIG);
+    b.mov_from_f32(Register::T1, FloatRegister::F12);
+    b.shift_arith_right(Register::T8, Register::T1, 23);
+    b.shift_logical_left(Register::T4, Register::T8, 23);
+    b.sub_u32(Register::T4, Register::T1, Register::T4);
+    b.const_word(1 << 23, Register::T1);
+    b.add_u32(Register::T4, Register::T4, Register::T1);
+    b.add_const_i32(Register::T8, Register::T8, -150);
+    b.mov(Register::T6, Register::Zero);
+    b.const_word(10, Register::T7);
+    // Store $t4 as the low halves of FMT_BIG and double it $t8 times
+    b.new_block(FMT_FLOAT_WIDE);
+    b.load_addr(Register::T0, FMT_BIG);
+    b.save_half(Register::T4, Register::T0);
+    b.shift_arith_right(Register::T4, Register::T4, 16);
+    b.save_half(Register::T4, MipsAddress::from(Register::T0) + 2);
+    for word in 1..FMT_BIG_WORDS {
+        b.save_word(
+            Register::Zero,
+            MipsAddress::from(Register::T0) + 4 * word as i32,
+        );
+    }
+    b.add_const_i32(Register::T2, Register::T0, 4 * FMT_BIG_WORDS as i32);
+    b.new_block(FMT_FLOAT_SHIFT);
+    b.branch_eq_zero(Register::T8, FMT_FLOAT_DIGITS);
+    b.load_addr(Register::T0, FMT_BIG);
+    b.mov(Register::T3, Register::Zero);
+    b.new_block(FMT_FLOAT_DOUBLE);
+    b.load_half_unsigned(Register::T4, Register::T0);
+    b.add_u32(Register::T4, Register::T4, Register::T4);
+    b.add_u32(Register::T4, Register::T4, Register::T3);
+    b.shift_arith_right(Register::T3, Register::T4, 16);
+    b.save_half(Register::T4, Register::T0);
+    b.add_const_i32(Register::T0, Register::T0, 2);
+    b.branch_not_eq(Register::T0, Register::T2, FMT_FLOAT_DOUBLE);
+    b.add_const_i32(Register::T8, Register::T8, -1);
+    b.branch(FMT_FLOAT_SHIFT);
+    b.new_block(FMT_FLOAT_DIGITS);
+    b.load_addr(Register::T0, fmt_text_end());
+    b.mov(Register::T2, Register::T0);
+    b.mov(Register::T5, Register::A1);
+    b.branch_eq_zero(Register::T5, FMT_FLOAT_INT);
+    b.new_block(FMT_FLOAT_FRAC);
+    b.mod_u32(Register::T1, Register::T6, Register::T7);
+    b.div_u32(Register::T6, Register::T6, Register::T7);
+    b.add_const_i32(Register::T1, Register::T1, '0' as i32);
+    fmt_push_char(b, Register::T1);
+    b.add_const_i32(Register::T5, Register::T5, -1);
+    b.branch_not_eq_zero(Register::T5, FMT_FLOAT_FRAC);
+    b.const_word('.' as u32, Register::T1);
+    fmt_push_char(b, Register::T1);
+    // Divide FMT_BIG by 10 from the top half down, the remainder
+    // ($t1) is the next digit and $t8 is left zero once it's all printed
+    b.new_block(FMT_FLOAT_INT);
+    b.load_addr(
+        Register::T5,
+        MipsAddress::from(FMT_BIG) + 4 * FMT_BIG_WORDS as i32,
+    );
+    b.mov(Register::T1, Register::Zero);
+    b.mov(Register::T8, Register::Zero);
+    b.new_block(FMT_FLOAT_DIV);
+    b.add_const_i32(Register::T5, Register::T5, -2);
+    b.load_half_unsigned(Register::T3, Register::T5);
+    b.shift_logical_left(Register::T1, Register::T1, 16);
+    b.or_i32(Register::T3, Register::T1, Register::T3);
+    b.mod_u32(Register::T1, Register::T3, Register::T7);
+    b.div_u32(Register::T3, Register::T3, Register::T7);
+    b.save_half(Register::T3, Register::T5);
+    b.or_i32(Register::T8, Register::T8, Register::T3);
+    b.load_addr(Register::T4, FMT_BIG);
+    b.branch_not_eq(Register::T5, Register::T4, FMT_FLOAT_DIV);
+    b.add_const_i32(Register::T1, Register::T1, '0' as i32);
+    fmt_push_char(b, Register::T1);
+    b.branch_not_eq_zero(Register::T8, FMT_FLOAT_INT);
+    b.branch(FMT_FIELD);
+
+    // Character in $a0
+    b.new_block(FMT_CHAR);
+    b.load_addr(Register::T0, fmt_text_end());
+    b.mov(Register::T2, Register::T0);
+    fmt_push_char(b, Register::A0);
+    b.branch(FMT_FIELD);
+
+    // String address in $a0
+    b.new_block(FMT_STR);
+    b.mov(Register::T0, Register::A0);
+    b.mov(Register::T2, Register::A0);
+    b.new_block(FMT_STR_LEN);
+    b.load_byte_unsigned(Register::T1, Register::T2);
+    b.branch_eq_zero(Register::T1, FMT_FIELD);
+    b.add_const_i32(Register::T2, Register::T2, 1);
+    b.branch(FMT_STR_LEN);
+
+    // Writes the text from $t0 to $t2 padded to the field width
+    b.new_block(FMT_FIELD);
+    b.and_const(Register::T1, Register::A3, FMT_ZERO_PAD);
+    b.branch_eq_zero(Register::T1, FMT_FIELD_SIGN);
+    // Leave room for the sign when padding with zeros
+    b.and_const(Register::T1, Register::A3, FMT_NEG);
+    b.sub_u32(Register::T3, Register::A2, Register::T1);
+    b.new_block(FMT_FIELD_ZERO);
+    b.sub_u32(Register::T1, Register::T2, Register::T0);
+    b.set_lt(Register::T1, Register::T1, Register::T3);
+    b.branch_eq_zero(Register::T1, FMT_FIELD_SIGN);
+    b.const_word('0' as u32, Register::T1);
+    fmt_push_char(b, Register::T1);
+    b.branch(FMT_FIELD_ZERO);
+    b.new_block(FMT_FIELD_SIGN);
+    b.and_const(Register::T1, Register::A3, FMT_NEG);
+    b.branch_eq_zero(Register::T1, FMT_FIELD_PAD);
+    b.const_word('-' as u32, Register::T1);
+    fmt_push_char(b, Register::T1);
+    // $t6 = text length, $t3 = padding
+    b.new_block(FMT_FIELD_PAD);
+    b.add_const_i32(Register::StackPtr, Register::StackPtr, -FMT_FIELD_FRAME);
+    b.save_word(Register::ReturnAddr, Register::StackPtr);
+    b.sub_u32(Register::T6, Register::T2, Register::T0);
+    b.sub_u32(Register::T3, Register::A2, Register::T6);
+    b.set_lt(Register::T1, Register::T3, Register::Zero);
+    b.branch_eq_zero(Register::T1, FMT_FIELD_JUSTIFY);
+    b.mov(Register::T3, Register::Zero);
+    b.new_block(FMT_FIELD_JUSTIFY);
+    b.and_const(Register::T1, Register::A3, FMT_LEFT);
+    b.branch_not_eq_zero(Register::T1, FMT_FIELD_TEXT);
+    b.load_addr(Register::A1, FMT_SPACES);
+    b.mov(Register::A2, Register::T3);
+    b.jump_and_link(FMT_WRITE);
+    b.mov(Register::T3, Register::Zero);
+    b.new_block(FMT_FIELD_TEXT);
+    b.mov(Register::A1, Register::T0);
+    b.mov(Register::A2, Register::T6);
+    b.jump_and_link(FMT_WRITE);
+    b.load_addr(Register::A1, FMT_SPACES);
+    b.mov(Register::A2, Register::T3);
+    b.jump_and_link(FMT_WRITE);
+    b.load_word(Register::ReturnAddr, Register::StackPtr);
+    b.add_const_i32(Register::StackPtr, Register::StackPtr, FMT_FIELD_FRAME);
+    b.jump_register(Register::ReturnAddr);
+
+    // Writes $a2 bytes at $a1 to the printf file descriptor through the
+    // prelude's write_file, keeping the registers the field routine uses
+    b.new_block(FMT_WRITE);
+    b.add_const_i32(Register::StackPtr, Register::StackPtr, -FMT_WRITE_FRAME);
+    let saved = [
+        Register::ReturnAddr,
+        Register::T0,
+        Register::T3,
+        Register::T6,
+    ];
+    for (slot, &reg) in saved.iter().enumerate() {
+        b.save_word(
+            reg,
+            MipsAddress::from(Register::StackPtr) + (16 + 4 * slot as i32),
+        );
+    }
+    b.load_word(Register::A0, FMT_FD);
+    b.add_const_i32(Register::A3, Register::StackPtr, FMT_WRITE_FRAME - 8);
+    b.jump_and_link(WRITE_FILE);
+    for (slot, &reg) in saved.iter().enumerate() {
+        b.load_word(
+            reg,
+            MipsAddress::from(Register::StackPtr) + (16 + 4 * slot as i32),
+        );
+    }
+    b.add_const_i32(Register::StackPtr, Register::StackPtr, FMT_WRITE_FRAME);
+    b.jump_register(Register::ReturnAddr);
 }
@@ -1,91 +1,368 @@
 use deimos_ast::*;
-use mips_builder::{DataDef, MipsBuilder, Register};
+use mips_builder::{DataDef, DataDirective, MipsBuilder, Register};
+use std::collections::HashSet;
 
+mod abi;
+mod asm;
 mod const_expr;
+mod debug;
 mod error;
 mod expr;
 mod internal;
 mod names;
+mod options;
 mod scope;
+mod stack;
 mod stmt;
+mod switch;
+
+pub use debug::Sources;
+pub use mips_builder::{DumpFormat, Image, Machine};
+pub use options::CodegenOptions;
+pub use stack::StackReport;
 
 use names::*;
 
-use error::ValidationResult;
+use error::{ValidationError, ValidationResult};
 use scope::{ConstructCounter, GlobalScope, LocalScope, Scope};
 
+/// Returns the stack space the sub used below its frame
 fn codegen_sub(
     b: &mut MipsBuilder,
     sub: &Function,
+    label: &str,
     scope: &Scope,
     p: &Program,
     c: &mut ConstructCounter,
-) -> ValidationResult<()> {
-    c.enter_fn(sub.name.data);
-    b.new_block(get_fn_name(sub.name.data));
-    scope.init_stack(b)?;
+) -> ValidationResult<u32> {
+    c.enter_fn(label, &p.bank.identifiers[sub.name.data]);
+    b.new_block(label.to_string());
+    scope.init_stack(b, &p.bank)?;
     scope.init_stack_ptr(b);
 
     stmt::codegen_block(b, &sub.block.block, scope, p, c)?;
 
-    b.new_block(get_fn_end(sub.name.data));
+    b.new_block(c.get_fn_end().expect("Sub end outside of sub"));
     scope.restore_ra(b);
     scope.cleanup_stack(b);
     b.jump_register(Register::ReturnAddr);
 
     c.clear_fn();
-    Ok(())
+    Ok(c.take_stack_below())
 }
 
+/// Returns the stack space the program body used below its frame
 fn codegen_main(
     b: &mut MipsBuilder,
     global: &GlobalScope,
+    local: &LocalScope,
+    body: &FunctionBlock,
     p: &Program,
     c: &mut ConstructCounter,
-) -> ValidationResult<()> {
-    let local = LocalScope::from_program(&p.body)?;
-    let scope = Scope::new(&local, global);
-    scope.init_stack(b)?;
-    stmt::codegen_block(b, &p.body.block, &scope, p, c)
+) -> ValidationResult<u32> {
+    let scope = Scope::new(local, global);
+    c.enter_program();
+    scope.init_stack(b, &p.bank)?;
+    stmt::codegen_block(b, &body.block, &scope, p, c)?;
+    c.clear_fn();
+    Ok(c.take_stack_below())
+}
+
+#[derive(Clone, Copy)]
+enum LabelKind {
+    Static,
+    Sub,
+}
+
+impl LabelKind {
+    fn label(self, name: &str, public: bool, readable: bool) -> String {
+        match self {
+            Self::Static => get_static_name(name, public, readable),
+            Self::Sub => get_fn_name(name, public, readable),
+        }
+    }
+
+    /// Every label an item defines. Subs also define their end label,
+    /// which readable labels scope to the sub instead.
+    fn defined(self, label: &str, readable: bool) -> Vec<String> {
+        match self {
+            Self::Sub if !readable => vec![label.to_string(), get_fn_end(label, None)],
+            _ => vec![label.to_string()],
+        }
+    }
+}
+
+/// Labels are derived from the source name so they stay the same
+/// between compilations of separate files. Public labels are fixed and
+/// can't clash with generated labels, so private labels that clash with
+/// another label get a suffix.
+fn get_labels(
+    p: &Program,
+    names: &[(Identifier, LabelKind)],
+    readable: bool,
+) -> ValidationResult<Vec<String>> {
+    let is_public = |name: &Identifier| p.public.contains(&name.data);
+    let get_label = |(name, kind): &(Identifier, LabelKind)| {
+        kind.label(&p.bank.identifiers[name.data], is_public(name), readable)
+    };
+    let mut taken = HashSet::new();
+    // Externs are called by their source name
+    for extern_fn in &p.extern_fns {
+        let label = &p.bank.identifiers[extern_fn.name.data];
+        if is_reserved_label(label) {
+            return Err(ValidationError::ReservedLabel(
+                extern_fn.name.loc,
+                label.clone(),
+            ));
+        }
+        taken.insert(label.clone());
+    }
+    for entry @ (name, kind) in names.iter().filter(|(name, _)| is_public(name)) {
+        let label = get_label(entry);
+        if is_reserved_label(&label) {
+            return Err(ValidationError::ReservedLabel(name.loc, label));
+        }
+        for defined in kind.defined(&label, readable) {
+            if !taken.insert(defined) {
+                return Err(ValidationError::ReservedLabel(name.loc, label));
+            }
+        }
+    }
+    let labels = names
+        .iter()
+        .map(|entry @ (name, kind)| {
+            let label = get_label(entry);
+            if is_public(name) {
+                return label;
+            }
+            let label = make_unique(label, |l| {
+                kind.defined(l, readable).iter().any(|d| taken.contains(d))
+            });
+            taken.extend(kind.defined(&label, readable));
+            label
+        })
+        .collect();
+    Ok(labels)
+}
+
+/// Checks that every declared type refers to a known enum
+fn check_decl_types(p: &Program, global: &GlobalScope) -> ValidationResult<()> {
+    let blocks = p.fns.iter().map(|f| &f.block).chain(&p.body);
+    let vars = p.static_vars.iter().chain(blocks.flat_map(|b| &b.vars));
+    let mem_vars = p.mem_vars.iter().map(|m| &m.variable);
+    for decl in vars.map(|v| &v.variable).chain(mem_vars) {
+        match decl {
+            DeclType::Param(typ)
+            | DeclType::Array {
+                array_type: typ, ..
+            } => global.check_type(&typ.data)?,
+        }
+    }
+    let args = p.fns.iter().flat_map(|f| &f.args);
+    let extern_args = p.extern_fns.iter().flat_map(|f| &f.args);
+    for arg in args.chain(extern_args) {
+        global.check_type(&arg.field_type.data)?;
+    }
+    for const_decl in &p.consts {
+        global.check_type(&const_decl.const_type.data)?;
+    }
+    for arg_type in p.signatures.iter().flat_map(|s| &s.args) {
+        global.check_type(arg_type)?;
+    }
+    Ok(())
 }
 
-pub fn codegen(p: &Program) -> ValidationResult<String> {
-    let mut global = GlobalScope::default();
+/// Whether a public sub or static is exported from the unit. Shared
+/// names are only exported when other units link against them.
+fn is_exported(p: &Program, options: &CodegenOptions, name: &Identifier) -> bool {
+    p.public.contains(&name.data)
+        && (options.export_shared || !options.shared.contains(&p.bank.identifiers[name.data]))
+}
+
+/// Builds the call graph and finds the stack depth of the program body
+/// and of every exported sub. `below` has the stack space each body used
+/// below its frame, with the program body last.
+fn stack_report(
+    p: &Program,
+    options: &CodegenOptions,
+    global: &GlobalScope,
+    fnc_scopes: &[LocalScope],
+    main_scope: Option<&LocalScope>,
+    fn_labels: &[String],
+    below: &[u32],
+) -> ValidationResult<StackReport> {
+    let mut frames = Vec::new();
+    let mut entries = Vec::new();
+    for ((fnc, local), &below) in p.fns.iter().zip(fnc_scopes).zip(below) {
+        let scope = Scope::new(local, global);
+        let name = p.bank.identifiers[fnc.name.data].clone();
+        let size = local.get_local_stack_size();
+        if is_exported(p, options, &fnc.name) {
+            entries.push(frames.len());
+        }
+        let block = &fnc.block.block;
+        frames.push(stack::Frame::new(
+            name, size, below, block, &scope, p, fn_labels,
+        )?);
+    }
+    if let (Some(body), Some(local)) = (&p.body, main_scope) {
+        let scope = Scope::new(local, global);
+        let size = local.get_local_stack_size();
+        let below = below[p.fns.len()];
+        entries.insert(0, frames.len());
+        let name = String::from("program");
+        frames.push(stack::Frame::new(
+            name,
+            size,
+            below,
+            &body.block,
+            &scope,
+            p,
+            fn_labels,
+        )?);
+    }
+    Ok(StackReport::new(&frames, &entries))
+}
+
+/// Assembly for a program along with the reports requested in the options
+pub struct CodegenOutput {
+    pub asm: String,
+    pub stack_report: Option<StackReport>,
+    pub source_map: Option<String>,
+    pub image: Option<Image>,
+}
+
+pub fn codegen(p: &Program, options: &CodegenOptions) -> ValidationResult<CodegenOutput> {
+    let mut global = GlobalScope::new(options.clone());
     let mut fnc_scopes = Vec::new();
-    for static_var in &p.static_vars {
-        global.insert_static(static_var);
+    let statics = p.static_vars.iter().map(|s| (s.name, LabelKind::Static));
+    let fns = p.fns.iter().map(|f| (f.name, LabelKind::Sub));
+    let mut static_labels = get_labels(
+        p,
+        &statics.chain(fns).collect::<Vec<_>>(),
+        options.readable_labels,
+    )?;
+    let fn_labels = static_labels.split_off(p.static_vars.len());
+
+    for (static_var, label) in p.static_vars.iter().zip(&static_labels) {
+        global.insert_static(static_var, label.clone());
+    }
+    for const_decl in &p.consts {
+        global.insert_const(const_decl);
     }
     for mem_var in &p.mem_vars {
         global.insert_mem(mem_var)?;
     }
-    for fnc in &p.fns {
+    for (fnc, label) in p.fns.iter().zip(&fn_labels) {
         fnc_scopes.push(LocalScope::from_fn(fnc)?);
-        global.insert_fn(fnc);
+        global.insert_fn(fnc, label.clone());
+    }
+    for extern_fn in &p.extern_fns {
+        global.insert_extern(extern_fn, &p.bank);
+    }
+    for enum_decl in &p.enums {
+        global.insert_enum(enum_decl);
     }
+    check_decl_types(p, &global)?;
+    let main_scope = p.body.as_ref().map(LocalScope::from_program).transpose()?;
 
     let mut codegen = MipsBuilder::new();
-    let mut counter = ConstructCounter::default();
+    let mut counter = ConstructCounter::new(options.readable_labels);
+    // Stack space each body uses below its frame, with the program body last
+    let mut below = vec![0; p.fns.len() + 1];
+
+    // Library units use the main unit's copy of shared subs and statics
+    let is_defined = |name: &Identifier| {
+        !options.library || !options.shared.contains(&p.bank.identifiers[name.data])
+    };
+    let statics = p.static_vars.iter().zip(static_labels);
+    let statics: Vec<_> = statics.filter(|(s, _)| is_defined(&s.name)).collect();
+    let fns = p
+        .fns
+        .iter()
+        .zip(fnc_scopes.iter())
+        .zip(&fn_labels)
+        .enumerate();
+    let fns: Vec<_> = fns.filter(|(_, ((f, _), _))| is_defined(&f.name)).collect();
+
+    // Export public subs and statics
+    for (static_var, label) in &statics {
+        if is_exported(p, options, &static_var.name) {
+            codegen.add_global(label.clone());
+        }
+    }
+    for (_, ((fnc, _), label)) in &fns {
+        if is_exported(p, options, &fnc.name) {
+            codegen.add_global(label.to_string());
+        }
+    }
 
     // Init static vars
-    for static_var in &p.static_vars {
-        const_expr::codegen_init_static(&mut codegen, &p.bank, static_var)?;
+    for (static_var, label) in statics {
+        const_expr::codegen_init_static(&mut codegen, &p.bank, static_var, label)?;
     }
     // Init string static vars
     for (str_id, str_val) in p.bank.strings.iter().enumerate() {
         let static_name = names::get_str_name(str_id);
         let mut str_def = DataDef::new(static_name);
-        str_def.add_dir(format!("\"{}\"", str_val));
+        str_def.add_dir(DataDirective::Asciiz(str_val.clone()));
         codegen.add_def(str_def);
     }
 
-    internal::setup_main(&mut codegen);
-    codegen_main(&mut codegen, &global, &p, &mut counter)?;
-    internal::teardown_main(&mut codegen);
+    // Library units link against the main unit's entry point and
+    // internal routines instead of defining their own
+    match (&p.body, &main_scope, options.library) {
+        (Some(body), Some(local), false) => {
+            internal::setup_main(&mut codegen);
+            below[p.fns.len()] = codegen_main(&mut codegen, &global, local, body, p, &mut counter)?;
+            internal::teardown_main(&mut codegen);
+        }
+        (None, _, true) => {}
+        (None, _, false) => return Err(ValidationError::NoBody),
+        (Some(_), _, true) => return Err(ValidationError::BodyInLibrary),
+        (Some(_), None, false) => unreachable!("Program body always has a scope"),
+    }
+
+    for (index, ((fnc, local), label)) in fns {
+        let scope = Scope::new(local, &global);
+        below[index] = codegen_sub(&mut codegen, fnc, label, &scope, p, &mut counter)?;
+    }
 
-    for (fnc, local) in p.fns.iter().zip(fnc_scopes.iter()) {
-        let scope = Scope::new(&local, &global);
-        codegen_sub(&mut codegen, fnc, &scope, p, &mut counter)?;
+    let mut stack_report = None;
+    if options.stack_report || options.stack_budget.is_some() {
+        let report = self::stack_report(
+            p,
+            options,
+            &global,
+            &fnc_scopes,
+            main_scope.as_ref(),
+            &fn_labels,
+            &below,
+        )?;
+        if let Some(budget) = options.stack_budget {
+            let over = report
+                .entries
+                .iter()
+                .find(|e| e.recursive || e.depth > budget);
+            if let Some(entry) = over {
+                return Err(ValidationError::StackBudget(entry.name.clone(), budget));
+            }
+        }
+        if options.stack_report {
+            stack_report = Some(report);
+        }
     }
 
-    Ok(codegen.codegen())
+    let image = (options.machine_code)
+        .map(|machine| codegen.encode(machine))
+        .transpose()
+        .map_err(ValidationError::Encode)?;
+    let (asm, origins) = codegen.codegen_with_origins();
+    let source_map = (options.debug_info.as_ref()).map(|sources| sources.source_map(&origins));
+    Ok(CodegenOutput {
+        asm,
+        stack_report,
+        source_map,
+        image,
+    })
 }
@@ -1,65 +1,248 @@
-pub const ARGC_GLOBAL: &'static str = "ARGC_GLOBAL";
-pub const ARGV_GLOBAL: &'static str = "ARGV_GLOBAL";
+use mips_builder::{is_mnemonic, WORD_CONSTS_LBL};
+
+pub const MAIN_LABEL: &str = "main";
+/// Prelude sub printf writes its output through
+pub const WRITE_FILE: &str = "write_file";
+
+pub const ARGC_GLOBAL: &str = "ARGC_GLOBAL";
+pub const ARGV_GLOBAL: &str = "ARGV_GLOBAL";
+
+pub const GET_FLOAT_BOOL: &str = "internal_get_float_bool";
+pub const GET_FLOAT_BOOL_FALSE: &str = "internal_get_float_bool_false";
+
+pub const GET_FLOAT_BOOL_INV: &str = "internal_get_float_bool_inv";
+pub const GET_FLOAT_BOOL_INV_FALSE: &str = "internal_get_float_bool_inv_false";
+
+pub const PRINT_BOOL: &str = "internal_print_bool";
+pub const PRINT_BOOL_TRUE: &str = "internal_print_bool_true";
+pub const TRUE_STR: &str = "INTERNAL_TRUE_STR";
+pub const FALSE_STR: &str = "INTERNAL_FALSE_STR";
+
+pub const FMT_FD: &str = "INTERNAL_FMT_FD";
+pub const FMT_BUF: &str = "INTERNAL_FMT_BUF";
+pub const FMT_SPACES: &str = "INTERNAL_FMT_SPACES";
+pub const FMT_BIG: &str = "INTERNAL_FMT_BIG";
+pub const FMT_WRITE: &str = "internal_fmt_write";
+pub const FMT_INT: &str = "internal_fmt_int";
+pub const FMT_UINT: &str = "internal_fmt_uint";
+pub const FMT_UINT_DIGIT: &str = "internal_fmt_uint_digit";
+pub const FMT_UINT_DEC: &str = "internal_fmt_uint_dec";
+pub const FMT_FLOAT: &str = "internal_fmt_float";
+pub const FMT_FLOAT_POS: &str = "internal_fmt_float_pos";
+pub const FMT_FLOAT_SCALE: &str = "internal_fmt_float_scale";
+pub const FMT_FLOAT_ROUND: &str = "internal_fmt_float_round";
+pub const FMT_FLOAT_BIG: &str = "internal_fmt_float_big";
+pub const FMT_FLOAT_WIDE: &str = "internal_fmt_float_wide";
+pub const FMT_FLOAT_SHIFT: &str = "internal_fmt_float_shift";
+pub const FMT_FLOAT_DOUBLE: &str = "internal_fmt_float_double";
+pub const FMT_FLOAT_DIGITS: &str = "internal_fmt_float_digits";
+pub const FMT_FLOAT_FRAC: &str = "internal_fmt_float_frac";
+pub const FMT_FLOAT_INT: &str = "internal_fmt_float_int";
+pub const FMT_FLOAT_DIV: &str = "internal_fmt_float_div";
+pub const FMT_CHAR: &str = "internal_fmt_char";
+pub const FMT_STR: &str = "internal_fmt_str";
+pub const FMT_STR_LEN: &str = "internal_fmt_str_len";
+pub const FMT_FIELD: &str = "internal_fmt_field";
+pub const FMT_FIELD_ZERO: &str = "internal_fmt_field_zero";
+pub const FMT_FIELD_SIGN: &str = "internal_fmt_field_sign";
+pub const FMT_FIELD_PAD: &str = "internal_fmt_field_pad";
+pub const FMT_FIELD_JUSTIFY: &str = "internal_fmt_field_justify";
+pub const FMT_FIELD_TEXT: &str = "internal_fmt_field_text";
+
+pub const PANIC: &str = "internal_panic";
+pub const PANIC_BOUNDS: &str = "internal_panic_bounds";
+pub const PANIC_DIV_ZERO: &str = "internal_panic_div_zero";
+pub const PANIC_NULL: &str = "internal_panic_null";
+pub const PANIC_STR: &str = "INTERNAL_PANIC_STR";
+pub const BOUNDS_STR: &str = "INTERNAL_BOUNDS_STR";
+pub const DIV_ZERO_STR: &str = "INTERNAL_DIV_ZERO_STR";
+pub const NULL_STR: &str = "INTERNAL_NULL_STR";
+
+pub const FN_PREFIX: &str = "USER_SUB_";
+pub const FN_END: &str = "_END";
+pub const READABLE_FN_PREFIX: &str = "sub_";
+pub const READABLE_FN_END: &str = "end";
+
+pub const STATIC_PREFIX: &str = "USER_STATIC_";
+pub const READABLE_STATIC_PREFIX: &str = "static_";
+pub const STRING_PREFIX: &str = "USER_STRING_";
+
+pub const IF_BLOCK_PREFIX: &str = "IF_BRANCH_";
+pub const ELIF_MODIFIER: &str = "_ELIF_";
+pub const ELSE_MODIFIER: &str = "_ELSE";
+pub const IF_BLOCK_END_SUFFIX: &str = "_END";
+
+pub const TERNARY_PREFIX: &str = "TERNARY_";
+pub const TERNARY_ELSE_SUFFIX: &str = "_ELSE";
+pub const TERNARY_END_SUFFIX: &str = "_END";
+
+pub const PROGRAM_SCOPE: &str = "program";
+
+pub const CHECK_PREFIX: &str = "CHECK_";
+pub const CHECK_PASS_SUFFIX: &str = "_PASS";
+
+pub const WHILE_BLOCK_PREFIX: &str = "WHILE_BLOCK_";
+pub const WHILE_BLOCK_SUFFIX: &str = "_END";
+
+pub const SWITCH_PREFIX: &str = "SWITCH_";
+pub const SWITCH_CASE_MODIFIER: &str = "_CASE_";
+pub const SWITCH_SEARCH_MODIFIER: &str = "_SEARCH_";
+pub const SWITCH_DEFAULT_SUFFIX: &str = "_DEFAULT";
+pub const SWITCH_TABLE_SUFFIX: &str = "_TABLE";
+pub const SWITCH_END_SUFFIX: &str = "_END";
+
+/// Control flow construct that labels are made for. Readable labels are
+/// scoped to the sub the construct is in, e.g. `towers_of_hanoi.if3.else`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstructId {
+    pub scope: Option<String>,
+    pub index: usize,
+}
 
-pub const GET_FLOAT_BOOL: &'static str = "internal_get_float_bool";
-pub const GET_FLOAT_BOOL_FALSE: &'static str = "internal_get_float_bool_false";
+impl ConstructId {
+    /// Label of the start of the construct
+    fn label(&self, prefix: &str, kind: &str) -> String {
+        match &self.scope {
+            Some(scope) => format!("{}.{}{}", scope, kind, self.index),
+            None => format!("{}{}", prefix, self.index),
+        }
+    }
+
+    /// Label of a part of the construct
+    fn part(&self, prefix: &str, kind: &str, suffix: &str, readable_suffix: &str) -> String {
+        match &self.scope {
+            Some(_) => format!("{}.{}", self.label(prefix, kind), readable_suffix),
+            None => format!("{}{}", self.label(prefix, kind), suffix),
+        }
+    }
+}
 
-pub const GET_FLOAT_BOOL_INV: &'static str = "internal_get_float_bool_inv";
-pub const GET_FLOAT_BOOL_INV_FALSE: &'static str = "internal_get_float_bool_inv_false";
+/// Prefixes of the labels made for the runtime and for generated code
+const RESERVED_PREFIXES: [&str; 10] = [
+    "internal_",
+    "INTERNAL_",
+    FN_PREFIX,
+    STATIC_PREFIX,
+    STRING_PREFIX,
+    IF_BLOCK_PREFIX,
+    TERNARY_PREFIX,
+    CHECK_PREFIX,
+    WHILE_BLOCK_PREFIX,
+    SWITCH_PREFIX,
+];
+
+/// Public and extern labels are the source name, so they can't be a
+/// label the runtime or generated code defines, or an instruction name
+pub fn is_reserved_label(name: &str) -> bool {
+    [MAIN_LABEL, ARGC_GLOBAL, ARGV_GLOBAL, WORD_CONSTS_LBL].contains(&name)
+        || RESERVED_PREFIXES.iter().any(|p| name.starts_with(p))
+        || is_mnemonic(name)
+}
 
-pub const FN_PREFIX: &'static str = "USER_SUB_";
-pub const FN_END: &'static str = "_END";
+/// Public subs keep their source name so other files can call them
+pub fn get_fn_name(name: &str, public: bool, readable: bool) -> String {
+    match (public, readable) {
+        (true, _) => name.to_string(),
+        (false, true) => format!("{}{}", READABLE_FN_PREFIX, name),
+        (false, false) => format!("{}{}", FN_PREFIX, name),
+    }
+}
 
-pub const STATIC_PREFIX: &'static str = "USER_STATIC_";
-pub const STRING_PREFIX: &'static str = "USER_STRING_";
+pub fn get_fn_end(fn_label: &str, scope: Option<&str>) -> String {
+    match scope {
+        Some(scope) => format!("{}.{}", scope, READABLE_FN_END),
+        None => format!("{}{}", fn_label, FN_END),
+    }
+}
 
-pub const IF_BLOCK_PREFIX: &'static str = "IF_BRANCH_";
-pub const ELIF_MODIFIER: &'static str = "_ELIF_";
-pub const ELSE_MODIFIER: &'static str = "_ELSE";
-pub const IF_BLOCK_END_SUFFIX: &'static str = "_END";
+pub fn get_static_name(name: &str, public: bool, readable: bool) -> String {
+    match (public, readable) {
+        (true, _) => name.to_string(),
+        (false, true) => format!("{}{}", READABLE_STATIC_PREFIX, name),
+        (false, false) => format!("{}{}", STATIC_PREFIX, name),
+    }
+}
 
-pub const WHILE_BLOCK_PREFIX: &'static str = "WHILE_BLOCK_";
-pub const WHILE_BLOCK_SUFFIX: &'static str = "_END";
+/// Adds a numbered suffix to a label until it's not taken
+pub fn make_unique(label: String, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(&label) {
+        return label;
+    }
+    (1..)
+        .map(|n| format!("{}_{}", label, n))
+        .find(|l| !is_taken(l))
+        .expect("Ran out of label suffixes")
+}
 
-pub fn get_fn_name(fn_id: usize) -> String {
-    format!("{}{}", FN_PREFIX, fn_id)
+pub fn get_str_name(str_id: usize) -> String {
+    format!("{}{}", STRING_PREFIX, str_id)
 }
 
-pub fn get_fn_end(fn_id: usize) -> String {
-    format!("{}{}{}", FN_PREFIX, fn_id, FN_END)
+pub fn get_if_lbl(id: &ConstructId) -> String {
+    id.label(IF_BLOCK_PREFIX, "if")
 }
 
-pub fn get_static_name(stat_id: usize) -> String {
-    format!("{}{}", STATIC_PREFIX, stat_id)
+pub fn get_elif_lbl(id: &ConstructId, elif_ind: usize) -> String {
+    let suffix = format!("{}{}", ELIF_MODIFIER, elif_ind);
+    id.part(IF_BLOCK_PREFIX, "if", &suffix, &format!("elif{}", elif_ind))
 }
 
-pub fn get_str_name(str_id: usize) -> String {
-    format!("{}{}", STRING_PREFIX, str_id)
+pub fn get_if_else(id: &ConstructId) -> String {
+    id.part(IF_BLOCK_PREFIX, "if", ELSE_MODIFIER, "else")
 }
 
-pub fn get_if_lbl(construct_id: usize) -> String {
-    format!("{}{}", IF_BLOCK_PREFIX, construct_id)
+pub fn get_if_end(id: &ConstructId) -> String {
+    id.part(IF_BLOCK_PREFIX, "if", IF_BLOCK_END_SUFFIX, "end")
 }
 
-pub fn get_elif_lbl(construct_id: usize, elif_ind: usize) -> String {
-    format!(
-        "{}{}{}{}",
-        IF_BLOCK_PREFIX, construct_id, ELIF_MODIFIER, elif_ind
+pub fn get_switch_case(id: &ConstructId, case_ind: usize) -> String {
+    let suffix = format!("{}{}", SWITCH_CASE_MODIFIER, case_ind);
+    id.part(
+        SWITCH_PREFIX,
+        "switch",
+        &suffix,
+        &format!("case{}", case_ind),
     )
 }
 
-pub fn get_if_else(construct_id: usize) -> String {
-    format!("{}{}{}", IF_BLOCK_PREFIX, construct_id, ELSE_MODIFIER)
+pub fn get_switch_search(id: &ConstructId, search_ind: usize) -> String {
+    let suffix = format!("{}{}", SWITCH_SEARCH_MODIFIER, search_ind);
+    id.part(
+        SWITCH_PREFIX,
+        "switch",
+        &suffix,
+        &format!("search{}", search_ind),
+    )
+}
+
+pub fn get_switch_default(id: &ConstructId) -> String {
+    id.part(SWITCH_PREFIX, "switch", SWITCH_DEFAULT_SUFFIX, "default")
+}
+
+pub fn get_switch_table(id: &ConstructId) -> String {
+    id.part(SWITCH_PREFIX, "switch", SWITCH_TABLE_SUFFIX, "table")
+}
+
+pub fn get_switch_end(id: &ConstructId) -> String {
+    id.part(SWITCH_PREFIX, "switch", SWITCH_END_SUFFIX, "end")
+}
+
+pub fn get_ternary_else(id: &ConstructId) -> String {
+    id.part(TERNARY_PREFIX, "ternary", TERNARY_ELSE_SUFFIX, "else")
+}
+
+pub fn get_ternary_end(id: &ConstructId) -> String {
+    id.part(TERNARY_PREFIX, "ternary", TERNARY_END_SUFFIX, "end")
 }
 
-pub fn get_if_end(construct_id: usize) -> String {
-    format!("{}{}{}", IF_BLOCK_PREFIX, construct_id, IF_BLOCK_END_SUFFIX)
+pub fn get_check_pass(id: &ConstructId) -> String {
+    id.part(CHECK_PREFIX, "check", CHECK_PASS_SUFFIX, "pass")
 }
 
-pub fn get_while_lbl(while_id: usize) -> String {
-    format!("{}{}", WHILE_BLOCK_PREFIX, while_id)
+pub fn get_while_lbl(id: &ConstructId) -> String {
+    id.label(WHILE_BLOCK_PREFIX, "while")
 }
 
-pub fn get_while_end(while_id: usize) -> String {
-    format!("{}{}{}", WHILE_BLOCK_PREFIX, while_id, WHILE_BLOCK_SUFFIX)
+pub fn get_while_end(id: &ConstructId) -> String {
+    id.part(WHILE_BLOCK_PREFIX, "while", WHILE_BLOCK_SUFFIX, "end")
 }
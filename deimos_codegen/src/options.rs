@@ -0,0 +1,36 @@
+use crate::debug::Sources;
+use mips_builder::Machine;
+use std::collections::HashSet;
+
+/// Settings that change how a program is compiled
+#[derive(Default, Debug, Clone)]
+pub struct CodegenOptions {
+    /// Reject conditions that aren't `bool`
+    pub strict_conditions: bool,
+    /// Compile a unit without a program body that is assembled
+    /// together with a main unit
+    pub library: bool,
+    /// Public subs and statics the main unit defines for every unit, like
+    /// the prelude. Library units link against them instead of emitting
+    /// their own copy.
+    pub shared: HashSet<String>,
+    /// Export the shared subs and statics from the main unit. They're
+    /// only needed when library units are linked against it, so they're
+    /// left out of `.globl`s and the stack report otherwise.
+    pub export_shared: bool,
+    /// Check array bounds, division by zero and null pointers at runtime.
+    /// Only arrays declared with a size are bounds-checked. Array
+    /// parameters (`i32[]`) and pointers don't carry a length, so
+    /// indexing through them is left unchecked.
+    pub runtime_checks: bool,
+    /// Annotate statements with their source lines and build a source map
+    pub debug_info: Option<Sources>,
+    /// Name labels after the subs and statics they belong to
+    pub readable_labels: bool,
+    /// Report the worst-case stack depth of every entry point
+    pub stack_report: bool,
+    /// Fail when an entry point may use more stack than this many bytes
+    pub stack_budget: Option<u32>,
+    /// Also encode the program into machine code for this machine
+    pub machine_code: Option<Machine>,
+}
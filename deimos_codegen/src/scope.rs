@@ -1,37 +1,30 @@
-use super::{const_expr::codegen_init_var, error::*, names::get_static_name};
+use super::{
+    abi::{arg_registers, STACK_ALIGN},
+    const_expr::codegen_init_var,
+    error::*,
+    expr::temp::ExprType,
+    names::{get_fn_end, ConstructId, PROGRAM_SCOPE},
+    options::CodegenOptions,
+};
 use deimos_ast::*;
-use mips_builder::{MipsAddress, MipsBuilder, Register};
+use mips_builder::{GenericRegister, MipsAddress, MipsBuilder, Register};
 use std::collections::HashMap;
 
-/// Calculate size of array type
+/// Calculate the stack space taken by a declaration. Every value
+/// takes at least a word so that stack slots stay word aligned.
 fn get_def_size(d: &DeclType) -> u32 {
     match d {
         DeclType::Param(_) => 4,
-        DeclType::Array {
-            size,
-            array_type:
-                Located {
-                    data:
-                        ParamType {
-                            param_type:
-                                Located {
-                                    data: PrimitiveType::U8,
-                                    ..
-                                },
-                            ..
-                        },
-                    ..
-                },
-            ..
-        } => size.data.div_ceil(4) * 4,
-        DeclType::Array { size, .. } => 4 * size.data,
+        DeclType::Array { .. } => d.size().div_ceil(4) * 4,
     }
 }
 
-/// Private type for representing local variables. The offset here
+/// Private type for representing local variables. The offset of a local
 /// is the inverse of the working stack offset in that it represents
 /// the running size of the stack as variables are inserted. Subtract
 /// this value from the stack size and you'll get the actual offset.
+/// Arguments live in the caller's frame, so their offset is relative
+/// to the top of the local stack.
 #[derive(Debug)]
 struct StackVal {
     val_type: DeclType,
@@ -46,6 +39,7 @@ enum StackValType {
     },
     Argument {
         offset: u32,
+        register: Option<GenericRegister>,
     },
 }
 
@@ -53,7 +47,7 @@ enum StackValType {
 /// Can be converted into MipsAddress later
 #[derive(Clone, Debug)]
 pub enum ValLocation {
-    Static(usize),
+    Static(String),
     RawAddr(u32),
     Stack(u32),
 }
@@ -62,7 +56,7 @@ impl<'a> From<ValLocation> for MipsAddress<'a> {
     fn from(value: ValLocation) -> Self {
         match value {
             ValLocation::RawAddr(a) => MipsAddress::Addr(a),
-            ValLocation::Static(i) => MipsAddress::Label(get_static_name(i).into()),
+            ValLocation::Static(label) => MipsAddress::Label(label.into()),
             ValLocation::Stack(offset) => MipsAddress::RegisterOffset {
                 register: Register::StackPtr,
                 offset: offset as i32,
@@ -91,8 +85,9 @@ pub struct LocalScope {
 impl LocalScope {
     pub fn from_fn(fnc: &Function) -> ValidationResult<Self> {
         let mut local = Self::default();
-        for param in &fnc.args {
-            local.insert_arg(param.name, param.field_type.clone())?;
+        let registers = arg_registers(fnc.args.iter().map(|a| &a.field_type.data));
+        for (param, register) in fnc.args.iter().zip(registers) {
+            local.insert_arg(param.name, param.field_type.clone(), register)?;
         }
         local.insert_ra();
         local.insert_fn_body(&fnc.block)?;
@@ -132,14 +127,18 @@ impl LocalScope {
     }
 
     /// Insert argument into function scope
-    fn insert_arg(&mut self, name: Identifier, typ: impl Into<DeclType>) -> ValidationResult<()> {
+    fn insert_arg(
+        &mut self,
+        name: Identifier,
+        typ: impl Into<DeclType>,
+        register: Option<GenericRegister>,
+    ) -> ValidationResult<()> {
         let typ = typ.into();
+        let offset = self.arg_stack_size;
         self.arg_stack_size += get_def_size(&typ);
         let ins_val = StackVal {
             val_type: typ,
-            data: StackValType::Argument {
-                offset: self.arg_stack_size,
-            },
+            data: StackValType::Argument { offset, register },
         };
         self.insert_val_internal(name, ins_val)
     }
@@ -184,31 +183,21 @@ impl LocalScope {
             .unwrap_or_else(|| global.get_val(name).cloned())
     }
 
-    /// Gets function from global scope. Checks local scope for any
-    /// variables that might shadow the desired function.
-    fn get_fn<'a>(
+    /// Gets constant from global scope if it isn't shadowed by a local
+    fn get_const(
         &self,
         name: Identifier,
-        global: &'a GlobalScope,
-    ) -> ValidationResult<&'a FunctionArgs> {
+        global: &GlobalScope,
+    ) -> Option<(PrimitiveValue, ParamType)> {
         if self.vars.contains_key(&name.data) {
-            return Err(ValidationError::ShadowedFuncCall(name.loc));
+            return None;
         }
-        global.get_fn(name)
+        global.get_const(name)
     }
 
     /// Padded size of the stack
-    fn get_local_stack_size(&self) -> u32 {
-        self.local_stack_size.div_ceil(4) * 4
-    }
-
-    /// Padded size of the args
-    fn get_arg_stack_size(&self) -> u32 {
-        self.arg_stack_size.div_ceil(4) * 4
-    }
-
-    fn get_stack_size(&self) -> u32 {
-        self.get_arg_stack_size() + self.get_local_stack_size()
+    pub fn get_local_stack_size(&self) -> u32 {
+        self.local_stack_size.div_ceil(STACK_ALIGN) * STACK_ALIGN
     }
 
     fn calc_offset_local(&self, offset: u32) -> u32 {
@@ -216,38 +205,57 @@ impl LocalScope {
     }
 
     fn calc_offset_arg(&self, offset: u32) -> u32 {
-        self.get_stack_size() - offset
+        self.get_local_stack_size() + offset
     }
 
     fn calc_offset(&self, val: &StackValType) -> u32 {
         match val {
-            StackValType::Argument { offset } => self.calc_offset_arg(*offset),
+            StackValType::Argument { offset, .. } => self.calc_offset_arg(*offset),
             StackValType::LocalVar { offset, .. } => self.calc_offset_local(*offset),
         }
     }
 }
 
+/// Callable sub and the label calls jump to
+pub struct FnSignature {
+    pub args: FunctionArgs,
+    pub label: String,
+    /// Index of the sub's signature, used for the type of its address
+    pub signature: usize,
+}
+
 enum GlobalVal {
     Val(LocatedValue),
-    Fnc(FunctionArgs),
+    Fnc(FnSignature),
+    Const(PrimitiveValue, ParamType),
 }
 
-#[derive(Default)]
 pub struct GlobalScope {
     vars: HashMap<usize, GlobalVal>,
+    /// Backing integer type of every enum
+    enums: HashMap<usize, PrimitiveType>,
+    options: CodegenOptions,
 }
 
 impl GlobalScope {
+    pub fn new(options: CodegenOptions) -> Self {
+        Self {
+            vars: HashMap::new(),
+            enums: HashMap::new(),
+            options,
+        }
+    }
+
     fn get(&self, name: Identifier) -> ValidationResult<&GlobalVal> {
         self.vars
             .get(&name.data)
             .ok_or(ValidationError::UndefinedIdent(name.loc))
     }
 
-    fn get_fn(&self, name: Identifier) -> ValidationResult<&FunctionArgs> {
+    fn get_fn(&self, name: Identifier) -> ValidationResult<&FnSignature> {
         match self.get(name)? {
-            GlobalVal::Fnc(args) => Ok(args),
-            GlobalVal::Val(_) => Err(ValidationError::NotAFunc(name.loc)),
+            GlobalVal::Fnc(sig) => Ok(sig),
+            GlobalVal::Val(_) | GlobalVal::Const(..) => Err(ValidationError::NotAFunc(name.loc)),
         }
     }
 
@@ -255,36 +263,80 @@ impl GlobalScope {
         match self.get(name)? {
             GlobalVal::Val(args) => Ok(args),
             GlobalVal::Fnc(_) => Err(ValidationError::FuncInExpr(name.loc)),
+            GlobalVal::Const(..) => Err(ValidationError::ConstReference(name.loc)),
+        }
+    }
+
+    fn get_const(&self, name: Identifier) -> Option<(PrimitiveValue, ParamType)> {
+        match self.vars.get(&name.data) {
+            Some(GlobalVal::Const(val, typ)) => Some((*val, typ.clone())),
+            _ => None,
         }
     }
 
     pub fn insert_mem(&mut self, mem: &MemVar) -> ValidationResult<()> {
-        if mem.var.field_type.data.indirection == 0 {
-            return Err(ValidationError::InvalidMemVarType(mem.var.field_type.loc));
+        if let DeclType::Param(typ) = &mem.variable {
+            if typ.data.indirection == 0 {
+                return Err(ValidationError::InvalidMemVarType(typ.loc));
+            }
         }
         self.vars.insert(
-            mem.var.name.data,
+            mem.name.data,
             GlobalVal::Val(LocatedValue {
                 loc: ValLocation::RawAddr(mem.addr.data),
-                val: mem.var.field_type.clone().into(),
+                val: mem.variable.clone(),
             }),
         );
         Ok(())
     }
 
-    pub fn insert_static(&mut self, static_var: &VarDecl) {
+    pub fn insert_static(&mut self, static_var: &VarDecl, label: String) {
         self.vars.insert(
             static_var.name.data,
             GlobalVal::Val(LocatedValue {
-                loc: ValLocation::Static(static_var.name.data),
+                loc: ValLocation::Static(label),
                 val: static_var.variable.clone(),
             }),
         );
     }
 
-    pub fn insert_fn(&mut self, fnc: &Function) {
-        self.vars
-            .insert(fnc.name.data, GlobalVal::Fnc(fnc.args.clone()));
+    pub fn insert_const(&mut self, const_decl: &ConstDecl) {
+        self.vars.insert(
+            const_decl.name.data,
+            GlobalVal::Const(const_decl.value.data, const_decl.const_type.data.clone()),
+        );
+    }
+
+    pub fn insert_enum(&mut self, enum_decl: &EnumDecl) {
+        self.enums.insert(enum_decl.name.data, enum_decl.repr.data);
+    }
+
+    /// Checks that an enum type refers to a declared enum
+    pub fn check_type(&self, typ: &ParamType) -> ValidationResult<()> {
+        match typ.param_type.data {
+            PrimitiveType::Enum(name) if !self.enums.contains_key(&name) => {
+                Err(ValidationError::UnknownType(typ.param_type.loc))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn insert_fn(&mut self, fnc: &Function, label: String) {
+        let sig = FnSignature {
+            args: fnc.args.clone(),
+            label,
+            signature: fnc.signature,
+        };
+        self.vars.insert(fnc.name.data, GlobalVal::Fnc(sig));
+    }
+
+    pub fn insert_extern(&mut self, fnc: &ExternFn, bank: &StringBank) {
+        let sig = FnSignature {
+            args: fnc.args.clone(),
+            label: bank.identifiers[fnc.name.data].clone(),
+            signature: fnc.signature,
+        };
+        self.vars.insert(fnc.name.data, GlobalVal::Fnc(sig));
     }
 }
 
@@ -315,12 +367,43 @@ impl<'a> Scope<'a> {
         self.local.get_var(name, self.global, self.stack_shift)
     }
 
-    pub fn get_fn(&self, name: Identifier) -> ValidationResult<&'a FunctionArgs> {
-        self.local.get_fn(name, &self.global)
+    pub fn get_const(&self, name: Identifier) -> Option<(PrimitiveValue, ParamType)> {
+        self.local.get_const(name, self.global)
+    }
+
+    pub fn check_type(&self, typ: &ParamType) -> ValidationResult<()> {
+        self.global.check_type(typ)
+    }
+
+    /// Whether a value of type `arg` can be passed for a parameter of
+    /// type `param`. Enum parameters also take their backing integer
+    /// type, so subs keep accepting the integers they took before their
+    /// parameters were given an enum type.
+    pub fn accepts_arg(&self, param: ExprType, arg: ExprType) -> bool {
+        match (param.base, param.indirection, arg.indirection) {
+            (PrimitiveType::Enum(name), 0, 0) if param != arg => {
+                self.global.enums.get(&name) == Some(&arg.base)
+            }
+            _ => param == arg,
+        }
+    }
+
+    /// Bytes pushed below the frame, like argument or clobber areas
+    pub fn stack_shift(&self) -> u32 {
+        self.stack_shift
+    }
+
+    pub fn options(&self) -> &'a CodegenOptions {
+        &self.global.options
+    }
+
+    pub fn get_fn(&self, name: Identifier) -> ValidationResult<&'a FnSignature> {
+        self.global.get_fn(name)
     }
 
-    /// Allocate enough space for the return address and local variables
-    pub fn init_stack(&self, b: &mut MipsBuilder) -> ValidationResult<()> {
+    /// Allocate enough space for the return address and local variables.
+    /// Register arguments are spilled into their slots in the caller's frame
+    pub fn init_stack(&self, b: &mut MipsBuilder, bank: &StringBank) -> ValidationResult<()> {
         let neg_stack = -(self.local.get_local_stack_size() as i32);
         b.add_const_i32(Register::StackPtr, Register::StackPtr, neg_stack);
         for val in self
@@ -329,13 +412,25 @@ impl<'a> Scope<'a> {
             .iter()
             .flat_map(|e| self.local.vars.get(e))
         {
-            if let StackValType::LocalVar {
-                offset,
-                init_val: Some(init),
-            } = &val.data
-            {
-                let var_offset = self.local.calc_offset_local(*offset) as i32;
-                codegen_init_var(b, val.val_type.clone(), init, var_offset)?;
+            match &val.data {
+                StackValType::Argument {
+                    offset,
+                    register: Some(register),
+                } => {
+                    let addr = ValLocation::Stack(self.local.calc_offset_arg(*offset));
+                    match *register {
+                        GenericRegister::Regular(r) => b.save_word(r, addr),
+                        GenericRegister::Float(f) => b.save_f32(f, addr),
+                    }
+                }
+                StackValType::LocalVar {
+                    offset,
+                    init_val: Some(init),
+                } => {
+                    let var_offset = self.local.calc_offset_local(*offset) as i32;
+                    codegen_init_var(b, bank, val.val_type.clone(), init, var_offset)?;
+                }
+                _ => {}
             }
         }
         Ok(())
@@ -349,7 +444,7 @@ impl<'a> Scope<'a> {
         b.add_const_i32(
             Register::StackPtr,
             Register::StackPtr,
-            self.local.get_stack_size() as i32,
+            self.local.get_local_stack_size() as i32,
         );
     }
 
@@ -358,50 +453,141 @@ impl<'a> Scope<'a> {
     }
 }
 
+impl TypeScope for Scope<'_> {
+    type Error = ValidationError;
+
+    fn name_type(&self, name: Identifier) -> ValidationResult<DeclType> {
+        match self.get_const(name) {
+            Some((_, typ)) => Ok(Located::new(typ, name.loc).into()),
+            None => Ok(self.get_var(name)?.val),
+        }
+    }
+
+    fn var_type(&self, name: Identifier) -> ValidationResult<DeclType> {
+        Ok(self.get_var(name)?.val)
+    }
+
+    fn sub_signature(&self, name: Identifier) -> Option<usize> {
+        self.get_fn(name).ok().map(|fnc| fnc.signature)
+    }
+
+    fn check_type(&self, typ: &ParamType) -> ValidationResult<()> {
+        Scope::check_type(self, typ)
+    }
+}
+
 #[derive(Default)]
 pub struct ConstructCounter {
     if_count: usize,
+    switch_count: usize,
+    ternary_count: usize,
+    check_count: usize,
     loop_count: usize,
-    loop_stack: Vec<usize>,
-    in_func: Option<usize>,
+    loop_stack: Vec<ConstructId>,
+    in_func: Option<String>,
+    /// Sub or program body that readable labels are named after
+    scope: Option<String>,
+    readable_labels: bool,
+    /// Deepest stack use below the frame of the current body
+    stack_below: u32,
 }
 
 impl ConstructCounter {
-    fn new_loop(&mut self) -> usize {
+    pub fn new(readable_labels: bool) -> Self {
+        Self {
+            readable_labels,
+            ..Default::default()
+        }
+    }
+
+    fn new_id(&self, index: usize) -> ConstructId {
+        ConstructId {
+            scope: self.scope.clone(),
+            index,
+        }
+    }
+
+    fn new_loop(&mut self) -> ConstructId {
         let old = self.loop_count;
         self.loop_count += 1;
-        old
+        self.new_id(old)
     }
 
-    pub fn start_loop(&mut self) -> usize {
-        let ind = self.new_loop();
-        self.loop_stack.push(ind);
-        ind
+    pub fn start_loop(&mut self) -> ConstructId {
+        let id = self.new_loop();
+        self.loop_stack.push(id.clone());
+        id
     }
 
-    pub fn end_loop(&mut self) -> usize {
+    pub fn end_loop(&mut self) -> ConstructId {
         self.loop_stack.pop().expect("Loop end without start")
     }
 
-    pub fn new_if(&mut self) -> usize {
+    pub fn new_if(&mut self) -> ConstructId {
         let old = self.if_count;
         self.if_count += 1;
-        old
+        self.new_id(old)
+    }
+
+    pub fn new_switch(&mut self) -> ConstructId {
+        let old = self.switch_count;
+        self.switch_count += 1;
+        self.new_id(old)
+    }
+
+    pub fn new_ternary(&mut self) -> ConstructId {
+        let old = self.ternary_count;
+        self.ternary_count += 1;
+        self.new_id(old)
     }
 
-    pub fn enter_fn(&mut self, fnc_id: usize) {
-        self.in_func = fnc_id.into();
+    pub fn new_check(&mut self) -> ConstructId {
+        let old = self.check_count;
+        self.check_count += 1;
+        self.new_id(old)
+    }
+
+    /// Readable labels are scoped to a sub, so numbering starts over
+    fn enter_scope(&mut self, name: &str) {
+        if self.readable_labels {
+            *self = Self {
+                scope: Some(name.to_string()),
+                ..Self::new(true)
+            };
+        }
+    }
+
+    pub fn enter_program(&mut self) {
+        self.enter_scope(PROGRAM_SCOPE);
+    }
+
+    pub fn enter_fn(&mut self, fnc_label: &str, name: &str) {
+        self.enter_scope(name);
+        self.in_func = Some(fnc_label.to_string());
     }
 
     pub fn clear_fn(&mut self) {
         self.in_func = None;
+        self.scope = None;
+    }
+
+    /// Records stack space used below the frame of the current body
+    pub fn note_stack_below(&mut self, bytes: u32) {
+        self.stack_below = self.stack_below.max(bytes);
+    }
+
+    /// Stack space used below the frame since the last call
+    pub fn take_stack_below(&mut self) -> u32 {
+        std::mem::take(&mut self.stack_below)
     }
 
-    pub fn get_current_loop(&self) -> Option<usize> {
-        self.loop_stack.last().cloned()
+    pub fn get_current_loop(&self) -> Option<&ConstructId> {
+        self.loop_stack.last()
     }
 
-    pub fn get_current_fn(&self) -> Option<usize> {
-        self.in_func
+    /// Label jumped to when returning from the current sub
+    pub fn get_fn_end(&self) -> Option<String> {
+        let fn_label = self.in_func.as_deref()?;
+        Some(get_fn_end(fn_label, self.scope.as_deref()))
     }
 }
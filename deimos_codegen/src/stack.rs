@@ -0,0 +1,340 @@
+use deimos_ast::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::abi::arg_area_size;
+use crate::error::ValidationResult;
+use crate::internal::{FMT_FIELD_FRAME, FMT_WRITE_FRAME};
+use crate::names::WRITE_FILE;
+use crate::scope::Scope;
+use crate::stmt::{resolve_callee, Callee};
+
+/// Call from one frame to another. The caller reserves the argument
+/// area on top of its own frame before jumping.
+struct Call {
+    callee: usize,
+    arg_area: u32,
+}
+
+/// Sub or program body in the call graph
+pub struct Frame {
+    name: String,
+    size: u32,
+    /// Stack used below the frame by spilled temps and saved clobbers
+    below: u32,
+    /// Frames of the printf routines, which call write_file on top
+    helpers: u32,
+    calls: Vec<Call>,
+    /// Called extern subs, whose stack usage isn't known
+    externs: Vec<String>,
+    /// Largest argument area reserved for an extern call
+    extern_arg_area: u32,
+}
+
+impl Frame {
+    /// Collects every call made by a body. Calls through a sub pointer
+    /// may reach any sub with the pointer's signature.
+    pub fn new(
+        name: String,
+        size: u32,
+        below: u32,
+        block: &Block,
+        s: &Scope,
+        p: &Program,
+        fn_labels: &[String],
+    ) -> ValidationResult<Self> {
+        let mut frame = Frame {
+            name,
+            size,
+            below,
+            helpers: 0,
+            calls: Vec::new(),
+            externs: Vec::new(),
+            extern_arg_area: 0,
+        };
+        frame.collect_calls(block, s, p, fn_labels)?;
+        Ok(frame)
+    }
+
+    fn add_call(&mut self, callee: usize, arg_count: usize) {
+        let arg_area = arg_area_size(arg_count);
+        self.calls.push(Call { callee, arg_area });
+    }
+
+    /// Text is written through the write routine, formatted fields go
+    /// through the padding routine first
+    fn add_printf(&mut self, printf: &Printf, fn_labels: &[String]) {
+        let mut helpers = FMT_WRITE_FRAME as u32;
+        if !printf.args.is_empty() {
+            helpers += FMT_FIELD_FRAME as u32;
+        }
+        self.helpers = self.helpers.max(helpers);
+        if let Some(callee) = fn_labels.iter().position(|l| l == WRITE_FILE) {
+            self.calls.push(Call {
+                callee,
+                arg_area: helpers,
+            });
+        }
+    }
+
+    fn add_extern(&mut self, p: &Program, extern_fn: &ExternFn, arg_count: usize) {
+        self.extern_arg_area = self.extern_arg_area.max(arg_area_size(arg_count));
+        let name = &p.bank.identifiers[extern_fn.name.data];
+        if !self.externs.contains(name) {
+            self.externs.push(name.clone());
+        }
+    }
+
+    fn collect_calls(
+        &mut self,
+        block: &Block,
+        s: &Scope,
+        p: &Program,
+        fn_labels: &[String],
+    ) -> ValidationResult<()> {
+        for stmt in block {
+            match &stmt.data {
+                Statement::Call(invocation) => match resolve_callee(s, &invocation.function)? {
+                    Callee::Sub(fnc) => match fn_labels.iter().position(|l| *l == fnc.label) {
+                        Some(callee) => self.add_call(callee, fnc.args.len()),
+                        None => {
+                            let extern_fn = p
+                                .extern_fns
+                                .iter()
+                                .find(|e| p.bank.identifiers[e.name.data] == fnc.label);
+                            if let Some(extern_fn) = extern_fn {
+                                self.add_extern(p, extern_fn, fnc.args.len());
+                            }
+                        }
+                    },
+                    Callee::Pointer(sig) => {
+                        let arg_count = p.signatures[sig].args.len();
+                        for (callee, fnc) in p.fns.iter().enumerate() {
+                            if fnc.signature == sig {
+                                self.add_call(callee, arg_count);
+                            }
+                        }
+                        for extern_fn in &p.extern_fns {
+                            if extern_fn.signature == sig {
+                                self.add_extern(p, extern_fn, arg_count);
+                            }
+                        }
+                    }
+                },
+                Statement::LogicChain(chain) => {
+                    self.collect_calls(&chain.if_block.body, s, p, fn_labels)?;
+                    for elif in &chain.elifs {
+                        self.collect_calls(&elif.body, s, p, fn_labels)?;
+                    }
+                    if let Some(else_block) = &chain.else_block {
+                        self.collect_calls(else_block, s, p, fn_labels)?;
+                    }
+                }
+                Statement::Printf(printf) => self.add_printf(printf, fn_labels),
+                Statement::While(w) => self.collect_calls(&w.body, s, p, fn_labels)?,
+                Statement::Switch(sw) => {
+                    for case in &sw.cases {
+                        self.collect_calls(&case.body, s, p, fn_labels)?;
+                    }
+                    if let Some(default) = &sw.default {
+                        self.collect_calls(default, s, p, fn_labels)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Worst-case stack depth of an entry point
+pub struct EntryDepth {
+    pub name: String,
+    /// Depth in bytes. For recursive entry points this is only a lower
+    /// bound, since calls back into a cycle aren't followed.
+    pub depth: u32,
+    pub recursive: bool,
+}
+
+/// Static stack usage of a program, counting frames, argument areas,
+/// spilled temps, saved clobbers and the printf routines. The other
+/// runtime helpers, like the panic and bool routines, don't use the stack.
+pub struct StackReport {
+    pub entries: Vec<EntryDepth>,
+    /// Groups of subs that can call themselves through each other
+    pub cycles: Vec<Vec<String>>,
+    /// Extern subs reachable from an entry point
+    pub externs: Vec<String>,
+}
+
+/// Strongly connected components of the call graph, using Tarjan's
+/// algorithm
+struct Components<'a> {
+    frames: &'a [Frame],
+    next_index: usize,
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    component: Vec<usize>,
+    count: usize,
+}
+
+impl<'a> Components<'a> {
+    /// Finds the component of every frame
+    fn find(frames: &'a [Frame]) -> Vec<usize> {
+        let mut c = Components {
+            frames,
+            next_index: 0,
+            index: vec![None; frames.len()],
+            low_link: vec![0; frames.len()],
+            stack: Vec::new(),
+            on_stack: vec![false; frames.len()],
+            component: vec![0; frames.len()],
+            count: 0,
+        };
+        for node in 0..frames.len() {
+            if c.index[node].is_none() {
+                c.visit(node);
+            }
+        }
+        c.component
+    }
+
+    fn visit(&mut self, node: usize) {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.index[node] = Some(index);
+        self.low_link[node] = index;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        let frames = self.frames;
+        for call in &frames[node].calls {
+            match self.index[call.callee] {
+                None => {
+                    self.visit(call.callee);
+                    self.low_link[node] = self.low_link[node].min(self.low_link[call.callee]);
+                }
+                Some(callee_index) if self.on_stack[call.callee] => {
+                    self.low_link[node] = self.low_link[node].min(callee_index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low_link[node]) == self.index[node] {
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                self.component[member] = self.count;
+                if member == node {
+                    break;
+                }
+            }
+            self.count += 1;
+        }
+    }
+}
+
+/// Deepest path from a frame, skipping calls back into its own cycle
+fn frame_depth(
+    frames: &[Frame],
+    component: &[usize],
+    node: usize,
+    memo: &mut HashMap<usize, u32>,
+) -> u32 {
+    if let Some(depth) = memo.get(&node) {
+        return *depth;
+    }
+    let deepest_call = frames[node]
+        .calls
+        .iter()
+        .filter(|call| component[call.callee] != component[node])
+        .map(|call| call.arg_area + frame_depth(frames, component, call.callee, memo))
+        .fold(frames[node].extern_arg_area, u32::max)
+        .max(frames[node].below)
+        .max(frames[node].helpers);
+    let depth = frames[node].size + deepest_call;
+    memo.insert(node, depth);
+    depth
+}
+
+impl StackReport {
+    pub fn new(frames: &[Frame], entries: &[usize]) -> Self {
+        let component = Components::find(frames);
+        let is_recursive = |node: usize| {
+            let frame = &frames[node];
+            frame.calls.iter().any(|c| c.callee == node)
+                || (0..frames.len()).any(|n| n != node && component[n] == component[node])
+        };
+
+        let mut cycles: Vec<(usize, Vec<String>)> = Vec::new();
+        for node in (0..frames.len()).filter(|n| is_recursive(*n)) {
+            let name = frames[node].name.clone();
+            match cycles.iter_mut().find(|(c, _)| *c == component[node]) {
+                Some((_, members)) => members.push(name),
+                None => cycles.push((component[node], vec![name])),
+            }
+        }
+
+        let mut memo = HashMap::new();
+        let mut externs = Vec::new();
+        let mut entry_depths = Vec::new();
+        for &entry in entries {
+            // Walk everything reachable from the entry point
+            let mut reached = vec![false; frames.len()];
+            let mut pending = vec![entry];
+            while let Some(node) = pending.pop() {
+                if !reached[node] {
+                    reached[node] = true;
+                    pending.extend(frames[node].calls.iter().map(|c| c.callee));
+                }
+            }
+            for frame in (0..frames.len())
+                .filter(|n| reached[*n])
+                .map(|n| &frames[n])
+            {
+                for name in &frame.externs {
+                    if !externs.contains(name) {
+                        externs.push(name.clone());
+                    }
+                }
+            }
+            entry_depths.push(EntryDepth {
+                name: frames[entry].name.clone(),
+                depth: frame_depth(frames, &component, entry, &mut memo),
+                recursive: (0..frames.len()).any(|n| reached[n] && is_recursive(n)),
+            });
+        }
+
+        Self {
+            entries: entry_depths,
+            cycles: cycles.into_iter().map(|(_, members)| members).collect(),
+            externs,
+        }
+    }
+}
+
+impl Display for StackReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Worst-case stack depth:")?;
+        for entry in &self.entries {
+            if entry.recursive {
+                writeln!(
+                    f,
+                    "  {}: unbounded, at least {} bytes",
+                    entry.name, entry.depth
+                )?;
+            } else {
+                writeln!(f, "  {}: {} bytes", entry.name, entry.depth)?;
+            }
+        }
+        for cycle in &self.cycles {
+            writeln!(f, "Recursion through {}", cycle.join(", "))?;
+        }
+        if !self.externs.is_empty() {
+            writeln!(f, "Not counted: extern {}", self.externs.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,19 @@
 use deimos_ast::*;
 use mips_builder::{FloatRegister, GenericRegister, MipsAddress, MipsBuilder, Register};
 
+use crate::abi::{arg_area_size, arg_registers, STACK_ALIGN};
+use crate::asm::{parse_asm_line, AsmOperands};
 use crate::expr::print::codegen_print_val;
-use crate::expr::temp::{AccessMode, ExprType};
+use crate::expr::printf::codegen_printf;
+use crate::expr::temp::{AccessMode, ExprType, EXPR_TEMP};
+use crate::expr::value::{load_int, save_int};
 use crate::expr::{self, codegen_expr, RegisterBank};
-use crate::names::get_fn_name;
 use crate::names::*;
-use crate::scope::{LocatedValue, ValLocation};
+use crate::scope::{FnSignature, LocatedValue, ValLocation};
+use crate::switch::codegen_switch;
 
 use super::error::{ValidationError, ValidationResult};
-use super::expr::rvalue::codegen_assignment;
+use super::expr::rvalue::{codegen_assignment, codegen_increment};
 use super::scope::{ConstructCounter, Scope};
 
 /// Codegen for entire block
@@ -21,7 +25,12 @@ pub fn codegen_block(
     c: &mut ConstructCounter,
 ) -> ValidationResult<()> {
     for stmt in block {
+        let outer = b.set_origin(Some((stmt.loc.row, stmt.loc.col)));
+        if let Some(sources) = &scope.options().debug_info {
+            b.comment(&sources.annotation(stmt.loc));
+        }
         codegen_stmt(b, &stmt.data, scope, p, c)?;
+        b.set_origin(outer);
     }
     Ok(())
 }
@@ -35,14 +44,17 @@ pub fn codegen_stmt(
     c: &mut ConstructCounter,
 ) -> ValidationResult<()> {
     match stmt {
-        Statement::Assignment(assignment) => codegen_assignment(b, s, assignment),
-        Statement::Call(invoc) => codegen_fnc_call(b, invoc, s),
-        Statement::Asm(asm) => codegen_asm(b, asm, s, &p.bank),
+        Statement::Assignment(assignment) => codegen_assignment(b, s, assignment, c),
+        Statement::Increment(increment) => codegen_increment(b, s, increment, c),
+        Statement::Call(invoc) => codegen_fnc_call(b, invoc, s, p, c),
+        Statement::Asm(asm) => codegen_asm(b, asm, s, &p.bank, c),
         Statement::ControlBreak(control) => codegen_control_flow(b, control, c),
-        Statement::Syscall(syscall) => codegen_syscall(b, syscall, s),
+        Statement::Syscall(syscall) => codegen_syscall(b, syscall, s, c),
         Statement::LogicChain(l) => codegen_logic_chain(b, l, s, p, c),
         Statement::While(w) => codegen_while(b, w, s, p, c),
-        Statement::Print(p) => codegen_print(b, p, s),
+        Statement::Switch(sw) => codegen_switch(b, sw, s, p, c),
+        Statement::Print(p) => codegen_print(b, p, s, c),
+        Statement::Printf(printf) => codegen_printf(b, printf, s, &p.bank, c),
     }
 }
 
@@ -52,7 +64,7 @@ fn codegen_control_flow(
     c: &mut ConstructCounter,
 ) -> ValidationResult<()> {
     let label = match &f.data {
-        ControlBreak::Return => c.get_current_fn().map(get_fn_end),
+        ControlBreak::Return => c.get_fn_end(),
         ControlBreak::Continue => c.get_current_loop().map(get_while_lbl),
         ControlBreak::Break => c.get_current_loop().map(get_while_end),
     };
@@ -65,112 +77,212 @@ fn codegen_control_flow(
 }
 
 /// Codegen for print statement
-fn codegen_print(b: &mut MipsBuilder, print: &Print, s: &Scope) -> ValidationResult<()> {
+fn codegen_print(
+    b: &mut MipsBuilder,
+    print: &Print,
+    s: &Scope,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
     let mut bank = RegisterBank::default();
     for p_expr in &print.args {
-        let expr_val = codegen_expr(b, p_expr, s, &mut bank)?;
-        codegen_print_val(b, expr_val)?;
+        let expr_val = codegen_expr(b, p_expr, s, &mut bank, c)?;
+        codegen_print_val(b, expr_val, p_expr.get_loc())?;
         bank.clear();
     }
     Ok(())
 }
 
 /// Codegen for syscall statement
-fn codegen_syscall(b: &mut MipsBuilder, syscall: &Syscall, s: &Scope) -> ValidationResult<()> {
-    codegen_regload_before(b, &syscall.map.in_values, s)?;
-    b.add_syscall(syscall.syscall_id.data as u8);
-    codegen_regload_after(b, &syscall.map.out_values, s)
+fn codegen_syscall(
+    b: &mut MipsBuilder,
+    syscall: &Syscall,
+    s: &Scope,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
+    codegen_regload_block(b, &syscall.map, s, c, |b, _| {
+        b.add_syscall(syscall.syscall_id.data as u8);
+        Ok(())
+    })
 }
 
-/// Codegen for ASM block
+/// Codegen for ASM block.
+/// `{name}` placeholders get registers loaded from and stored back to
+/// their variables, `{&name}` is replaced by the variable's address.
 fn codegen_asm(
     b: &mut MipsBuilder,
     asm: &AsmBlock,
     s: &Scope,
     strs: &StringBank,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
+    let lines = asm
+        .asm_strings
+        .iter()
+        .map(|s| {
+            let text = std::str::from_utf8(&strs.strings[s.data])
+                .map_err(|_| ValidationError::InvalidAsmText(s.loc))?;
+            parse_asm_line(text, s.loc, strs)
+        })
+        .collect::<ValidationResult<Vec<_>>>()?;
+    let reserved: Vec<_> = (asm.map.in_values.keys())
+        .chain(asm.map.out_values.keys())
+        .chain(asm.map.clobbers.iter().map(|c| &c.data))
+        .map(|r| cvt_reg(*r))
+        .collect();
+
+    codegen_regload_block(b, &asm.map, s, c, |b, s| {
+        let operands = AsmOperands::allocate(&lines, &reserved, s)?;
+        operands.load(b);
+        for line in &lines {
+            b.instr(operands.expand(line, s)?);
+        }
+        operands.store(b);
+        Ok(())
+    })
+}
+
+/// Wraps the body of an asm or syscall block with its register bindings.
+/// Clobbered registers are saved below the current stack frame before the
+/// inputs are loaded and restored once the outputs are stored.
+fn codegen_regload_block(
+    b: &mut MipsBuilder,
+    map: &RegVars,
+    s: &Scope,
+    c: &mut ConstructCounter,
+    body: impl FnOnce(&mut MipsBuilder, &Scope) -> ValidationResult<()>,
 ) -> ValidationResult<()> {
-    codegen_regload_before(b, &asm.map.in_values, s)?;
-    for str_ind in &asm.asm_strings {
-        b.instr(strs.strings[str_ind.data].clone());
+    let clobber_size = (map.clobbers.len() as u32 * 4).div_ceil(STACK_ALIGN) * STACK_ALIGN;
+    c.note_stack_below(s.stack_shift() + clobber_size);
+    let clobber_addr = |index: usize| MipsAddress::RegisterOffset {
+        register: Register::StackPtr,
+        offset: index as i32 * 4,
+    };
+
+    if clobber_size > 0 {
+        b.add_const_i32(
+            Register::StackPtr,
+            Register::StackPtr,
+            -(clobber_size as i32),
+        );
+    }
+    for (index, reg) in map.clobbers.iter().enumerate() {
+        match cvt_reg(reg.data) {
+            GenericRegister::Regular(r) => b.save_word(r, clobber_addr(index)),
+            GenericRegister::Float(f) => b.save_f32(f, clobber_addr(index)),
+        }
+    }
+
+    let scope = s.shift_stack(clobber_size);
+    codegen_regload_before(b, &map.in_values, &scope)?;
+    body(b, &scope)?;
+    codegen_regload_after(b, &map.out_values, &scope)?;
+
+    for (index, reg) in map.clobbers.iter().enumerate() {
+        match cvt_reg(reg.data) {
+            GenericRegister::Regular(r) => b.load_word(r, clobber_addr(index)),
+            GenericRegister::Float(f) => b.load_f32(f, clobber_addr(index)),
+        }
+    }
+    if clobber_size > 0 {
+        b.add_const_i32(Register::StackPtr, Register::StackPtr, clobber_size as i32);
     }
-    codegen_regload_after(b, &asm.map.out_values, s)
+    Ok(())
 }
 
 /// Map AST node registers to real codegen registers
 fn cvt_reg(value: Reg) -> GenericRegister {
     match value {
-        Reg::A0 => GenericRegister::Regular(Register::A0),
-        Reg::A1 => GenericRegister::Regular(Register::A1),
-        Reg::A2 => GenericRegister::Regular(Register::A2),
-        Reg::A3 => GenericRegister::Regular(Register::A3),
-        Reg::F0 => GenericRegister::Float(FloatRegister::F0),
-        Reg::F12 => GenericRegister::Float(FloatRegister::F12),
-        Reg::V0 => GenericRegister::Regular(Register::V0),
+        Reg::V0 => Register::V0.into(),
+        Reg::V1 => Register::V1.into(),
+        Reg::A0 => Register::A0.into(),
+        Reg::A1 => Register::A1.into(),
+        Reg::A2 => Register::A2.into(),
+        Reg::A3 => Register::A3.into(),
+        Reg::T0 => Register::T0.into(),
+        Reg::T1 => Register::T1.into(),
+        Reg::T2 => Register::T2.into(),
+        Reg::T3 => Register::T3.into(),
+        Reg::T4 => Register::T4.into(),
+        Reg::T5 => Register::T5.into(),
+        Reg::T6 => Register::T6.into(),
+        Reg::T7 => Register::T7.into(),
+        Reg::T8 => Register::T8.into(),
+        Reg::T9 => Register::T9.into(),
+        Reg::S0 => Register::S0.into(),
+        Reg::S1 => Register::S1.into(),
+        Reg::S2 => Register::S2.into(),
+        Reg::S3 => Register::S3.into(),
+        Reg::S4 => Register::S4.into(),
+        Reg::S5 => Register::S5.into(),
+        Reg::S6 => Register::S6.into(),
+        Reg::S7 => Register::S7.into(),
+        Reg::F0 => FloatRegister::F0.into(),
+        Reg::F1 => FloatRegister::F1.into(),
+        Reg::F2 => FloatRegister::F2.into(),
+        Reg::F3 => FloatRegister::F3.into(),
+        Reg::F4 => FloatRegister::F4.into(),
+        Reg::F5 => FloatRegister::F5.into(),
+        Reg::F6 => FloatRegister::F6.into(),
+        Reg::F7 => FloatRegister::F7.into(),
+        Reg::F8 => FloatRegister::F8.into(),
+        Reg::F9 => FloatRegister::F9.into(),
+        Reg::F10 => FloatRegister::F10.into(),
+        Reg::F11 => FloatRegister::F11.into(),
+        Reg::F12 => FloatRegister::F12.into(),
+        Reg::F13 => FloatRegister::F13.into(),
+        Reg::F14 => FloatRegister::F14.into(),
+        Reg::F15 => FloatRegister::F15.into(),
+        Reg::F16 => FloatRegister::F16.into(),
+        Reg::F17 => FloatRegister::F17.into(),
+        Reg::F18 => FloatRegister::F18.into(),
+        Reg::F19 => FloatRegister::F19.into(),
+        Reg::F20 => FloatRegister::F20.into(),
+        Reg::F21 => FloatRegister::F21.into(),
+        Reg::F22 => FloatRegister::F22.into(),
+        Reg::F23 => FloatRegister::F23.into(),
+        Reg::F24 => FloatRegister::F24.into(),
+        Reg::F25 => FloatRegister::F25.into(),
+        Reg::F26 => FloatRegister::F26.into(),
+        Reg::F27 => FloatRegister::F27.into(),
+        Reg::F28 => FloatRegister::F28.into(),
+        Reg::F29 => FloatRegister::F29.into(),
+        Reg::F30 => FloatRegister::F30.into(),
+        Reg::F31 => FloatRegister::F31.into(),
     }
 }
 
 /// Scaffold for regload operations.
+/// Scalars can be bound wherever they are stored. Arrays and mem variables
+/// name a fixed address, so they can only be passed in by `fnc_array`.
 fn codegen_regload_apply(
     b: &mut MipsBuilder,
     vars: &RegisterMap,
     s: &Scope,
-    fnc_word: impl Fn(&mut MipsBuilder, Register, MipsAddress),
-    fnc_byte: impl Fn(&mut MipsBuilder, Register, MipsAddress),
+    fnc_int: impl Fn(&mut MipsBuilder, Register, MipsAddress, ExprType),
     fnc_f32: impl Fn(&mut MipsBuilder, FloatRegister, MipsAddress),
+    fnc_array: Option<fn(&mut MipsBuilder, Register, MipsAddress)>,
 ) -> ValidationResult<()> {
-    for (register, identifier) in vars {
-        let val = s.get_var(*identifier)?;
-        let reg = cvt_reg(*register);
-        match (reg, val) {
+    for (register, identifier) in vars.iter() {
+        let LocatedValue { loc, val } = s.get_var(*identifier)?;
+        let typ = match (&loc, &val) {
+            (ValLocation::RawAddr(_), _) | (_, DeclType::Array { .. }) => None,
+            (_, DeclType::Param(p)) => Some(ExprType::from(p.data.clone())),
+        };
+        match (cvt_reg(*register), typ, fnc_array) {
             (
                 GenericRegister::Float(f),
-                LocatedValue {
-                    loc: addr @ ValLocation::Stack(_),
-                    val:
-                        DeclType::Param(Located {
-                            data:
-                                ParamType {
-                                    param_type:
-                                        Located {
-                                            data: PrimitiveType::F32,
-                                            ..
-                                        },
-                                    indirection: 0,
-                                },
-                            ..
-                        }),
-                },
-            ) => {
-                fnc_f32(b, f, addr.into());
-            }
-            (
-                GenericRegister::Regular(r),
-                LocatedValue {
-                    loc: addr @ ValLocation::Stack(_),
-                    val:
-                        DeclType::Param(Located {
-                            data:
-                                ParamType {
-                                    param_type:
-                                        Located {
-                                            data: PrimitiveType::U8,
-                                            ..
-                                        },
-                                    indirection: 0,
-                                },
-                            ..
-                        }),
-                },
-            ) => {
-                fnc_byte(b, r, addr.into());
+                Some(ExprType {
+                    base: PrimitiveType::F32,
+                    indirection: 0,
+                }),
+                _,
+            ) => fnc_f32(b, f, loc.into()),
+            (GenericRegister::Regular(r), Some(typ), _)
+                if typ.indirection > 0 || typ.base != PrimitiveType::F32 =>
+            {
+                fnc_int(b, r, loc.into(), typ)
             }
-            (
-                GenericRegister::Regular(r),
-                LocatedValue {
-                    loc: addr @ ValLocation::Stack(_),
-                    val: DeclType::Param(_),
-                },
-            ) => fnc_word(b, r, addr.into()),
+            (GenericRegister::Regular(r), None, Some(array_fnc)) => array_fnc(b, r, loc.into()),
             _ => return Err(ValidationError::InvalidRegTransfer(*identifier, *register)),
         }
     }
@@ -188,15 +300,15 @@ fn codegen_regload_before(
         b,
         vars,
         s,
-        |b, reg, addr| {
-            b.load_word(reg, addr);
-        },
-        |b, reg, addr| {
-            b.load_byte(reg, addr);
+        |b, reg, addr, typ| {
+            load_int(b, typ, reg, addr);
         },
         |b, reg, addr| {
             b.load_f32(reg, addr);
         },
+        Some(|b, reg, addr| {
+            b.load_addr(reg, addr);
+        }),
     )
 }
 
@@ -211,53 +323,82 @@ fn codegen_regload_after(
         b,
         vars,
         s,
-        |b, reg, addr| {
-            b.save_word(reg, addr);
-        },
-        |b, reg, addr| {
-            b.save_byte(reg, addr);
+        |b, reg, addr, typ| {
+            save_int(b, typ, reg, addr);
         },
         |b, reg, addr| {
             b.save_f32(reg, addr);
         },
+        None,
     )
 }
 
-/// Codegen for function calls
+/// Target of a call
+pub enum Callee<'a> {
+    /// Sub called by name
+    Sub(&'a FnSignature),
+    /// Sub pointer with the signature at this index
+    Pointer(usize),
+}
+
+/// Names that aren't variables are called directly, anything else
+/// has to be a sub pointer
+pub fn resolve_callee<'a>(s: &Scope<'a>, function: &Expression) -> ValidationResult<Callee<'a>> {
+    match function {
+        Expression::Identifier(ident) if s.get_var(*ident).is_err() => {
+            Ok(Callee::Sub(s.get_fn(*ident)?))
+        }
+        function => match expr::value::get_expr_type(s, function)? {
+            ExprType {
+                base: PrimitiveType::Sub(sig),
+                indirection: 1,
+            } => Ok(Callee::Pointer(sig)),
+            _ => Err(ValidationError::NotAFunc(function.get_loc())),
+        },
+    }
+}
+
+/// Codegen for function calls.
+/// Every argument is evaluated into its stack slot first and the
+/// register arguments are loaded from there right before the jump.
 fn codegen_fnc_call(
     b: &mut MipsBuilder,
     invocation: &Invocation,
     caller_scope: &Scope,
+    p: &Program,
+    c: &mut ConstructCounter,
 ) -> ValidationResult<()> {
-    let fnc = caller_scope.get_fn(invocation.function)?;
-    let invoc_loc = invocation.function.loc;
-    if invocation.args.len() != fnc.len() {
+    let invoc_loc = invocation.function.get_loc();
+    let (signature, label) = match resolve_callee(caller_scope, &invocation.function)? {
+        Callee::Sub(fnc) => (Signature::from_args(&fnc.args), Some(fnc.label.as_str())),
+        Callee::Pointer(sig) => (p.signatures[sig].clone(), None),
+    };
+    let arg_types = &signature.args;
+    if invocation.args.len() != arg_types.len() {
         return Err(ValidationError::InvalidArgCount(invoc_loc));
     }
 
-    let arg_stack_size = fnc.len() * 4;
+    let arg_stack_size = arg_area_size(arg_types.len());
     b.add_const_i32(
         Register::StackPtr,
         Register::StackPtr,
-        (fnc.len() as i32) * -4,
+        -(arg_stack_size as i32),
     );
 
-    let scope = caller_scope.shift_stack(arg_stack_size as u32);
+    let scope = caller_scope.shift_stack(arg_stack_size);
+    let arg_addr = |index: usize| MipsAddress::RegisterOffset {
+        register: Register::StackPtr,
+        offset: index as i32 * 4,
+    };
 
     let mut bank = RegisterBank::default();
-    for (index, (arg_expr, fnc_type)) in invocation.args.iter().zip(fnc.iter()).enumerate() {
-        let expr = expr::codegen_expr(b, arg_expr, &scope, &mut bank)?;
-        let arg_expr = ExprType::from(fnc_type.field_type.data.clone());
-        if arg_expr != expr.computed_type {
+    for (index, (arg_expr, arg_type)) in invocation.args.iter().zip(arg_types).enumerate() {
+        let expr = expr::codegen_expr(b, arg_expr, &scope, &mut bank, c)?;
+        let arg_expr = ExprType::from(arg_type.clone());
+        if !scope.accepts_arg(arg_expr, expr.computed_type) {
             return Err(ValidationError::InvalidArgType(invoc_loc, index, arg_expr));
         }
 
-        let stack_offset = (fnc.len() - (index + 1)) * 4;
-        let addr = MipsAddress::RegisterOffset {
-            register: Register::StackPtr,
-            offset: stack_offset as i32,
-        };
-
         if let ExprType {
             indirection: 0,
             base: PrimitiveType::F32,
@@ -265,47 +406,70 @@ fn codegen_fnc_call(
         {
             let f_reg = expr.register.get_float()?;
             f_reg.use_reg(b, 0, AccessMode::Read, |b, f| {
-                b.save_f32(f, addr);
+                b.save_f32(f, arg_addr(index));
             });
         } else {
             let reg = expr.register.get_word()?;
             reg.use_reg(b, 0, AccessMode::Read, |b, r| {
-                b.save_word(r, addr);
+                b.save_word(r, arg_addr(index));
             });
         }
 
         bank.clear();
     }
 
-    let fn_name = get_fn_name(invocation.function.data);
-    b.jump_and_link(&fn_name);
+    // The sub pointer is loaded last so it isn't clobbered by the arguments
+    if label.is_none() {
+        let callee = codegen_expr(b, &invocation.function, &scope, &mut bank, c)?;
+        callee.register.get_word()?.load_to(b, EXPR_TEMP[0]);
+    }
+    for (index, register) in arg_registers(arg_types).into_iter().enumerate() {
+        match register {
+            Some(GenericRegister::Regular(r)) => b.load_word(r, arg_addr(index)),
+            Some(GenericRegister::Float(f)) => b.load_f32(f, arg_addr(index)),
+            None => {}
+        }
+    }
+
+    match label {
+        Some(label) => b.jump_and_link(label),
+        None => b.jump_and_link_register(EXPR_TEMP[0]),
+    }
+    b.add_const_i32(
+        Register::StackPtr,
+        Register::StackPtr,
+        arg_stack_size as i32,
+    );
 
     Ok(())
 }
 
 /// Generates the branch instructions for a given condition.
 /// Branches to fail_branch if expression is equal to zero.
-fn codegen_condition(
+pub fn codegen_condition(
     b: &mut MipsBuilder,
     e: &Expression,
     s: &Scope,
     bank: &mut RegisterBank,
+    c: &mut ConstructCounter,
     fail_branch: &str,
 ) -> ValidationResult<()> {
-    let expr = codegen_expr(b, e, s, bank)?;
-
-    if let ExprType {
-        indirection: 0,
-        base: PrimitiveType::F32,
-    } = expr.computed_type
-    {
-        return Err(ValidationError::FloatInCondition(e.get_loc()));
+    let expr = codegen_expr(b, e, s, bank, c)?;
+
+    match (expr.computed_type.base, expr.computed_type.indirection) {
+        (PrimitiveType::F32, 0) => return Err(ValidationError::FloatInCondition(e.get_loc())),
+        (PrimitiveType::Bool, 0) => {}
+        _ if s.options().strict_conditions => {
+            return Err(ValidationError::NonBoolCondition(e.get_loc()));
+        }
+        _ => {}
     }
 
     let reg = expr.register.get_word()?;
     reg.use_reg(b, 0, AccessMode::Read, |b, r| {
-        b.branch_eq_zero(r, &fail_branch);
+        b.branch_eq_zero(r, fail_branch);
     });
+    bank.free_reg(reg);
 
     Ok(())
 }
@@ -319,14 +483,14 @@ fn codegen_while(
     c: &mut ConstructCounter,
 ) -> ValidationResult<()> {
     let loop_id = c.start_loop();
-    let loop_start_lbl = get_while_lbl(loop_id);
-    let loop_end_lbl = get_while_end(loop_id);
+    let loop_start_lbl = get_while_lbl(&loop_id);
+    let loop_end_lbl = get_while_end(&loop_id);
 
     let mut bank = RegisterBank::default();
 
     b.new_block(loop_start_lbl.clone());
 
-    codegen_condition(b, &loop_block.condition, s, &mut bank, &loop_end_lbl)?;
+    codegen_condition(b, &loop_block.condition, s, &mut bank, c, &loop_end_lbl)?;
 
     codegen_block(b, &loop_block.body, s, p, c)?;
     b.branch(&loop_start_lbl);
@@ -348,29 +512,29 @@ fn codegen_logic_chain(
     c: &mut ConstructCounter,
 ) -> ValidationResult<()> {
     let if_id = c.new_if();
-    let if_lbl = get_if_lbl(if_id);
+    let if_lbl = get_if_lbl(&if_id);
 
     // Iterator for checking next label in logic chain
     let elif_lbls = (0..l.elifs.len())
-        .map(|i| get_elif_lbl(if_id, i))
+        .map(|i| get_elif_lbl(&if_id, i))
         .collect::<Vec<_>>();
-    let end_lbl = get_if_end(if_id);
-    let else_lbl = l.else_block.as_ref().map(|_| get_if_else(if_id));
+    let end_lbl = get_if_end(&if_id);
+    let else_lbl = l.else_block.as_ref().map(|_| get_if_else(&if_id));
     let mut lbl_iter = elif_lbls.iter().chain(else_lbl.iter());
     let mut next_lbl = || lbl_iter.next().unwrap_or(&end_lbl);
 
     let mut bank = RegisterBank::default();
 
     b.new_block(if_lbl);
-    codegen_condition(b, &l.if_block.condition, s, &mut bank, next_lbl())?;
+    codegen_condition(b, &l.if_block.condition, s, &mut bank, c, next_lbl())?;
 
     codegen_block(b, &l.if_block.body, s, p, c)?;
     b.branch(&end_lbl); // Go to end if body was run
 
     // Elifs
     for (index, elif) in l.elifs.iter().enumerate() {
-        b.new_block(get_elif_lbl(if_id, index));
-        codegen_condition(b, &elif.condition, s, &mut bank, next_lbl())?;
+        b.new_block(get_elif_lbl(&if_id, index));
+        codegen_condition(b, &elif.condition, s, &mut bank, c, next_lbl())?;
 
         codegen_block(b, &elif.body, s, p, c)?;
         b.branch(&end_lbl); // Go to end if body was run
@@ -378,7 +542,7 @@ fn codegen_logic_chain(
 
     // Generate else block
     if let Some(else_block) = &l.else_block {
-        b.new_block(get_if_else(if_id));
+        b.new_block(get_if_else(&if_id));
         codegen_block(b, else_block, s, p, c)?;
     }
 
@@ -0,0 +1,209 @@
+use deimos_ast::*;
+use mips_builder::{DataDef, DataDirective, MipsAddress, MipsBuilder};
+
+use crate::error::{ValidationError, ValidationResult};
+use crate::expr::temp::EXPR_TEMP;
+use crate::expr::{codegen_expr, RegisterBank};
+use crate::names::*;
+use crate::scope::{ConstructCounter, Scope};
+use crate::stmt::codegen_block;
+
+/// Switches need at least this many case values to use a jump table
+const JUMP_TABLE_MIN_CASES: usize = 4;
+/// Jump tables may have at most this many slots per case value
+const JUMP_TABLE_MAX_SPREAD: i64 = 2;
+/// Binary search ranges of this size or less are compared one by one
+const LINEAR_SEARCH_LEN: usize = 3;
+
+/// Value of a case label. The parser folds constant expressions, so a
+/// label is either a literal or a constant. Enum switches only take
+/// variants of the same enum.
+fn eval_case(expr: &Expression, typ: PrimitiveType, s: &Scope) -> ValidationResult<i64> {
+    let mismatched = ValidationError::MismatchedType(expr.get_loc());
+    let val = match expr {
+        Expression::Identifier(ident) => {
+            let (val, const_type) = s
+                .get_const(*ident)
+                .ok_or(ValidationError::NonConstCase(expr.get_loc()))?;
+            let const_type = const_type.param_type.data;
+            if (typ.is_enum() || const_type.is_enum()) && const_type != typ {
+                return Err(mismatched);
+            }
+            val
+        }
+        Expression::Primitive(_) if typ.is_enum() => return Err(mismatched),
+        Expression::Primitive(p) => p.data,
+        _ => return Err(ValidationError::NonConstCase(expr.get_loc())),
+    };
+    match val {
+        // Enum values are compared by their bits
+        PrimitiveValue::Int(i) if typ.is_enum() => Ok(i as u32 as i64),
+        PrimitiveValue::Int(i) => Ok(i as i64),
+        PrimitiveValue::Unsigned(u) => Ok(u as i64),
+        PrimitiveValue::Byte(b) => Ok(b as i64),
+        _ => Err(mismatched),
+    }
+}
+
+/// Range of values an integer type can hold
+fn type_range(typ: PrimitiveType) -> (i64, i64) {
+    let bits = typ.size() * 8;
+    if typ.is_signed() {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
+
+/// Collects the value of every case label sorted by value, along with
+/// the index of its case
+fn case_values(sw: &Switch, typ: PrimitiveType, s: &Scope) -> ValidationResult<Vec<(i64, usize)>> {
+    let (min, max) = type_range(typ);
+    let mut values = Vec::new();
+    for (index, case) in sw.cases.iter().enumerate() {
+        for expr in &case.values {
+            let val = eval_case(expr, typ, s)?;
+            if val < min || val > max {
+                return Err(ValidationError::MismatchedType(expr.get_loc()));
+            }
+            if values.iter().any(|(v, _)| *v == val) {
+                return Err(ValidationError::DuplicateCase(expr.get_loc()));
+            }
+            values.push((val, index));
+        }
+    }
+    values.sort();
+    Ok(values)
+}
+
+/// Jumps through a table of case labels indexed by the value minus the
+/// smallest case. Values outside the table go to the fallback.
+fn codegen_jump_table(
+    b: &mut MipsBuilder,
+    values: &[(i64, usize)],
+    switch_id: &ConstructId,
+    fallback: &str,
+) {
+    let (val, tmp) = (EXPR_TEMP[0], EXPR_TEMP[1]);
+    let min = values[0].0;
+    let max = values[values.len() - 1].0;
+
+    let mut labels = Vec::new();
+    let mut cases = values.iter().peekable();
+    for slot in min..=max {
+        match cases.next_if(|(v, _)| *v == slot) {
+            Some((_, index)) => labels.push(get_switch_case(switch_id, *index)),
+            None => labels.push(fallback.to_string()),
+        }
+    }
+    let table = get_switch_table(switch_id);
+    let mut def = DataDef::new(table.clone());
+    def.add_dir(DataDirective::Labels(labels));
+    b.add_def(def);
+
+    b.const_word(min as u32, tmp);
+    b.sub_u32(val, val, tmp);
+    b.const_word((max - min + 1) as u32, tmp);
+    b.branch_ge_u32(val, tmp, fallback);
+    b.shift_logical_left(val, val, 2);
+    b.load_addr(tmp, table.as_str());
+    b.add_u32(val, val, tmp);
+    b.load_word(val, MipsAddress::Register(val));
+    b.jump_register(val);
+}
+
+/// Binary search over the sorted case values
+fn codegen_search(
+    b: &mut MipsBuilder,
+    values: &[(i64, usize)],
+    signed: bool,
+    switch_id: &ConstructId,
+    fallback: &str,
+    search_count: &mut usize,
+) {
+    let (val, tmp) = (EXPR_TEMP[0], EXPR_TEMP[1]);
+    if values.len() <= LINEAR_SEARCH_LEN {
+        for (case_val, index) in values {
+            b.const_word(*case_val as u32, tmp);
+            b.branch_eq(val, tmp, &get_switch_case(switch_id, *index));
+        }
+        b.branch(fallback);
+        return;
+    }
+
+    let mid = values.len() / 2;
+    let (mid_val, mid_index) = values[mid];
+    let lower_lbl = get_switch_search(switch_id, *search_count);
+    *search_count += 1;
+
+    b.const_word(mid_val as u32, tmp);
+    b.branch_eq(val, tmp, &get_switch_case(switch_id, mid_index));
+    if signed {
+        b.branch_lt(val, tmp, &lower_lbl);
+    } else {
+        b.branch_lt_u32(val, tmp, &lower_lbl);
+    }
+    codegen_search(
+        b,
+        &values[mid + 1..],
+        signed,
+        switch_id,
+        fallback,
+        search_count,
+    );
+    b.new_block(lower_lbl);
+    codegen_search(b, &values[..mid], signed, switch_id, fallback, search_count);
+}
+
+/// Codegen for switch statement.
+/// Dense case values are dispatched through a jump table, sparse ones
+/// with a binary search
+pub fn codegen_switch(
+    b: &mut MipsBuilder,
+    sw: &Switch,
+    s: &Scope,
+    p: &Program,
+    c: &mut ConstructCounter,
+) -> ValidationResult<()> {
+    let switch_id = c.new_switch();
+    let end_lbl = get_switch_end(&switch_id);
+    let fallback = match sw.default {
+        Some(_) => get_switch_default(&switch_id),
+        None => end_lbl.clone(),
+    };
+
+    let mut bank = RegisterBank::default();
+    let expr = codegen_expr(b, &sw.value, s, &mut bank, c)?;
+    let typ = match expr.type_tuple() {
+        (typ, 0) if typ.is_int() || typ.is_enum() => typ,
+        _ => return Err(ValidationError::InvalidSwitchValue(sw.value.get_loc())),
+    };
+    let values = case_values(sw, typ, s)?;
+    expr.register.get_word()?.load_to(b, EXPR_TEMP[0]);
+
+    if values.is_empty() {
+        b.branch(&fallback);
+    } else {
+        let spread = values[values.len() - 1].0 - values[0].0 + 1;
+        if values.len() >= JUMP_TABLE_MIN_CASES
+            && spread <= values.len() as i64 * JUMP_TABLE_MAX_SPREAD
+        {
+            codegen_jump_table(b, &values, &switch_id, &fallback);
+        } else {
+            let signed = typ.is_signed();
+            codegen_search(b, &values, signed, &switch_id, &fallback, &mut 0);
+        }
+    }
+
+    for (index, case) in sw.cases.iter().enumerate() {
+        b.new_block(get_switch_case(&switch_id, index));
+        codegen_block(b, &case.body, s, p, c)?;
+        b.branch(&end_lbl);
+    }
+    if let Some(default) = &sw.default {
+        b.new_block(get_switch_default(&switch_id));
+        codegen_block(b, default, s, p, c)?;
+    }
+    b.new_block(end_lbl);
+    Ok(())
+}
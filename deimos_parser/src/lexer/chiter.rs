@@ -47,6 +47,11 @@ impl<'a> ChIter<'a> {
         self.peeker.peek().cloned()
     }
 
+    /// Next character that isn't whitespace, without advancing
+    pub fn peek_past_whitespace(&self) -> Option<char> {
+        self.peeker.clone().find(|c| !c.is_whitespace())
+    }
+
     pub fn next_if(&mut self, f: impl FnOnce(char) -> bool) -> Option<char> {
         match self.peek() {
             Some(c) if f(c) => self.next(),
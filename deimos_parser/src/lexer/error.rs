@@ -8,6 +8,8 @@ pub enum LexErrorKind {
     InvalidNumber,
     InvalidRegister,
     UnexpectedChar(char),
+    InvalidEscape,
+    InvalidCharLiteral,
 }
 impl LexErrorKind {
     pub fn with_loc(self, loc: Location) -> LexError {
@@ -22,6 +24,8 @@ impl Display for LexErrorKind {
             Self::InvalidNumber => write!(f, "Invalid number"),
             Self::InvalidRegister => write!(f, "Invalid register"),
             Self::UnexpectedChar(c) => write!(f, "Unexpected char '{}'", c),
+            Self::InvalidEscape => write!(f, "Invalid escape sequence"),
+            Self::InvalidCharLiteral => write!(f, "Invalid character literal"),
         }
     }
 }
@@ -14,7 +14,7 @@ macro_rules! keyword_map {
                 }
             }
 
-            pub fn from_str(s: &str) -> Option<Self> {
+            pub fn parse(s: &str) -> Option<Self> {
                 use self::$name::*;
                 match s {
                     $( $s => Some($field), )*
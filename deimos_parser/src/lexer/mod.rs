@@ -3,20 +3,59 @@ use deimos_ast::{Located, StringBank};
 mod chiter;
 mod error;
 mod macros;
+#[cfg(test)]
+mod tests;
 mod tokens;
 mod util;
 
 pub use error::*;
 pub use tokens::*;
 
+/// Decodes the escape sequence after a backslash into the byte it represents
+fn lex_escape(chars: &mut chiter::ChIter) -> LexResult<u8> {
+    let escape_loc = chars.get_loc();
+    let byte = match chars.expect_any()? {
+        'n' => b'\n',
+        't' => b'\t',
+        'r' => b'\r',
+        '0' => b'\0',
+        '\\' => b'\\',
+        '"' => b'"',
+        '\'' => b'\'',
+        'x' => {
+            let mut hex = String::new();
+            for _ in 0..2 {
+                match chars.next_if(|c| c.is_ascii_hexdigit()) {
+                    Some(c) => hex.push(c),
+                    None => return Err(LexErrorKind::InvalidEscape.with_loc(escape_loc)),
+                }
+            }
+            u8::from_str_radix(&hex, 16)
+                .map_err(|_| LexErrorKind::InvalidEscape.with_loc(escape_loc))?
+        }
+        _ => return Err(LexErrorKind::InvalidEscape.with_loc(escape_loc)),
+    };
+    Ok(byte)
+}
+
 #[derive(Default, Debug)]
 pub struct Tokens {
     pub lexemes: Vec<Located<Lexeme>>,
     pub bank: StringBank,
 }
 
+/// `len` is only a keyword where it's applied like a function, so it
+/// can still name variables and subs
+fn is_len_builtin(chars: &chiter::ChIter, prev: Option<&Located<Lexeme>>) -> bool {
+    let names_sub = matches!(
+        prev.map(|l| &l.data),
+        Some(Lexeme::Keyword(Keyword::Fn | Keyword::Call))
+    );
+    chars.peek_past_whitespace() == Some('(') && !names_sub
+}
+
 pub fn lex(s: &str) -> LexResult<Tokens> {
-    let mut chars = chiter::ChIter::new(&s);
+    let mut chars = chiter::ChIter::new(s);
     let mut bank = util::TempStringBank::default();
     let mut lexemes = Vec::new();
 
@@ -30,7 +69,9 @@ pub fn lex(s: &str) -> LexResult<Tokens> {
                     ident.push(c);
                 }
 
-                if let Some(k) = Keyword::from_str(&ident) {
+                if let Some(k) = Keyword::parse(&ident)
+                    .filter(|k| *k != Keyword::Len || is_len_builtin(&chars, lexemes.last()))
+                {
                     Lexeme::Keyword(k)
                 } else if let Some(p) = test_primitive(&ident) {
                     Lexeme::Primitive(p)
@@ -105,11 +146,13 @@ pub fn lex(s: &str) -> LexResult<Tokens> {
                             .map(Lexeme::Float)
                             .map_err(|_| LexErrorKind::InvalidNumber.with_loc(lexeme_loc))?
                     } else if chars.next_if_eq('u') {
-                        u32::from_str_radix(&num_buf, 16)
+                        num_buf
+                            .parse::<u32>()
                             .map(Lexeme::Unsigned)
                             .map_err(|_| LexErrorKind::InvalidNumber.with_loc(lexeme_loc))?
                     } else {
-                        i32::from_str_radix(&num_buf, 10)
+                        num_buf
+                            .parse::<i32>()
                             .map(Lexeme::Integer)
                             .map_err(|_| LexErrorKind::InvalidNumber.with_loc(lexeme_loc))?
                     }
@@ -117,12 +160,31 @@ pub fn lex(s: &str) -> LexResult<Tokens> {
             }
 
             // Parse operators
-            '+' => Lexeme::Plus,
-            '-' => Lexeme::Minus,
-            '*' => Lexeme::Multiply,
-            '/' => Lexeme::Divide,
-            '%' => Lexeme::Modulo,
-            '&' => Lexeme::Reference,
+            '+' if chars.next_if_eq('+') => Lexeme::Increment,
+            '-' if chars.next_if_eq('-') => Lexeme::Decrement,
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' => {
+                let next_eq = chars.next_if_eq('=');
+                match c {
+                    '+' if next_eq => Lexeme::PlusEq,
+                    '-' if next_eq => Lexeme::MinusEq,
+                    '*' if next_eq => Lexeme::MultiplyEq,
+                    '/' if next_eq => Lexeme::DivideEq,
+                    '%' if next_eq => Lexeme::ModuloEq,
+                    '&' if next_eq => Lexeme::AndEq,
+                    '|' if next_eq => Lexeme::OrEq,
+                    '^' if next_eq => Lexeme::XorEq,
+                    '+' => Lexeme::Plus,
+                    '-' => Lexeme::Minus,
+                    '*' => Lexeme::Multiply,
+                    '/' => Lexeme::Divide,
+                    '%' => Lexeme::Modulo,
+                    '&' => Lexeme::Reference,
+                    // '|' and '^' are only used in compound assignments
+                    _ => return Err(LexErrorKind::UnexpectedChar(c).with_loc(lexeme_loc)),
+                }
+            }
+
+            '?' => Lexeme::Question,
 
             // Parse comments
             '#' => {
@@ -130,6 +192,17 @@ pub fn lex(s: &str) -> LexResult<Tokens> {
                 continue;
             }
 
+            // Parse shifts
+            '<' | '>' if chars.next_if_eq(c) => {
+                let next_eq = chars.next_if_eq('=');
+                match c {
+                    '<' if next_eq => Lexeme::ShiftLeftEq,
+                    '>' if next_eq => Lexeme::ShiftRightEq,
+                    '<' => Lexeme::ShiftLeft,
+                    _ => Lexeme::ShiftRight,
+                }
+            }
+
             // Parse N or Neq operators
             '>' | '=' | '<' | '!' => {
                 let next_eq = chars.next_if_eq('=');
@@ -159,20 +232,30 @@ pub fn lex(s: &str) -> LexResult<Tokens> {
 
             // Parse string
             '"' => {
-                let mut s = String::new();
+                let mut s = Vec::new();
                 loop {
                     match chars.expect_any()? {
                         '"' => break,
-                        '\\' => {
-                            s.push('\\');
-                            s.push(chars.expect_any()?);
-                        }
-                        c => s.push(c),
+                        '\\' => s.push(lex_escape(&mut chars)?),
+                        c => s.extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
                     }
                 }
                 Lexeme::String(bank.get_string(s))
             }
 
+            // Parse character literal
+            '\'' => {
+                let byte = match chars.expect_any()? {
+                    '\\' => lex_escape(&mut chars)?,
+                    c if c.is_ascii() && c != '\'' => c as u8,
+                    _ => return Err(LexErrorKind::InvalidCharLiteral.with_loc(lexeme_loc)),
+                };
+                if !chars.next_if_eq('\'') {
+                    return Err(LexErrorKind::InvalidCharLiteral.with_loc(lexeme_loc));
+                }
+                Lexeme::Char(byte)
+            }
+
             // Parse groupers [], (), and {}
             '(' => Lexeme::GroupBegin(Grouper::Parenthesis),
             ')' => Lexeme::GroupEnd(Grouper::Parenthesis),
@@ -0,0 +1,86 @@
+use super::{lex, Keyword, Lexeme};
+
+/// Lexes a single literal and returns its lexeme
+fn literal(src: &str) -> Lexeme {
+    let tokens = lex(src).unwrap();
+    assert_eq!(tokens.lexemes.len(), 1, "{src} lexed to several tokens");
+    tokens.lexemes[0].data.clone()
+}
+
+#[test]
+fn unsigned_literals_are_decimal() {
+    assert_eq!(literal("16u"), Lexeme::Unsigned(16));
+    assert_eq!(literal("700u"), Lexeme::Unsigned(700));
+    assert_eq!(literal("4294967295u"), Lexeme::Unsigned(u32::MAX));
+}
+
+#[test]
+fn hex_unsigned_literals_are_hex() {
+    assert_eq!(literal("0x10u"), Lexeme::Unsigned(16));
+    assert_eq!(literal("0xffffffffu"), Lexeme::Unsigned(u32::MAX));
+}
+
+#[test]
+fn out_of_range_unsigned_literal_is_rejected() {
+    assert!(lex("4294967296u").is_err());
+}
+
+#[test]
+fn string_escapes_can_hold_any_byte() {
+    let tokens = lex(r#""a\x80\xff\x00""#).unwrap();
+    assert_eq!(tokens.lexemes[0].data, Lexeme::String(0));
+    assert_eq!(tokens.bank.strings[0], b"a\x80\xff\x00");
+}
+
+#[test]
+fn strings_keep_utf8_source_text() {
+    let tokens = lex("\"é\"").unwrap();
+    assert_eq!(tokens.bank.strings[0], "é".as_bytes());
+}
+
+#[test]
+fn len_is_a_keyword_only_when_applied() {
+    let is_len = |src: &str| -> Vec<bool> {
+        lex(src)
+            .unwrap()
+            .lexemes
+            .iter()
+            .filter_map(|l| match &l.data {
+                Lexeme::Keyword(Keyword::Len) => Some(true),
+                Lexeme::Identifier(_) => Some(false),
+                _ => None,
+            })
+            .collect()
+    };
+    assert_eq!(is_len("len (a)"), [true, false]);
+    assert_eq!(is_len("len = len + 1u"), [false, false]);
+    assert_eq!(
+        is_len("sub len(s: &u8) call len(s)"),
+        [false, false, false, false]
+    );
+}
+
+#[test]
+fn shifts_and_bitwise_assignments_are_single_tokens() {
+    let tokens = lex("a <<= b >> c < d ^= e >>= f << g |= h &= i").unwrap();
+    let ops: Vec<_> = tokens
+        .lexemes
+        .iter()
+        .map(|l| &l.data)
+        .filter(|l| !matches!(l, Lexeme::Identifier(_)))
+        .cloned()
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            Lexeme::ShiftLeftEq,
+            Lexeme::ShiftRight,
+            Lexeme::LessThan,
+            Lexeme::XorEq,
+            Lexeme::ShiftRightEq,
+            Lexeme::ShiftLeft,
+            Lexeme::OrEq,
+            Lexeme::AndEq,
+        ]
+    );
+}
@@ -7,44 +7,55 @@ pub fn test_primitive(s: &str) -> Option<PrimitiveType> {
         "u32" => PrimitiveType::U32,
         "f32" => PrimitiveType::F32,
         "u8" => PrimitiveType::U8,
+        "i8" => PrimitiveType::I8,
+        "i16" => PrimitiveType::I16,
+        "u16" => PrimitiveType::U16,
+        "bool" => PrimitiveType::Bool,
         _ => return None,
     })
 }
 
 pub fn test_register(s: &str) -> Option<Reg> {
-    Some(match s {
-        "a0" => Reg::A0,
-        "a1" => Reg::A1,
-        "a2" => Reg::A2,
-        "a3" => Reg::A3,
-        "v0" => Reg::V0,
-        "f0" => Reg::F0,
-        "f12" => Reg::F12,
-        _ => return None,
-    })
+    Reg::from_name(s)
 }
 
 keyword_map!(Keyword {
     Program -> "program",
     Fn -> "sub",
+    Extern -> "extern",
+    Pub -> "pub",
     Cast -> "as",
+    Len -> "len",
+    SizeOf -> "sizeof",
+    AlignOf -> "alignof",
     Call -> "call",
     Let -> "let",
     If -> "if",
     Elif -> "elif",
     Else -> "else",
+    Switch -> "switch",
+    Case -> "case",
+    Default -> "default",
     And -> "and",
     Or -> "or",
+    Xor -> "xor",
     Record -> "record",
     Print -> "print",
+    Printf -> "printf",
+    Fprintf -> "fprintf",
     Break -> "break",
     Continue -> "continue",
     Return -> "return",
     Syscall -> "syscall",
     In -> "in",
     Out -> "out",
+    Clobber -> "clobber",
     Static -> "static",
     Mem -> "mem",
+    Const -> "const",
+    Enum -> "enum",
+    True -> "true",
+    False -> "false",
     While -> "while",
     Asm -> "asm",
 });
@@ -64,6 +75,7 @@ pub enum Lexeme {
 
     Integer(i32),
     Unsigned(u32),
+    Char(u8),
     Float(f32),
     String(usize),
     Identifier(usize),
@@ -73,10 +85,26 @@ pub enum Lexeme {
     Multiply,
     Divide,
     Modulo,
+    ShiftLeft,
+    ShiftRight,
     Equals,
     Reference,
 
+    PlusEq,
+    MinusEq,
+    MultiplyEq,
+    DivideEq,
+    ModuloEq,
+    AndEq,
+    OrEq,
+    XorEq,
+    ShiftLeftEq,
+    ShiftRightEq,
+    Increment,
+    Decrement,
+
     Colon,
+    Question,
     Semicolon,
     Comma,
     Peroid,
@@ -10,15 +10,12 @@ pub fn is_powsign(c: char) -> bool {
 }
 
 pub fn is_regchar(c: char) -> bool {
-    match c {
-        '0'..='3' | 'a' | 'v' | 'f' => true,
-        _ => false,
-    }
+    c.is_ascii_lowercase() || c.is_ascii_digit()
 }
 
 #[derive(Default, Debug)]
 pub struct TempStringBank {
-    string_bank: HashMap<String, usize>,
+    string_bank: HashMap<Vec<u8>, usize>,
     ident_bank: HashMap<String, usize>,
 }
 
@@ -28,7 +25,7 @@ impl TempStringBank {
         *self.ident_bank.entry(s).or_insert(new_index)
     }
 
-    pub fn get_string(&mut self, s: String) -> usize {
+    pub fn get_string(&mut self, s: Vec<u8>) -> usize {
         let new_index = self.string_bank.len();
         *self.string_bank.entry(s).or_insert(new_index)
     }
@@ -43,7 +40,7 @@ impl From<TempStringBank> for StringBank {
         }
 
         let string_count = value.string_bank.len();
-        let mut strings = vec![String::new(); string_count];
+        let mut strings = vec![Vec::new(); string_count];
         for (s, index) in value.string_bank {
             strings[index] = s;
         }
@@ -0,0 +1,290 @@
+use super::{ParseError, ParseResult};
+use deimos_ast::*;
+use std::collections::HashMap;
+
+/// Values of the constants declared so far. Constant expressions
+/// are folded while parsing, so a constant has to be declared before
+/// it is referenced.
+#[derive(Default, Clone)]
+pub struct ConstTable {
+    values: HashMap<usize, PrimitiveValue>,
+    /// Declared types of the names in scope, used by `sizeof` and `len`
+    types: HashMap<usize, DeclType>,
+}
+
+impl ConstTable {
+    pub fn insert(&mut self, name: usize, val: PrimitiveValue, typ: &Located<ParamType>) {
+        self.insert_type(name, typ.clone().into());
+        self.values.insert(name, val);
+    }
+
+    /// Records the type of a variable, or of an enum for its name. A
+    /// variable shadows any constant with the same name.
+    pub fn insert_type(&mut self, name: usize, typ: DeclType) {
+        self.values.remove(&name);
+        self.types.insert(name, typ);
+    }
+
+    /// Evaluates an expression at compile time. The expression is typed
+    /// like codegen types it first, so enums only mix with their own
+    /// variants.
+    pub fn eval(&self, expr: &Expression) -> ParseResult<Located<PrimitiveValue>> {
+        type_of(expr, self)?;
+        self.fold(expr)
+    }
+
+    fn fold(&self, expr: &Expression) -> ParseResult<Located<PrimitiveValue>> {
+        let loc = expr.get_loc();
+        let val = match expr {
+            Expression::Primitive(p) => p.data,
+            Expression::Identifier(ident) => *self
+                .values
+                .get(&ident.data)
+                .ok_or(ParseError::NonConstExpr(ident.loc))?,
+            Expression::Len { array, loc } => match type_of(array, self)? {
+                DeclType::Array { dims, .. } => PrimitiveValue::Unsigned(dims[0].data),
+                DeclType::Param(_) => return Err(ParseError::InvalidLen(*loc)),
+            },
+            Expression::SizeOf { operand, .. } => {
+                let size = match operand {
+                    SizeOperand::Type(typ) => typ.data.size(),
+                    SizeOperand::Expr(expr) => type_of(expr, self)?.size(),
+                };
+                PrimitiveValue::Unsigned(size)
+            }
+            Expression::AlignOf { typ, .. } => PrimitiveValue::Unsigned(typ.data.align()),
+            Expression::Unary { operand, op } => {
+                let val = self.fold(operand)?.data;
+                eval_unary(val, *op)?
+            }
+            Expression::Binary { left, right, op } => {
+                let left = self.fold(left)?.data;
+                let right = self.fold(right)?.data;
+                eval_binary(left, right, *op)?
+            }
+            Expression::Cast { value, cast_type } => {
+                let val = self.fold(value)?.data;
+                eval_cast(val, cast_type).ok_or(ParseError::MismatchedConstType(loc))?
+            }
+            Expression::Ternary {
+                condition,
+                then_value,
+                else_value,
+            } => match self.fold(condition)?.data {
+                PrimitiveValue::Bool(true) => self.fold(then_value)?.data,
+                PrimitiveValue::Bool(false) => self.fold(else_value)?.data,
+                _ => return Err(ParseError::MismatchedConstType(condition.get_loc())),
+            },
+        };
+        Ok(Located::new(val, loc))
+    }
+
+    /// Evaluates an expression that must produce a word-sized integer
+    /// (array sizes, addresses)
+    pub fn eval_u32(&self, expr: &Expression) -> ParseResult<Located<u32>> {
+        let Located { data, loc } = self.eval(expr)?;
+        match data {
+            PrimitiveValue::Int(i) => u32::try_from(i)
+                .map(|u| Located::new(u, loc))
+                .map_err(|_| ParseError::NegativeConst(loc)),
+            PrimitiveValue::Unsigned(u) => Ok(Located::new(u, loc)),
+            PrimitiveValue::Byte(b) => Ok(Located::new(b as u32, loc)),
+            _ => Err(ParseError::MismatchedConstType(loc)),
+        }
+    }
+}
+
+impl TypeScope for ConstTable {
+    type Error = ParseError;
+
+    fn name_type(&self, name: Identifier) -> ParseResult<DeclType> {
+        self.types
+            .get(&name.data)
+            .cloned()
+            .ok_or(ParseError::NonConstExpr(name.loc))
+    }
+
+    fn var_type(&self, name: Identifier) -> ParseResult<DeclType> {
+        match self.values.contains_key(&name.data) {
+            true => Err(ParseError::NonConstExpr(name.loc)),
+            false => self.name_type(name),
+        }
+    }
+
+    fn sub_signature(&self, _name: Identifier) -> Option<usize> {
+        None
+    }
+}
+
+/// Converts an evaluated value to the declared type of a constant
+pub fn coerce_const(val: Located<PrimitiveValue>, typ: &ParamType) -> ParseResult<PrimitiveValue> {
+    use PrimitiveValue::*;
+
+    let coerced = match (typ.param_type.data, typ.indirection, val.data) {
+        (PrimitiveType::U8, 1, String(s)) => Some(String(s)),
+        (PrimitiveType::Bool, 0, Bool(b)) => Some(Bool(b)),
+        (PrimitiveType::U8, 0, Int(0..=255) | Unsigned(0..=255) | Byte(_))
+        | (PrimitiveType::I32 | PrimitiveType::U32, 0, Int(_) | Unsigned(_) | Byte(_))
+        | (PrimitiveType::Enum(_), 0, Int(_) | Unsigned(_))
+        | (PrimitiveType::F32, 0, Float(_) | Int(_) | Unsigned(_) | Byte(_)) => {
+            eval_cast(val.data, typ)
+        }
+        // Narrow types must be able to hold the value without wrapping
+        (
+            PrimitiveType::I8 | PrimitiveType::I16 | PrimitiveType::U16,
+            0,
+            Int(_) | Unsigned(_) | Byte(_),
+        ) => eval_cast(val.data, typ).filter(|c| int_value(*c) == int_value(val.data)),
+        _ => None,
+    };
+    coerced.ok_or(ParseError::MismatchedConstType(val.loc))
+}
+
+fn eval_unary(val: PrimitiveValue, op: Located<UnaryOp>) -> ParseResult<PrimitiveValue> {
+    match (op.data, val) {
+        (UnaryOp::Negation, PrimitiveValue::Int(i)) => Ok(PrimitiveValue::Int(i.wrapping_neg())),
+        (UnaryOp::Negation, PrimitiveValue::Float(f)) => Ok(PrimitiveValue::Float(-f)),
+        (UnaryOp::LogicNot, PrimitiveValue::Int(i)) => Ok(PrimitiveValue::Bool(i == 0)),
+        (UnaryOp::LogicNot, PrimitiveValue::Unsigned(u)) => Ok(PrimitiveValue::Bool(u == 0)),
+        (UnaryOp::LogicNot, PrimitiveValue::Byte(b)) => Ok(PrimitiveValue::Bool(b == 0)),
+        (UnaryOp::LogicNot, PrimitiveValue::Float(f)) => Ok(PrimitiveValue::Bool(f == 0.0)),
+        (UnaryOp::LogicNot, PrimitiveValue::Bool(b)) => Ok(PrimitiveValue::Bool(!b)),
+        (UnaryOp::Deref | UnaryOp::Reference, _) => Err(ParseError::NonConstExpr(op.loc)),
+        _ => Err(ParseError::MismatchedConstType(op.loc)),
+    }
+}
+
+fn eval_binary(
+    left: PrimitiveValue,
+    right: PrimitiveValue,
+    op: Located<BinaryOp>,
+) -> ParseResult<PrimitiveValue> {
+    use PrimitiveValue::*;
+
+    let div_zero = ParseError::ConstDivByZero(op.loc);
+    let val = match (op.data, left, right) {
+        (BinaryOp::IndexAccess, _, _) => return Err(ParseError::NonConstExpr(op.loc)),
+
+        (BinaryOp::Add, Int(l), Int(r)) => Int(l.wrapping_add(r)),
+        (BinaryOp::Sub, Int(l), Int(r)) => Int(l.wrapping_sub(r)),
+        (BinaryOp::Mult, Int(l), Int(r)) => Int(l.wrapping_mul(r)),
+        (BinaryOp::Div, Int(l), Int(r)) => Int(l.checked_div(r).ok_or(div_zero)?),
+        (BinaryOp::Mod, Int(l), Int(r)) => Int(l.checked_rem(r).ok_or(div_zero)?),
+        (BinaryOp::And, Int(l), Int(r)) => Int(l & r),
+        (BinaryOp::Or, Int(l), Int(r)) => Int(l | r),
+        (BinaryOp::Xor, Int(l), Int(r)) => Int(l ^ r),
+
+        (BinaryOp::Add, Unsigned(l), Unsigned(r)) => Unsigned(l.wrapping_add(r)),
+        (BinaryOp::Sub, Unsigned(l), Unsigned(r)) => Unsigned(l.wrapping_sub(r)),
+        (BinaryOp::Mult, Unsigned(l), Unsigned(r)) => Unsigned(l.wrapping_mul(r)),
+        (BinaryOp::Div, Unsigned(l), Unsigned(r)) => Unsigned(l.checked_div(r).ok_or(div_zero)?),
+        (BinaryOp::Mod, Unsigned(l), Unsigned(r)) => Unsigned(l.checked_rem(r).ok_or(div_zero)?),
+        (BinaryOp::And, Unsigned(l), Unsigned(r)) => Unsigned(l & r),
+        (BinaryOp::Or, Unsigned(l), Unsigned(r)) => Unsigned(l | r),
+        (BinaryOp::Xor, Unsigned(l), Unsigned(r)) => Unsigned(l ^ r),
+
+        (BinaryOp::Add, Byte(l), Byte(r)) => Byte(l.wrapping_add(r)),
+        (BinaryOp::Sub, Byte(l), Byte(r)) => Byte(l.wrapping_sub(r)),
+        (BinaryOp::Mult, Byte(l), Byte(r)) => Byte(l.wrapping_mul(r)),
+        (BinaryOp::Div, Byte(l), Byte(r)) => Byte(l.checked_div(r).ok_or(div_zero)?),
+        (BinaryOp::Mod, Byte(l), Byte(r)) => Byte(l.checked_rem(r).ok_or(div_zero)?),
+        (BinaryOp::And, Byte(l), Byte(r)) => Byte(l & r),
+        (BinaryOp::Or, Byte(l), Byte(r)) => Byte(l | r),
+        (BinaryOp::Xor, Byte(l), Byte(r)) => Byte(l ^ r),
+
+        (BinaryOp::And, Bool(l), Bool(r)) => Bool(l && r),
+        (BinaryOp::Or, Bool(l), Bool(r)) => Bool(l || r),
+        (BinaryOp::Xor, Bool(l), Bool(r)) => Bool(l != r),
+
+        // Like the MIPS shift instructions, only the low 5 bits of the
+        // shift amount are used
+        (BinaryOp::ShiftLeft | BinaryOp::ShiftRight, l, r) => {
+            let amount = int_value(r).ok_or(ParseError::MismatchedConstType(op.loc))? as u32 & 31;
+            let left = matches!(op.data, BinaryOp::ShiftLeft);
+            match l {
+                Int(l) if left => Int(l << amount),
+                Int(l) => Int(l >> amount),
+                Unsigned(l) if left => Unsigned(l << amount),
+                Unsigned(l) => Unsigned(l >> amount),
+                Byte(l) if left => Byte(((l as u32) << amount) as u8),
+                Byte(l) => Byte(((l as u32) >> amount) as u8),
+                _ => return Err(ParseError::MismatchedConstType(op.loc)),
+            }
+        }
+
+        (BinaryOp::Add, Float(l), Float(r)) => Float(l + r),
+        (BinaryOp::Sub, Float(l), Float(r)) => Float(l - r),
+        (BinaryOp::Mult, Float(l), Float(r)) => Float(l * r),
+        (BinaryOp::Div, Float(l), Float(r)) => Float(l / r),
+
+        (cmp, l, r) => {
+            let ordering = match (l, r) {
+                (Int(l), Int(r)) => l.partial_cmp(&r),
+                (Unsigned(l), Unsigned(r)) => l.partial_cmp(&r),
+                (Byte(l), Byte(r)) => l.partial_cmp(&r),
+                (Float(l), Float(r)) => l.partial_cmp(&r),
+                (Bool(l), Bool(r)) => l.partial_cmp(&r),
+                _ => return Err(ParseError::MismatchedConstType(op.loc)),
+            };
+            let result = match cmp {
+                BinaryOp::Equal => ordering.is_some_and(|o| o.is_eq()),
+                BinaryOp::NotEq => !ordering.is_some_and(|o| o.is_eq()),
+                BinaryOp::LessThan => ordering.is_some_and(|o| o.is_lt()),
+                BinaryOp::LessThanEq => ordering.is_some_and(|o| o.is_le()),
+                BinaryOp::GreaterThan => ordering.is_some_and(|o| o.is_gt()),
+                BinaryOp::GreaterThanEq => ordering.is_some_and(|o| o.is_ge()),
+                _ => return Err(ParseError::MismatchedConstType(op.loc)),
+            };
+            Bool(result)
+        }
+    };
+    Ok(val)
+}
+
+pub fn int_value(val: PrimitiveValue) -> Option<i64> {
+    match val {
+        PrimitiveValue::Int(i) => Some(i as i64),
+        PrimitiveValue::Unsigned(u) => Some(u as i64),
+        PrimitiveValue::Byte(b) => Some(b as i64),
+        _ => None,
+    }
+}
+
+/// Converts an integer to a value of a word-sized integer type, if it fits
+pub fn word_value(val: i64, typ: PrimitiveType) -> Option<PrimitiveValue> {
+    match typ {
+        PrimitiveType::I32 => i32::try_from(val).ok().map(PrimitiveValue::Int),
+        PrimitiveType::U32 => u32::try_from(val).ok().map(PrimitiveValue::Unsigned),
+        _ => None,
+    }
+}
+
+fn eval_cast(val: PrimitiveValue, typ: &ParamType) -> Option<PrimitiveValue> {
+    use PrimitiveValue::*;
+
+    if typ.indirection > 0 {
+        return None;
+    }
+    let word = match val {
+        Int(i) => i as u32,
+        Unsigned(u) => u,
+        Byte(b) => b as u32,
+        Float(f) => f as i32 as u32,
+        Bool(b) => b as u32,
+        String(_) => return None,
+    };
+    Some(match (typ.param_type.data, val) {
+        (PrimitiveType::Bool, Float(f)) => Bool(f != 0.0),
+        (PrimitiveType::Bool, _) => Bool(word != 0),
+        (PrimitiveType::F32, Float(f)) => Float(f),
+        (PrimitiveType::F32, Int(i)) => Float(i as f32),
+        (PrimitiveType::F32, _) => Float(word as f32),
+        (PrimitiveType::I32, _) => Int(word as i32),
+        (PrimitiveType::U32 | PrimitiveType::Enum(_), _) => Unsigned(word),
+        (PrimitiveType::U8, _) => Byte(word as u8),
+        (PrimitiveType::I8, _) => Int(word as i8 as i32),
+        (PrimitiveType::I16, _) => Int(word as i16 as i32),
+        (PrimitiveType::U16, _) => Unsigned(word as u16 as u32),
+        (PrimitiveType::Sub(_), _) => return None,
+    })
+}
@@ -1,12 +1,11 @@
 use crate::lexer::{Keyword, Lexeme};
-use deimos_ast::{Located, Location, Reg};
+use deimos_ast::{Located, Location, Reg, TypeError};
 use std::error::Error;
 use std::fmt::Display;
 
 #[derive(Debug)]
 pub enum ParseError {
     UnexpectedEOF,
-    NoBody,
     NakedExpression(Location),
     InvalidRedefinition(Located<usize>),
     BodyRedefinition(Location),
@@ -15,6 +14,16 @@ pub enum ParseError {
     UnexpectedToken(Located<Lexeme>),
     ReservedWord(Keyword),
     ExpectedRValue(Location),
+    NonConstExpr(Location),
+    MismatchedConstType(Location),
+    ConstDivByZero(Location),
+    NegativeConst(Location),
+    ArrayTooLarge(Location),
+    InvalidEnumType(Location),
+    EnumOverflow(Location),
+    InvalidLen(Location),
+    BareSubType(Location),
+    IncrementInExpr(Location),
 }
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -23,7 +32,6 @@ impl Display for ParseError {
             Self::UnexpectedToken(t) => write!(f, "Unexpected token {:?} at {}", t.data, t.loc),
             Self::InvalidOperation(l) => write!(f, "Invalid operation at {}", l),
             Self::ReservedWord(k) => write!(f, "Reserved word \"{:?}\"", k),
-            Self::NoBody => write!(f, "No program body"),
             Self::InvalidRedefinition(i) => write!(f, "Invalid redefinition at {}", i.loc),
             Self::BodyRedefinition(l) => write!(f, "Redefined body at {}", l),
             Self::DuplicateRegister(r) => write!(f, "Duplicate register {:?} at {}", r.data, r.loc),
@@ -33,9 +41,35 @@ impl Display for ParseError {
                 l
             ),
             Self::ExpectedRValue(l) => write!(f, "Expected RValue at {}", l),
+            Self::NonConstExpr(l) => {
+                write!(f, "Expression at {} can't be evaluated at compile time", l)
+            }
+            Self::MismatchedConstType(l) => {
+                write!(f, "Mismatched type in constant expression at {}", l)
+            }
+            Self::ConstDivByZero(l) => {
+                write!(f, "Division by zero in constant expression at {}", l)
+            }
+            Self::NegativeConst(l) => write!(f, "Constant at {} can't be negative", l),
+            Self::ArrayTooLarge(l) => write!(f, "Array at {} is too large", l),
+            Self::InvalidEnumType(l) => write!(f, "Enum at {} must be backed by i32 or u32", l),
+            Self::EnumOverflow(l) => write!(f, "Enum variant value at {} is out of range", l),
+            Self::InvalidLen(l) => write!(f, "len() at {} requires a fixed-size array", l),
+            Self::BareSubType(l) => write!(f, "Sub type at {} must be a pointer", l),
+            Self::IncrementInExpr(l) => write!(f, "'++' at {} can only be used as a statement", l),
         }
     }
 }
 impl Error for ParseError {}
 
+impl From<TypeError> for ParseError {
+    fn from(value: TypeError) -> Self {
+        match value {
+            TypeError::InvalidUnary(_, l)
+            | TypeError::InvalidBinary(_, l)
+            | TypeError::Mismatched(l) => Self::MismatchedConstType(l),
+        }
+    }
+}
+
 pub type ParseResult<T> = Result<T, ParseError>;
@@ -9,27 +9,53 @@ use crate::{
 
 mod operators;
 mod shunt;
+#[cfg(test)]
+mod tests;
 
 pub fn parse_param_type(tokens: &mut TokenIter) -> ParseResult<Located<ParamType>> {
     let mut indirection = 0usize;
     let mut loc = None;
-    loop {
+    let (prim, prim_loc) = loop {
         next_guard!(tokens(l) {
             Lexeme::Reference => {
                 indirection += 1;
                 loc.get_or_insert(l);
             },
-            Lexeme::Primitive(p) => {
-                let loc = loc.unwrap_or(l);
-                return Ok(Located::new(
-                    ParamType {
-                        param_type: Located::new(p, l),
-                        indirection,
-                    },
-                    loc,
-                ));
-            },
+            Lexeme::Primitive(p) => break (p, l),
+            // Any other name refers to an enum. Enums may be declared
+            // after they are used, so the name is checked during codegen.
+            Lexeme::Identifier(i) => break (PrimitiveType::Enum(i), l),
         });
+    };
+    Ok(Located::new(
+        ParamType {
+            param_type: Located::new(prim, prim_loc),
+            indirection,
+        },
+        loc.unwrap_or(prim_loc),
+    ))
+}
+
+/// Parses a type that makes up the whole token sequence
+fn parse_whole_type(mut tokens: TokenIter) -> ParseResult<Located<ParamType>> {
+    let typ = parse_param_type(&mut tokens)?;
+    match tokens.next() {
+        Some(t) => Err(ParseError::UnexpectedToken(t)),
+        None => Ok(typ),
+    }
+}
+
+/// Operands made of a primitive type behind any number of references
+/// are types, anything else is an expression
+fn parse_size_operand(tokens: TokenIter) -> ParseResult<SizeOperand> {
+    let mut lookahead = tokens.clone();
+    while lookahead.next_if_eq(Lexeme::Reference).is_some() {}
+    match lookahead.peek() {
+        Some(Located {
+            data: Lexeme::Primitive(_),
+            ..
+        }) => Ok(SizeOperand::Type(parse_whole_type(tokens)?)),
+        _ => Ok(SizeOperand::Expr(Box::new(parse_expression(tokens)?))),
     }
 }
 
@@ -46,6 +72,14 @@ pub fn parse_expression(mut tokens: TokenIter) -> ParseResult<Expression> {
                 let prim = Located::new(PrimitiveValue::Unsigned(i), token.loc);
                 stack.push_expr(Expression::Primitive(prim));
             }
+            Lexeme::Char(c) if stack.yield_unary() => {
+                let prim = Located::new(PrimitiveValue::Byte(c), token.loc);
+                stack.push_expr(Expression::Primitive(prim));
+            }
+            Lexeme::Keyword(k @ (Keyword::True | Keyword::False)) if stack.yield_unary() => {
+                let prim = Located::new(PrimitiveValue::Bool(k == Keyword::True), token.loc);
+                stack.push_expr(Expression::Primitive(prim));
+            }
             Lexeme::Float(f) if stack.yield_unary() => {
                 let prim = Located::new(PrimitiveValue::Float(f), token.loc);
                 stack.push_expr(Expression::Primitive(prim));
@@ -54,6 +88,36 @@ pub fn parse_expression(mut tokens: TokenIter) -> ParseResult<Expression> {
                 let prim = Located::new(PrimitiveValue::String(s), token.loc);
                 stack.push_expr(Expression::Primitive(prim));
             }
+            Lexeme::Keyword(Keyword::Len) if stack.yield_unary() => {
+                tokens.expect_begin(Grouper::Parenthesis)?;
+                let array = tokens
+                    .take_group(Grouper::Parenthesis)
+                    .and_then(parse_expression)?;
+                stack.push_expr(Expression::Len {
+                    array: Box::new(array),
+                    loc: token.loc,
+                });
+            }
+            Lexeme::Keyword(Keyword::SizeOf) if stack.yield_unary() => {
+                tokens.expect_begin(Grouper::Parenthesis)?;
+                let operand = tokens
+                    .take_group(Grouper::Parenthesis)
+                    .and_then(parse_size_operand)?;
+                stack.push_expr(Expression::SizeOf {
+                    operand,
+                    loc: token.loc,
+                });
+            }
+            Lexeme::Keyword(Keyword::AlignOf) if stack.yield_unary() => {
+                tokens.expect_begin(Grouper::Parenthesis)?;
+                let typ = tokens
+                    .take_group(Grouper::Parenthesis)
+                    .and_then(parse_whole_type)?;
+                stack.push_expr(Expression::AlignOf {
+                    typ,
+                    loc: token.loc,
+                });
+            }
             Lexeme::Identifier(i) if stack.yield_unary() => {
                 stack.push_expr(Expression::Identifier(Located::new(i, token.loc)));
             }
@@ -65,6 +129,21 @@ pub fn parse_expression(mut tokens: TokenIter) -> ParseResult<Expression> {
             Lexeme::Plus => stack.push_op(BinaryOp::Add, token.loc)?,
             Lexeme::Minus if stack.yield_unary() => stack.push_op(UnaryOp::Negation, token.loc)?,
             Lexeme::Minus => stack.push_op(BinaryOp::Sub, token.loc)?,
+            // `--` is lexed as one token, in an expression it's two minus signs
+            Lexeme::Decrement => {
+                let second = Location {
+                    col: token.loc.col + 1,
+                    ..token.loc
+                };
+                for loc in [token.loc, second] {
+                    if stack.yield_unary() {
+                        stack.push_op(UnaryOp::Negation, loc)?;
+                    } else {
+                        stack.push_op(BinaryOp::Sub, loc)?;
+                    }
+                }
+            }
+            Lexeme::Increment => return Err(ParseError::IncrementInExpr(token.loc)),
             Lexeme::Multiply => stack.push_op(BinaryOp::Mult, token.loc)?,
             Lexeme::Divide => stack.push_op(BinaryOp::Div, token.loc)?,
             Lexeme::Modulo => stack.push_op(BinaryOp::Mod, token.loc)?,
@@ -76,11 +155,16 @@ pub fn parse_expression(mut tokens: TokenIter) -> ParseResult<Expression> {
             Lexeme::LogicNotEq => stack.push_op(BinaryOp::NotEq, token.loc)?,
             Lexeme::Keyword(Keyword::And) => stack.push_op(BinaryOp::And, token.loc)?,
             Lexeme::Keyword(Keyword::Or) => stack.push_op(BinaryOp::Or, token.loc)?,
+            Lexeme::Keyword(Keyword::Xor) => stack.push_op(BinaryOp::Xor, token.loc)?,
+            Lexeme::ShiftLeft => stack.push_op(BinaryOp::ShiftLeft, token.loc)?,
+            Lexeme::ShiftRight => stack.push_op(BinaryOp::ShiftRight, token.loc)?,
             Lexeme::Keyword(Keyword::Cast) => {
                 let cast_type = parse_param_type(&mut tokens)?;
                 stack.push_op(Operator::Cast, token.loc)?;
                 stack.push_cast_type(cast_type.data);
             }
+            Lexeme::Question => stack.push_question(token.loc)?,
+            Lexeme::Colon => stack.push_colon(token.loc)?,
             Lexeme::GroupBegin(Grouper::Bracket) => {
                 stack.push_op(BinaryOp::IndexAccess, token.loc)?;
                 stack.push_open(Grouper::Bracket, token.loc);
@@ -5,6 +5,8 @@ pub enum Operator {
     Binary(BinaryOp),
     Unary(UnaryOp),
     Cast,
+    /// Applied once the `:` of a conditional expression is reached
+    Ternary,
 }
 
 impl From<BinaryOp> for Operator {
@@ -26,10 +28,13 @@ pub enum Precedence {
     Cast,
     MulDiv,
     AddSub,
+    Shift,
     Cmp,
     Eq,
     And,
+    Xor,
     Or,
+    Ternary,
 }
 
 impl Operator {
@@ -40,6 +45,7 @@ impl Operator {
             Operator::Unary(_) => Precedence::Unary,
             Operator::Binary(BinaryOp::Mult | BinaryOp::Div | BinaryOp::Mod) => Precedence::MulDiv,
             Operator::Binary(BinaryOp::Add | BinaryOp::Sub) => Precedence::AddSub,
+            Operator::Binary(BinaryOp::ShiftLeft | BinaryOp::ShiftRight) => Precedence::Shift,
             Operator::Binary(
                 BinaryOp::GreaterThan
                 | BinaryOp::GreaterThanEq
@@ -48,7 +54,15 @@ impl Operator {
             ) => Precedence::Cmp,
             Operator::Binary(BinaryOp::Equal | BinaryOp::NotEq) => Precedence::Eq,
             Operator::Binary(BinaryOp::And) => Precedence::And,
+            Operator::Binary(BinaryOp::Xor) => Precedence::Xor,
             Operator::Binary(BinaryOp::Or) => Precedence::Or,
+            Operator::Ternary => Precedence::Ternary,
         }
     }
+
+    /// Prefix unary operators and conditionals group right to left,
+    /// everything else groups left to right (`a - b - c` is `(a - b) - c`)
+    pub fn is_left_assoc(&self) -> bool {
+        !matches!(self, Operator::Unary(_) | Operator::Ternary)
+    }
 }
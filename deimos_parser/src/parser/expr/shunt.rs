@@ -10,6 +10,8 @@ use std::collections::VecDeque;
 #[derive(Debug)]
 enum OpStack {
     Open(Grouper),
+    /// `?` of a conditional waiting for its `:`
+    Question,
     Op(Operator),
 }
 
@@ -54,6 +56,7 @@ impl ShuntingStack {
             Operator::Binary(b) => self.apply_bin(b, loc),
             Operator::Unary(u) => self.apply_un(u, loc),
             Operator::Cast => self.apply_cast(loc),
+            Operator::Ternary => self.apply_ternary(loc),
         }
     }
 
@@ -101,16 +104,55 @@ impl ShuntingStack {
         }
     }
 
+    fn apply_ternary(&mut self, loc: Location) -> ParseResult<()> {
+        let else_value = self.operands.pop_back();
+        let then_value = self.operands.pop_back();
+        let condition = self.operands.pop_back();
+        if let (
+            Some(ExprStack::Expression(c)),
+            Some(ExprStack::Expression(t)),
+            Some(ExprStack::Expression(e)),
+        ) = (condition, then_value, else_value)
+        {
+            self.operands
+                .push_back(ExprStack::Expression(Expression::Ternary {
+                    condition: Box::new(c),
+                    then_value: Box::new(t),
+                    else_value: Box::new(e),
+                }));
+            Ok(())
+        } else {
+            Err(ParseError::InvalidOperation(loc))
+        }
+    }
+
     fn back_higher_prec(&self, o: &Operator) -> bool {
         self.operators
             .back()
             .filter(|b| match b.data {
-                OpStack::Op(op) => op.precedence() < o.precedence(),
+                OpStack::Op(op) => {
+                    op.precedence() < o.precedence()
+                        || (op.precedence() == o.precedence() && o.is_left_assoc())
+                }
                 _ => false,
             })
             .is_some()
     }
 
+    /// Applies the operators on top of the stack that bind tighter than `op`
+    fn apply_higher_prec(&mut self, op: &Operator, loc: Location) -> ParseResult<()> {
+        while self.back_higher_prec(op) {
+            if let Some(Located {
+                data: OpStack::Op(o),
+                ..
+            }) = self.operators.pop_back()
+            {
+                self.apply_op(o, loc)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Push operation onto op stack
     pub fn push_op(&mut self, o: impl Into<Operator>, loc: Location) -> ParseResult<()> {
         let op = o.into();
@@ -125,23 +167,52 @@ impl ShuntingStack {
             _ => {}
         }
 
-        while self.back_higher_prec(&op) {
-            if let Some(Located {
-                data: OpStack::Op(o),
-                ..
-            }) = self.operators.pop_back()
-            {
-                self.apply_op(o, loc)?;
-            }
-        }
+        self.apply_higher_prec(&op, loc)?;
         self.last_item = Some(match op {
-            Operator::Binary(_) | Operator::Cast => LastItem::BinOp,
+            Operator::Binary(_) | Operator::Cast | Operator::Ternary => LastItem::BinOp,
             Operator::Unary(_) => LastItem::UnOp,
         });
         self.operators.push_back(Located::new(OpStack::Op(op), loc));
         Ok(())
     }
 
+    /// Applies everything in the condition before pushing the `?`
+    pub fn push_question(&mut self, loc: Location) -> ParseResult<()> {
+        if self.yield_unary() {
+            return Err(ParseError::InvalidOperation(loc));
+        }
+        self.apply_higher_prec(&Operator::Ternary, loc)?;
+        self.operators
+            .push_back(Located::new(OpStack::Question, loc));
+        self.last_item = Some(LastItem::BinOp);
+        Ok(())
+    }
+
+    /// Closes the value chosen when the condition holds. The rest of the
+    /// expression is the value chosen otherwise.
+    pub fn push_colon(&mut self, loc: Location) -> ParseResult<()> {
+        if self.yield_unary() {
+            return Err(ParseError::InvalidOperation(loc));
+        }
+        loop {
+            match self.operators.pop_back() {
+                Some(Located {
+                    data: OpStack::Question,
+                    ..
+                }) => break,
+                Some(Located {
+                    data: OpStack::Op(o),
+                    ..
+                }) => self.apply_op(o, loc)?,
+                _ => return Err(ParseError::InvalidOperation(loc)),
+            }
+        }
+        self.operators
+            .push_back(Located::new(OpStack::Op(Operator::Ternary), loc));
+        self.last_item = Some(LastItem::BinOp);
+        Ok(())
+    }
+
     pub fn push_open(&mut self, g: Grouper, loc: Location) {
         self.operators
             .push_back(Located::new(OpStack::Open(g), loc));
@@ -185,6 +256,7 @@ impl ShuntingStack {
             let loc = op_item.loc;
             match op_item.data {
                 OpStack::Open(_) => Err(ParseError::UnexpectedEOF),
+                OpStack::Question => Err(ParseError::InvalidOperation(loc)),
                 OpStack::Op(op) => self.apply_op(op, loc),
             }?;
         }
@@ -0,0 +1,118 @@
+use super::parse_expression;
+use crate::lex;
+use crate::parser::iter::TokenIter;
+use crate::parser::ParseError;
+use deimos_ast::{Expression, StringBank};
+
+/// Parses an expression and writes it out with every operation
+/// parenthesized
+fn grouping(src: &str) -> String {
+    let tokens = lex(src).unwrap();
+    let expr = parse_expression(TokenIter::new(&tokens.lexemes)).unwrap();
+    show(&expr, &tokens.bank)
+}
+
+fn show(e: &Expression, bank: &StringBank) -> String {
+    match e {
+        Expression::Binary { left, right, op } => {
+            format!("({} {:?} {})", show(left, bank), op.data, show(right, bank))
+        }
+        Expression::Unary { operand, op } => format!("({:?} {})", op.data, show(operand, bank)),
+        Expression::Identifier(ident) => bank.identifiers[ident.data].clone(),
+        Expression::Primitive(p) => format!("{:?}", p.data),
+        Expression::Ternary {
+            condition,
+            then_value,
+            else_value,
+        } => format!(
+            "({} ? {} : {})",
+            show(condition, bank),
+            show(then_value, bank),
+            show(else_value, bank)
+        ),
+        _ => panic!("show can't write out {:?}", e),
+    }
+}
+
+#[test]
+fn binary_operators_group_left_to_right() {
+    assert_eq!(grouping("a - b - c"), "((a Sub b) Sub c)");
+    assert_eq!(grouping("a - b + c"), "((a Sub b) Add c)");
+    assert_eq!(grouping("a / b * c"), "((a Div b) Mult c)");
+    assert_eq!(grouping("a == b != c"), "((a Equal b) NotEq c)");
+    assert_eq!(grouping("a[b][c]"), "((a IndexAccess b) IndexAccess c)");
+}
+
+#[test]
+fn precedence_still_applies() {
+    assert_eq!(grouping("a - b * c"), "(a Sub (b Mult c))");
+    assert_eq!(grouping("a * b - c * d"), "((a Mult b) Sub (c Mult d))");
+}
+
+#[test]
+fn unary_operators_group_right_to_left() {
+    assert_eq!(grouping("- - a"), "(Negation (Negation a))");
+    assert_eq!(grouping("!-a"), "(LogicNot (Negation a))");
+    assert_eq!(grouping("a - -b"), "(a Sub (Negation b))");
+}
+
+#[test]
+fn double_minus_is_two_operators() {
+    assert_eq!(grouping("a--b"), "(a Sub (Negation b))");
+    assert_eq!(grouping("a---b"), "(a Sub (Negation (Negation b)))");
+    assert_eq!(grouping("--a"), "(Negation (Negation a))");
+}
+
+#[test]
+fn increment_is_not_an_operator() {
+    let tokens = lex("a++b").unwrap();
+    let result = parse_expression(TokenIter::new(&tokens.lexemes));
+    assert!(matches!(result, Err(ParseError::IncrementInExpr(_))));
+}
+
+#[test]
+fn shifts_bind_between_sums_and_comparisons() {
+    assert_eq!(grouping("a << b + c"), "(a ShiftLeft (b Add c))");
+    assert_eq!(
+        grouping("a >> b < c << d"),
+        "((a ShiftRight b) LessThan (c ShiftLeft d))"
+    );
+    assert_eq!(grouping("a << b >> c"), "((a ShiftLeft b) ShiftRight c)");
+}
+
+#[test]
+fn xor_binds_between_and_and_or() {
+    assert_eq!(grouping("a or b xor c and d"), "(a Or (b Xor (c And d)))");
+    assert_eq!(grouping("a and b xor c or d"), "(((a And b) Xor c) Or d)");
+}
+
+#[test]
+fn conditionals_bind_loosest() {
+    assert_eq!(
+        grouping("a == b ? c + d : e"),
+        "((a Equal b) ? (c Add d) : e)"
+    );
+    assert_eq!(
+        grouping("a or b ? c : d and e"),
+        "((a Or b) ? c : (d And e))"
+    );
+    assert_eq!(
+        grouping("-a ? *b : c[d]"),
+        "((Negation a) ? (Deref b) : (c IndexAccess d))"
+    );
+}
+
+#[test]
+fn conditionals_nest_to_the_right() {
+    assert_eq!(grouping("a ? b : c ? d : e"), "(a ? b : (c ? d : e))");
+    assert_eq!(grouping("a ? b ? c : d : e"), "(a ? (b ? c : d) : e)");
+    assert_eq!(grouping("(a ? b : c) ? d : e"), "((a ? b : c) ? d : e)");
+}
+
+#[test]
+fn conditional_needs_both_arms() {
+    let tokens = lex("a ? b").unwrap();
+    assert!(parse_expression(TokenIter::new(&tokens.lexemes)).is_err());
+    let tokens = lex("a : b").unwrap();
+    assert!(parse_expression(TokenIter::new(&tokens.lexemes)).is_err());
+}
@@ -8,7 +8,7 @@ macro_rules! next_guard {
                     loc: $loc_bind,
                 }) => $b,
             )*)*
-            Some(t) => return Err(crate::parser::ParseError::UnexpectedToken(t)),
+            Some(t) => return Err($crate::parser::ParseError::UnexpectedToken(t)),
             None => return Err($iter.eof_err()),
         }
     };
@@ -125,7 +125,7 @@ impl<'a> TokenIter<'a> {
             Some(Located {
                 data: Lexeme::Unsigned(i),
                 loc,
-            }) => Ok(Located::new(i as u32, loc)),
+            }) => Ok(Located::new(i, loc)),
             Some(t) => Err(ParseError::UnexpectedToken(t)),
             None => Err(self.eof_err()),
         }
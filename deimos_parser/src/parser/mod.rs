@@ -2,57 +2,146 @@ use crate::next_guard;
 
 use super::lexer::*;
 use deimos_ast::*;
+use std::collections::HashSet;
 
+mod consts;
 mod error;
 mod expr;
 mod iter;
+#[cfg(test)]
+mod tests;
 pub use error::*;
 
+use self::consts::ConstTable;
 use self::iter::TokenIter;
 
 pub fn parse(Tokens { lexemes, bank }: Tokens) -> ParseResult<Program> {
     let mut tokens = TokenIter::new(&lexemes);
     let mut definitions = Definitions::new();
     let mut fns = Vec::new();
+    let mut extern_fns = Vec::new();
     let mut static_vars = Vec::new();
     let mut mem_vars = Vec::new();
+    let mut consts = Vec::new();
+    let mut enums = Vec::new();
+    let mut signatures = Vec::new();
+    let mut public = HashSet::new();
+    let mut const_table = ConstTable::default();
     let mut body = None;
 
-    while let Some(token) = tokens.next() {
+    while let Some(mut token) = tokens.next() {
+        let is_public = token.data == Lexeme::Keyword(Keyword::Pub);
+        if is_public {
+            token = tokens
+                .expect_next(|t| matches!(t, Lexeme::Keyword(Keyword::Fn | Keyword::Static)))?;
+        }
         let (name, def) = match token.data {
             Lexeme::Keyword(Keyword::Fn) => {
                 let name = tokens.expect_ident()?;
-                let args = parse_fn_params(&mut tokens)?;
-                let block = parse_fn_body(&mut tokens)?;
+                let args = parse_fn_params(&mut tokens, &mut signatures)?;
+                let signature = intern_signature(&mut signatures, Signature::from_args(&args));
+                let mut fn_consts = const_table.clone();
+                for arg in &args {
+                    fn_consts.insert_type(arg.name.data, arg.field_type.clone().into());
+                }
+                let block = parse_fn_body(&mut tokens, fn_consts, &mut signatures)?;
                 let fn_id = fns.len();
-                fns.push(Function { name, args, block });
+                fns.push(Function {
+                    name,
+                    args,
+                    signature,
+                    block,
+                });
                 (name, Definition::Function(fn_id))
             }
+            Lexeme::Keyword(Keyword::Extern) => {
+                tokens.expect_next_eq(Lexeme::Keyword(Keyword::Fn))?;
+                let name = tokens.expect_ident()?;
+                let args = parse_fn_params(&mut tokens, &mut signatures)?;
+                let signature = intern_signature(&mut signatures, Signature::from_args(&args));
+                tokens.expect_semicolon()?;
+                let extern_id = extern_fns.len();
+                extern_fns.push(ExternFn {
+                    name,
+                    args,
+                    signature,
+                });
+                (name, Definition::Extern(extern_id))
+            }
             Lexeme::Keyword(Keyword::Record) => {
                 return Err(ParseError::ReservedWord(Keyword::Record))
             }
             Lexeme::Keyword(Keyword::Mem) => {
-                let addr = tokens.expect_group(Grouper::Parenthesis, |t| t.expect_int())?;
-                let var = parse_typed_ident(&mut tokens)?;
-                let name = var.name;
+                tokens.expect_begin(Grouper::Parenthesis)?;
+                let addr = tokens
+                    .take_group(Grouper::Parenthesis)
+                    .and_then(expr::parse_expression)
+                    .and_then(|e| const_table.eval_u32(&e))?;
+                let name = tokens.expect_ident()?;
+                tokens.expect_colon()?;
+                let variable = parse_decl_type(&mut tokens, &const_table, &mut signatures)?;
                 tokens.expect_semicolon()?;
+                const_table.insert_type(name.data, variable.clone());
                 let mem_id = mem_vars.len();
-                mem_vars.push(MemVar { addr, var });
+                mem_vars.push(MemVar {
+                    name,
+                    variable,
+                    addr,
+                });
                 (name, Definition::MemVar(mem_id))
             }
             Lexeme::Keyword(Keyword::Static) => {
-                let static_var = parse_fn_varinit(&mut tokens)?;
+                let static_var = parse_fn_varinit(&mut tokens, &const_table, &mut signatures)?;
                 tokens.expect_semicolon()?;
+                const_table.insert_type(static_var.name.data, static_var.variable.clone());
                 let name = static_var.name;
                 let static_id = static_vars.len();
                 static_vars.push(static_var);
                 (name, Definition::MemVar(static_id))
             }
+            Lexeme::Keyword(Keyword::Const) => {
+                let name = tokens.expect_ident()?;
+                tokens.expect_colon()?;
+                let const_type = expr::parse_param_type(&mut tokens)?;
+                tokens.expect_next_eq(Lexeme::Equals)?;
+                let value = tokens
+                    .until_level_eq(Lexeme::Semicolon)
+                    .and_then(expr::parse_expression)
+                    .and_then(|e| const_table.eval(&e))?;
+                let loc = value.loc;
+                let value = consts::coerce_const(value, &const_type.data)?;
+                const_table.insert(name.data, value, &const_type);
+                let const_id = consts.len();
+                consts.push(ConstDecl {
+                    name,
+                    const_type,
+                    value: Located::new(value, loc),
+                });
+                (name, Definition::Const(const_id))
+            }
+            Lexeme::Keyword(Keyword::Enum) => {
+                let (enum_decl, variants) = parse_enum(&mut tokens, &mut const_table)?;
+                for variant in variants {
+                    let const_def = Definition::Const(consts.len());
+                    if definitions.insert(variant.name.data, const_def).is_some() {
+                        return Err(ParseError::InvalidRedefinition(variant.name));
+                    }
+                    consts.push(variant);
+                }
+                let name = enum_decl.name;
+                let enum_id = enums.len();
+                enums.push(enum_decl);
+                (name, Definition::Enum(enum_id))
+            }
             Lexeme::Keyword(Keyword::Program) if body.is_some() => {
                 return Err(ParseError::BodyRedefinition(token.loc));
             }
             Lexeme::Keyword(Keyword::Program) => {
-                body = Some(parse_fn_body(&mut tokens)?);
+                body = Some(parse_fn_body(
+                    &mut tokens,
+                    const_table.clone(),
+                    &mut signatures,
+                )?);
                 continue;
             }
             _ => return Err(ParseError::UnexpectedToken(token)),
@@ -60,43 +149,179 @@ pub fn parse(Tokens { lexemes, bank }: Tokens) -> ParseResult<Program> {
         if definitions.insert(name.data, def).is_some() {
             return Err(ParseError::InvalidRedefinition(name));
         }
+        if is_public {
+            public.insert(name.data);
+        }
     }
 
-    body.map(|body| Program {
+    Ok(Program {
         bank,
         definitions,
+        public,
         fns,
+        extern_fns,
         static_vars,
         mem_vars,
+        consts,
+        enums,
+        signatures,
         body,
     })
-    .ok_or(ParseError::NoBody)
 }
 
-fn parse_decl_type(tokens: &mut TokenIter) -> ParseResult<DeclType> {
-    let base = expr::parse_param_type(tokens)?;
-    if tokens
+/// Parses an enum and declares its variants as constants of the enum
+/// type. A variant without a value follows the previous one, starting
+/// from 0.
+fn parse_enum(
+    tokens: &mut TokenIter,
+    const_table: &mut ConstTable,
+) -> ParseResult<(EnumDecl, Vec<ConstDecl>)> {
+    let name = tokens.expect_ident()?;
+    tokens.expect_colon()?;
+    let repr_type = expr::parse_param_type(tokens)?;
+    let repr = match &repr_type.data {
+        ParamType {
+            param_type:
+                repr @ Located {
+                    data: PrimitiveType::I32 | PrimitiveType::U32,
+                    ..
+                },
+            indirection: 0,
+        } => *repr,
+        _ => return Err(ParseError::InvalidEnumType(repr_type.loc)),
+    };
+    let enum_type = Located::new(
+        ParamType {
+            param_type: Located::new(PrimitiveType::Enum(name.data), name.loc),
+            indirection: 0,
+        },
+        name.loc,
+    );
+    const_table.insert_type(name.data, enum_type.clone().into());
+
+    let mut variants = Vec::new();
+    let mut consts = Vec::new();
+    let mut next = 0i64;
+    tokens.expect_begin(Grouper::Brace)?;
+    for mut variant in tokens.level_split_comma(Grouper::Brace)? {
+        let var_name = variant.expect_ident()?;
+        let value = if variant.next_if_eq(Lexeme::Equals).is_some() {
+            let value = const_table.eval(&expr::parse_expression(variant)?)?;
+            Located::new(consts::coerce_const(value, &repr_type.data)?, value.loc)
+        } else if let Some(t) = variant.next() {
+            return Err(ParseError::UnexpectedToken(t));
+        } else {
+            consts::word_value(next, repr.data)
+                .map(|v| Located::new(v, var_name.loc))
+                .ok_or(ParseError::EnumOverflow(var_name.loc))?
+        };
+        next = consts::int_value(value.data).map_or(next, |v| v + 1);
+        const_table.insert(var_name.data, value.data, &enum_type);
+        variants.push(var_name);
+        consts.push(ConstDecl {
+            name: var_name,
+            const_type: enum_type.clone(),
+            value,
+        });
+    }
+    let enum_decl = EnumDecl {
+        name,
+        repr,
+        variants,
+    };
+    Ok((enum_decl, consts))
+}
+
+/// Finds the index of a sub's signature, adding it if it's new
+fn intern_signature(signatures: &mut Vec<Signature>, signature: Signature) -> usize {
+    match signatures.iter().position(|s| s.type_eq(&signature)) {
+        Some(index) => index,
+        None => {
+            signatures.push(signature);
+            signatures.len() - 1
+        }
+    }
+}
+
+/// Parses the type of a variable or argument. Unlike types in
+/// expressions, these can be sub pointers like `&sub(i32, &u8)`.
+fn parse_var_type(
+    tokens: &mut TokenIter,
+    signatures: &mut Vec<Signature>,
+) -> ParseResult<Located<ParamType>> {
+    let mut lookahead = tokens.clone();
+    let mut indirection = 0;
+    while lookahead.next_if_eq(Lexeme::Reference).is_some() {
+        indirection += 1;
+    }
+    let Some(sub_loc) = lookahead.next_if_key(Keyword::Fn) else {
+        return expr::parse_param_type(tokens);
+    };
+    if indirection == 0 {
+        return Err(ParseError::BareSubType(sub_loc));
+    }
+    let loc = tokens.peek().map_or(sub_loc, |t| t.loc);
+    *tokens = lookahead;
+
+    let mut args = Vec::new();
+    tokens.expect_begin(Grouper::Parenthesis)?;
+    for mut arg in tokens.level_split_comma(Grouper::Parenthesis)? {
+        let arg_type = parse_var_type(&mut arg, signatures)?;
+        if let Some(t) = arg.next() {
+            return Err(ParseError::UnexpectedToken(t));
+        }
+        args.push(arg_type.data);
+    }
+    let signature = intern_signature(signatures, Signature { args });
+    let typ = ParamType {
+        param_type: Located::new(PrimitiveType::Sub(signature), sub_loc),
+        indirection,
+    };
+    Ok(Located::new(typ, loc))
+}
+
+fn parse_decl_type(
+    tokens: &mut TokenIter,
+    consts: &ConstTable,
+    signatures: &mut Vec<Signature>,
+) -> ParseResult<DeclType> {
+    let base = parse_var_type(tokens, signatures)?;
+    let mut dims = Vec::new();
+    while tokens
         .next_if_eq(Lexeme::GroupBegin(Grouper::Bracket))
         .is_some()
     {
-        let array_size = tokens.expect_int()?;
-        tokens.expect_end(Grouper::Bracket)?;
-        Ok(DeclType::Array {
-            array_type: base,
-            size: array_size,
-        })
-    } else {
-        Ok(DeclType::Param(base))
+        let dim = tokens
+            .take_group(Grouper::Bracket)
+            .and_then(expr::parse_expression)?;
+        dims.push(consts.eval_u32(&dim)?);
+    }
+    if dims.is_empty() {
+        return Ok(DeclType::Param(base));
+    }
+    let loc = base.loc;
+    let array = DeclType::Array {
+        array_type: base,
+        dims,
+    };
+    // Stack offsets are signed, so the array has to fit in an i32
+    match array.checked_size() {
+        Some(size) if size <= i32::MAX as u32 => Ok(array),
+        _ => Err(ParseError::ArrayTooLarge(loc)),
     }
 }
 
-fn parse_fn_varinit(tokens: &mut TokenIter) -> ParseResult<VarDecl> {
+fn parse_fn_varinit(
+    tokens: &mut TokenIter,
+    consts: &ConstTable,
+    signatures: &mut Vec<Signature>,
+) -> ParseResult<VarDecl> {
     let name = tokens.expect_ident()?;
     let mut init_val = None;
     tokens.expect_colon()?;
-    let var_type = parse_decl_type(tokens)?;
+    let var_type = parse_decl_type(tokens, consts, signatures)?;
     if tokens.next_if_eq(Lexeme::Equals).is_some() {
-        init_val = Some(parse_initval(tokens)?);
+        init_val = Some(parse_initval(tokens, consts)?);
     }
     Ok(VarDecl {
         variable: var_type,
@@ -105,22 +330,7 @@ fn parse_fn_varinit(tokens: &mut TokenIter) -> ParseResult<VarDecl> {
     })
 }
 
-fn parse_primitive_val(tokens: &mut TokenIter) -> ParseResult<Located<PrimitiveValue>> {
-    Ok(next_guard!(tokens (_loc) {
-        Lexeme::Minus => {
-            next_guard!(tokens (loc) {
-                Lexeme::Integer(i) => Located::new(PrimitiveValue::Int(i * -1), loc),
-                Lexeme::Float(f) => Located::new(PrimitiveValue::Float(f * -1.0), loc),
-            })
-        },
-        Lexeme::Integer(i) => Located::new(PrimitiveValue::Int(i), _loc),
-        Lexeme::Float(f) => Located::new(PrimitiveValue::Float(f), _loc),
-        Lexeme::String(s) => Located::new(PrimitiveValue::String(s), _loc),
-        Lexeme::Unsigned(u) => Located::new(PrimitiveValue::Unsigned(u), _loc),
-    }))
-}
-
-fn parse_initval(tokens: &mut TokenIter) -> ParseResult<Located<InitValue>> {
+fn parse_initval(tokens: &mut TokenIter, consts: &ConstTable) -> ParseResult<Located<InitValue>> {
     if let Some(list_loc) = tokens.next_if_eq(Lexeme::GroupBegin(Grouper::Bracket)) {
         let mut vals = Vec::new();
         loop {
@@ -130,7 +340,7 @@ fn parse_initval(tokens: &mut TokenIter) -> ParseResult<Located<InitValue>> {
             {
                 break;
             }
-            vals.push(parse_primitive_val(tokens)?);
+            vals.push(parse_initval(tokens, consts)?);
             next_guard!(tokens {
                 Lexeme::Comma => {},
                 Lexeme::GroupEnd(Grouper::Bracket) => break,
@@ -138,19 +348,23 @@ fn parse_initval(tokens: &mut TokenIter) -> ParseResult<Located<InitValue>> {
         }
         Ok(Located::new(InitValue::List(vals), list_loc))
     } else {
-        let Located { data, loc } = parse_primitive_val(tokens)?;
+        let expr = tokens.until_level(|t| {
+            matches!(
+                t,
+                Lexeme::Comma | Lexeme::Semicolon | Lexeme::GroupEnd(Grouper::Bracket)
+            )
+        })?;
+        // Leave the terminator for the caller
+        tokens.prev();
+        let Located { data, loc } = consts.eval(&expr::parse_expression(expr)?)?;
         Ok(Located::new(InitValue::Primitive(data), loc))
     }
 }
 
-fn parse_typed_ident(tokens: &mut TokenIter) -> ParseResult<TypedIdent> {
-    let name = tokens.expect_ident()?;
-    tokens.expect_colon()?;
-    let field_type = expr::parse_param_type(tokens)?;
-    Ok(TypedIdent { name, field_type })
-}
-
-fn parse_fn_params(tokens: &mut TokenIter) -> ParseResult<FunctionArgs> {
+fn parse_fn_params(
+    tokens: &mut TokenIter,
+    signatures: &mut Vec<Signature>,
+) -> ParseResult<FunctionArgs> {
     let mut args = Vec::new();
     tokens.expect_begin(Grouper::Parenthesis)?;
     loop {
@@ -158,7 +372,12 @@ fn parse_fn_params(tokens: &mut TokenIter) -> ParseResult<FunctionArgs> {
             Lexeme::GroupEnd(Grouper::Parenthesis) => break,
             Lexeme::Identifier(ident) => {
                 tokens.expect_colon()?;
-                let field_type = expr::parse_param_type(tokens)?;
+                let mut field_type = parse_var_type(tokens, signatures)?;
+                // Array parameters take a pointer to the first element
+                if tokens.next_if_eq(Lexeme::GroupBegin(Grouper::Bracket)).is_some() {
+                    tokens.expect_end(Grouper::Bracket)?;
+                    field_type.data.indirection += 1;
+                }
                 args.push(TypedIdent {
                     name: Located::new(ident, _loc),
                     field_type
@@ -195,6 +414,28 @@ fn parse_regmap(tokens: &mut TokenIter) -> ParseResult<RegisterMap> {
     Ok(map)
 }
 
+fn parse_clobbers(tokens: &mut TokenIter) -> ParseResult<Vec<Located<Reg>>> {
+    tokens.expect_begin(Grouper::Parenthesis)?;
+    let mut clobbers: Vec<Located<Reg>> = Vec::new();
+    loop {
+        next_guard!(tokens(_loc) {
+            Lexeme::GroupEnd(Grouper::Parenthesis) => break,
+            Lexeme::Register(reg) => {
+                let reg = Located::new(reg, _loc);
+                if clobbers.iter().any(|c| c.data == reg.data) {
+                    return Err(ParseError::DuplicateRegister(reg));
+                }
+                clobbers.push(reg);
+                next_guard!(tokens {
+                    Lexeme::Comma => {},
+                    Lexeme::GroupEnd(Grouper::Parenthesis) => break,
+                })
+            },
+        });
+    }
+    Ok(clobbers)
+}
+
 fn parse_inout_regmaps(tokens: &mut TokenIter) -> ParseResult<RegVars> {
     let mut vars = RegVars::default();
     if tokens.next_if_key(Keyword::In).is_some() {
@@ -207,6 +448,11 @@ fn parse_inout_regmaps(tokens: &mut TokenIter) -> ParseResult<RegVars> {
         vars.out_values = parse_regmap(tokens)?;
         tokens.expect_semicolon()?;
     }
+    if tokens.next_if_key(Keyword::Clobber).is_some() {
+        tokens.expect_colon()?;
+        vars.clobbers = parse_clobbers(tokens)?;
+        tokens.expect_semicolon()?;
+    }
     Ok(vars)
 }
 
@@ -239,22 +485,68 @@ fn parse_asm(tokens: &mut TokenIter) -> ParseResult<AsmBlock> {
     })
 }
 
-fn parse_assignment(tokens: &mut TokenIter) -> ParseResult<Assignment> {
-    let rtokens = tokens.until_level(|t| *t == Lexeme::Equals || *t == Lexeme::Semicolon)?;
-    if let Some(Located {
-        data: Lexeme::Semicolon,
-        loc,
-    }) = rtokens.get_end()
+/// Operator applied by a compound assignment such as `+=`
+fn compound_op(lex: &Lexeme) -> Option<BinaryOp> {
+    Some(match lex {
+        Lexeme::PlusEq => BinaryOp::Add,
+        Lexeme::MinusEq => BinaryOp::Sub,
+        Lexeme::MultiplyEq => BinaryOp::Mult,
+        Lexeme::DivideEq => BinaryOp::Div,
+        Lexeme::ModuloEq => BinaryOp::Mod,
+        Lexeme::AndEq => BinaryOp::And,
+        Lexeme::OrEq => BinaryOp::Or,
+        Lexeme::XorEq => BinaryOp::Xor,
+        Lexeme::ShiftLeftEq => BinaryOp::ShiftLeft,
+        Lexeme::ShiftRightEq => BinaryOp::ShiftRight,
+        _ => return None,
+    })
+}
+
+/// `x++` and `++x` are both statements that add one to `x`
+fn increment(end: &Lexeme, loc: Location, rvalue: Located<RValue>) -> Statement {
+    let op = match end {
+        Lexeme::Increment => BinaryOp::Add,
+        _ => BinaryOp::Sub,
+    };
+    Statement::Increment(Increment {
+        rvalue,
+        op: Located::new(op, loc),
+    })
+}
+
+fn parse_assignment(tokens: &mut TokenIter) -> ParseResult<Statement> {
+    if let Some(Located { data, loc }) =
+        tokens.next_if(|t| matches!(t, Lexeme::Increment | Lexeme::Decrement))
     {
-        return Err(ParseError::NakedExpression(*loc));
+        let rvalue = tokens
+            .until_level_eq(Lexeme::Semicolon)
+            .and_then(expr::parse_rvalue)?;
+        return Ok(increment(&data, loc, rvalue));
+    }
+    let rtokens = tokens.until_level(|t| {
+        matches!(
+            t,
+            Lexeme::Equals | Lexeme::Increment | Lexeme::Decrement | Lexeme::Semicolon
+        ) || compound_op(t).is_some()
+    })?;
+    let Some(Located { data: end, loc }) = rtokens.get_end().cloned() else {
+        return Err(tokens.eof_err());
+    };
+    if end == Lexeme::Semicolon {
+        return Err(ParseError::NakedExpression(loc));
     }
 
     let rvalue = expr::parse_rvalue(rtokens)?;
+    if let Lexeme::Increment | Lexeme::Decrement = end {
+        tokens.expect_semicolon()?;
+        return Ok(increment(&end, loc, rvalue));
+    }
     let lvalue = tokens
         .until_level_eq(Lexeme::Semicolon)
         .and_then(expr::parse_expression)?;
+    let op = compound_op(&end).map(|op| Located::new(op, loc));
 
-    Ok(Assignment { rvalue, lvalue })
+    Ok(Statement::Assignment(Assignment { rvalue, lvalue, op }))
 }
 
 fn parse_print(tokens: &mut TokenIter) -> ParseResult<Print> {
@@ -279,14 +571,50 @@ fn parse_print(tokens: &mut TokenIter) -> ParseResult<Print> {
     Ok(Print { args })
 }
 
-fn parse_block_until_end(tokens: &mut TokenIter) -> ParseResult<Block> {
+/// Parses `printf("fmt", args...)` or `fprintf(fd, "fmt", args...)`
+fn parse_printf(tokens: &mut TokenIter, has_fd: bool) -> ParseResult<Printf> {
+    tokens.expect_begin(Grouper::Parenthesis)?;
+    let mut fd = None;
+    if has_fd {
+        let fd_tokens = tokens.until_level_eq(Lexeme::Comma)?;
+        fd = Some(expr::parse_expression(fd_tokens)?);
+    }
+    let format = tokens.expect_string()?;
+    let mut args = Vec::new();
+    if tokens
+        .next_if_eq(Lexeme::GroupEnd(Grouper::Parenthesis))
+        .is_none()
+    {
+        tokens.expect_next_eq(Lexeme::Comma)?;
+        args = tokens
+            .level_split_comma(Grouper::Parenthesis)?
+            .into_iter()
+            .map(expr::parse_expression)
+            .collect::<ParseResult<Vec<Expression>>>()?;
+    }
+    tokens.expect_semicolon()?;
+    Ok(Printf { fd, format, args })
+}
+
+fn parse_block_until_end(tokens: &mut TokenIter, consts: &ConstTable) -> ParseResult<Block> {
     let mut block = Block::new();
     loop {
         let token = tokens.next().ok_or(tokens.eof_err())?;
         let stmt = match token.data {
             Lexeme::GroupEnd(Grouper::Brace) => break,
             Lexeme::Keyword(Keyword::Call) => {
-                let function = tokens.expect_ident()?;
+                let mut function = Expression::Identifier(tokens.expect_ident()?);
+                // Sub pointers can be called straight from an array
+                while let Some(loc) = tokens.next_if_eq(Lexeme::GroupBegin(Grouper::Bracket)) {
+                    let index = tokens
+                        .take_group(Grouper::Bracket)
+                        .and_then(expr::parse_expression)?;
+                    function = Expression::Binary {
+                        left: Box::new(function),
+                        right: Box::new(index),
+                        op: Located::new(BinaryOp::IndexAccess, loc),
+                    };
+                }
                 tokens.expect_next_eq(Lexeme::GroupBegin(Grouper::Parenthesis))?;
                 let args = tokens
                     .level_split_comma(Grouper::Parenthesis)?
@@ -298,14 +626,14 @@ fn parse_block_until_end(tokens: &mut TokenIter) -> ParseResult<Block> {
             }
             Lexeme::Keyword(Keyword::Syscall) => Statement::Syscall(parse_syscall(tokens)?),
             Lexeme::Keyword(Keyword::If) => {
-                let if_block = parse_condition_body(tokens)?;
+                let if_block = parse_condition_body(tokens, consts)?;
                 let mut elifs = Vec::new();
                 let mut else_block = None;
                 while tokens.next_if_key(Keyword::Elif).is_some() {
-                    elifs.push(parse_condition_body(tokens)?);
+                    elifs.push(parse_condition_body(tokens, consts)?);
                 }
                 if tokens.next_if_key(Keyword::Else).is_some() {
-                    else_block = Some(parse_block(tokens)?);
+                    else_block = Some(parse_block(tokens, consts)?);
                 }
                 Statement::LogicChain(LogicChain {
                     if_block,
@@ -313,7 +641,10 @@ fn parse_block_until_end(tokens: &mut TokenIter) -> ParseResult<Block> {
                     else_block,
                 })
             }
-            Lexeme::Keyword(Keyword::While) => Statement::While(parse_condition_body(tokens)?),
+            Lexeme::Keyword(Keyword::While) => {
+                Statement::While(parse_condition_body(tokens, consts)?)
+            }
+            Lexeme::Keyword(Keyword::Switch) => Statement::Switch(parse_switch(tokens, consts)?),
             Lexeme::Keyword(k @ Keyword::Break | k @ Keyword::Continue | k @ Keyword::Return) => {
                 tokens.expect_semicolon()?;
                 Statement::ControlBreak(Located::new(
@@ -328,9 +659,11 @@ fn parse_block_until_end(tokens: &mut TokenIter) -> ParseResult<Block> {
             }
             Lexeme::Keyword(Keyword::Asm) => Statement::Asm(parse_asm(tokens)?),
             Lexeme::Keyword(Keyword::Print) => Statement::Print(parse_print(tokens)?),
+            Lexeme::Keyword(Keyword::Printf) => Statement::Printf(parse_printf(tokens, false)?),
+            Lexeme::Keyword(Keyword::Fprintf) => Statement::Printf(parse_printf(tokens, true)?),
             _ => {
                 tokens.prev();
-                Statement::Assignment(parse_assignment(tokens)?)
+                parse_assignment(tokens)?
             }
         };
         block.push(Located::new(stmt, token.loc));
@@ -338,27 +671,84 @@ fn parse_block_until_end(tokens: &mut TokenIter) -> ParseResult<Block> {
     Ok(block)
 }
 
-fn parse_condition_body(tokens: &mut TokenIter) -> ParseResult<ConditionBody> {
+fn parse_condition_body(tokens: &mut TokenIter, consts: &ConstTable) -> ParseResult<ConditionBody> {
     tokens.expect_begin(Grouper::Parenthesis)?;
     let condition = tokens
         .take_group(Grouper::Parenthesis)
         .and_then(expr::parse_expression)?;
-    let body = parse_block(tokens)?;
+    let body = parse_block(tokens, consts)?;
     Ok(ConditionBody { condition, body })
 }
 
-fn parse_block(tokens: &mut TokenIter) -> ParseResult<Block> {
+/// Case values are folded here, except for names of constants and enum
+/// variants which codegen checks against the type of the switch value
+fn parse_switch(tokens: &mut TokenIter, consts: &ConstTable) -> ParseResult<Switch> {
+    tokens.expect_begin(Grouper::Parenthesis)?;
+    let value = tokens
+        .take_group(Grouper::Parenthesis)
+        .and_then(expr::parse_expression)?;
+
+    tokens.expect_begin(Grouper::Brace)?;
+    let mut cases = Vec::new();
+    let mut default = None;
+    loop {
+        next_guard!(tokens(_loc) {
+            Lexeme::GroupEnd(Grouper::Brace) => break,
+            Lexeme::Keyword(Keyword::Case) => {
+                let mut values = Vec::new();
+                loop {
+                    let tokens = tokens.until_level(|t| *t == Lexeme::Comma || *t == Lexeme::Colon)?;
+                    let end = tokens.get_end().cloned();
+                    let value = expr::parse_expression(tokens)?;
+                    values.push(match value {
+                        Expression::Identifier(_) => value,
+                        value => Expression::Primitive(consts.eval(&value)?),
+                    });
+                    if let Some(Located { data: Lexeme::Colon, .. }) = end {
+                        break;
+                    }
+                }
+                let body = parse_block(tokens, consts)?;
+                cases.push(SwitchCase { values, body });
+            },
+            Lexeme::Keyword(Keyword::Default) => {
+                if default.is_some() {
+                    return Err(ParseError::UnexpectedToken(Located::new(
+                        Lexeme::Keyword(Keyword::Default),
+                        _loc,
+                    )));
+                }
+                tokens.expect_colon()?;
+                default = Some(parse_block(tokens, consts)?);
+            },
+        });
+    }
+    Ok(Switch {
+        value,
+        cases,
+        default,
+    })
+}
+
+fn parse_block(tokens: &mut TokenIter, consts: &ConstTable) -> ParseResult<Block> {
     tokens.expect_begin(Grouper::Brace)?;
-    parse_block_until_end(tokens)
+    parse_block_until_end(tokens, consts)
 }
 
-fn parse_fn_body(tokens: &mut TokenIter) -> ParseResult<FunctionBlock> {
+/// Locals are added to the scope's constant table as they are declared,
+/// so later declarations can use their sizes
+fn parse_fn_body(
+    tokens: &mut TokenIter,
+    mut consts: ConstTable,
+    signatures: &mut Vec<Signature>,
+) -> ParseResult<FunctionBlock> {
     tokens.expect_begin(Grouper::Brace)?;
 
     let mut vars = Vec::new();
     if tokens.next_if_key(Keyword::Let).is_some() {
         loop {
-            let decl = parse_fn_varinit(tokens)?;
+            let decl = parse_fn_varinit(tokens, &consts, signatures)?;
+            consts.insert_type(decl.name.data, decl.variable.clone());
             vars.push(decl);
             next_guard!(tokens {
                 Lexeme::Semicolon => break,
@@ -367,6 +757,6 @@ fn parse_fn_body(tokens: &mut TokenIter) -> ParseResult<FunctionBlock> {
         }
     }
 
-    let block = parse_block_until_end(tokens)?;
+    let block = parse_block_until_end(tokens, &consts)?;
     Ok(FunctionBlock { vars, block })
 }
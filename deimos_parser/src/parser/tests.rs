@@ -0,0 +1,181 @@
+use super::{parse, ParseError, ParseResult};
+use crate::lex;
+use deimos_ast::{BinaryOp, DeclType, Expression, PrimitiveValue, Program, Statement};
+
+fn parse_src(src: &str) -> ParseResult<Program> {
+    parse(lex(src).unwrap())
+}
+
+/// Dimensions of the first local of the first sub
+fn local_dims(p: &Program) -> Vec<u32> {
+    match &p.fns[0].block.vars[0].variable {
+        DeclType::Array { dims, .. } => dims.iter().map(|d| d.data).collect(),
+        DeclType::Param(_) => vec![],
+    }
+}
+
+#[test]
+fn negative_array_size_is_rejected() {
+    let result = parse_src("const N: i32 = -4; static a: i32[N]; program {}");
+    assert!(matches!(result, Err(ParseError::NegativeConst(_))));
+}
+
+#[test]
+fn oversized_array_is_rejected() {
+    let result = parse_src("sub f() { let a: i32[65536][65536]; } program {}");
+    assert!(matches!(result, Err(ParseError::ArrayTooLarge(_))));
+    let result = parse_src("static a: u8[1024][1024][2048]; program {}");
+    assert!(matches!(result, Err(ParseError::ArrayTooLarge(_))));
+}
+
+#[test]
+fn negative_mem_address_is_rejected() {
+    let result = parse_src("mem(-4) a: &i32; program {}");
+    assert!(matches!(result, Err(ParseError::NegativeConst(_))));
+}
+
+#[test]
+fn constant_sizes_locals() {
+    let p = parse_src("const N: i32 = 4; sub f() { let a: i32[N * 2]; } program {}").unwrap();
+    assert_eq!(local_dims(&p), [8]);
+}
+
+#[test]
+fn parameter_shadows_constant() {
+    let result = parse_src("const N: u32 = 4; sub f(N: u32) { let a: i32[N]; } program {}");
+    assert!(matches!(result, Err(ParseError::NonConstExpr(_))));
+}
+
+#[test]
+fn local_shadows_constant() {
+    let result = parse_src("const N: u32 = 4; sub f() { let N: u32, a: i32[N]; } program {}");
+    assert!(matches!(result, Err(ParseError::NonConstExpr(_))));
+}
+
+#[test]
+fn shadowing_ends_with_the_sub() {
+    let p = parse_src(
+        "const N: u32 = 4;
+         sub f(N: u32) {}
+         sub g() { let a: i32[N]; }
+         program {}",
+    )
+    .unwrap();
+    assert_eq!(p.fns[1].block.vars[0].variable.size(), 16);
+}
+
+#[test]
+fn case_labels_are_folded() {
+    let p = parse_src(
+        "const N: i32 = 4;
+         sub f(x: i32) { switch (x) { case N: {} case N * 2 + 1, -N: {} } }
+         program {}",
+    )
+    .unwrap();
+    let Statement::Switch(sw) = &p.fns[0].block.block[0].data else {
+        panic!("expected a switch");
+    };
+    let values: Vec<_> = sw.cases[1]
+        .values
+        .iter()
+        .map(|v| match v {
+            Expression::Primitive(p) => p.data,
+            v => panic!("{v:?} wasn't folded"),
+        })
+        .collect();
+    assert!(matches!(
+        values[..],
+        [PrimitiveValue::Int(9), PrimitiveValue::Int(-4)]
+    ));
+    assert!(matches!(sw.cases[0].values[0], Expression::Identifier(_)));
+}
+
+#[test]
+fn case_labels_must_be_constant() {
+    let result = parse_src("sub f(x: i32) { switch (x) { case x + 1: {} } } program {}");
+    assert!(matches!(result, Err(ParseError::NonConstExpr(_))));
+}
+
+#[test]
+fn increments_go_before_or_after() {
+    let p = parse_src("sub f(x: i32) { x++; ++x; x--; --x; } program {}").unwrap();
+    let ops: Vec<_> = p.fns[0]
+        .block
+        .block
+        .iter()
+        .map(|stmt| match &stmt.data {
+            Statement::Increment(inc) => inc.op.data,
+            s => panic!("{s:?} isn't an increment"),
+        })
+        .collect();
+    assert!(matches!(
+        ops[..],
+        [BinaryOp::Add, BinaryOp::Add, BinaryOp::Sub, BinaryOp::Sub]
+    ));
+}
+
+#[test]
+fn compound_assignments_keep_their_operator() {
+    let p = parse_src(
+        "sub f(x: i32) {
+            x = 1; x += 1; x -= 1; x *= 2; x /= 2; x %= 3;
+            x &= 6; x |= 1; x ^= 5; x <<= 2; x >>= 1;
+        }
+        program {}",
+    )
+    .unwrap();
+    let ops: Vec<_> = p.fns[0]
+        .block
+        .block
+        .iter()
+        .map(|stmt| match &stmt.data {
+            Statement::Assignment(assign) => assign.op.map(|op| op.data),
+            s => panic!("{s:?} isn't an assignment"),
+        })
+        .collect();
+    assert!(matches!(
+        ops[..],
+        [
+            None,
+            Some(BinaryOp::Add),
+            Some(BinaryOp::Sub),
+            Some(BinaryOp::Mult),
+            Some(BinaryOp::Div),
+            Some(BinaryOp::Mod),
+            Some(BinaryOp::And),
+            Some(BinaryOp::Or),
+            Some(BinaryOp::Xor),
+            Some(BinaryOp::ShiftLeft),
+            Some(BinaryOp::ShiftRight),
+        ]
+    ));
+}
+
+#[test]
+fn bitwise_constants_are_folded() {
+    let p = parse_src(
+        "const N: i32 = 1 << 4 xor 3;
+         const M: u32 = 0xf0u >> 4 or 0x100u;
+         sub f() { let a: i32[N][M][-64 >> 4 and 7]; }
+         program {}",
+    )
+    .unwrap();
+    assert_eq!(local_dims(&p), [19, 271, 4]);
+}
+
+#[test]
+fn constants_are_typed_before_folding() {
+    for src in [
+        "enum E: u32 { A = 1 } const X: bool = A == 1u; program {}",
+        "enum E: u32 { A = 1 } enum F: u32 { B = 1 } const X: bool = A == B; program {}",
+        "const X: u32 = 1u + 2; program {}",
+        "enum E: u32 { A = 1 } program { let n: u32 = A + 1u; }",
+    ] {
+        let result = parse_src(src);
+        assert!(
+            matches!(result, Err(ParseError::MismatchedConstType(_))),
+            "{src}"
+        );
+    }
+    parse_src("enum E: u32 { A = 1 } const X: bool = A == A; program {}").unwrap();
+}
@@ -0,0 +1,931 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::register_number;
+
+const ZERO: u32 = 0;
+const AT: u32 = 1;
+const RA: u32 = 31;
+
+const SPECIAL: u32 = 0x00;
+const REGIMM: u32 = 0x01;
+const COP1: u32 = 0x11;
+const SPECIAL2: u32 = 0x1c;
+
+const FMT_S: u32 = 0x10;
+const FMT_W: u32 = 0x14;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    UnknownInstruction(String),
+    InvalidOperands(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    /// Branch or jump target out of reach of the instruction
+    OutOfRange(String),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownInstruction(instr) => write!(f, "No encoding for '{}'", instr),
+            Self::InvalidOperands(instr) => write!(f, "Invalid operands in '{}'", instr),
+            Self::UndefinedLabel(label) => write!(f, "Undefined label '{}'", label),
+            Self::DuplicateLabel(label) => write!(f, "Label '{}' is defined twice", label),
+            Self::OutOfRange(label) => write!(f, "Label '{}' is out of range", label),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+pub type EncodeResult<T> = Result<T, EncodeError>;
+
+fn find_label(labels: &HashMap<String, u32>, label: &str) -> EncodeResult<u32> {
+    labels
+        .get(label)
+        .copied()
+        .ok_or_else(|| EncodeError::UndefinedLabel(label.to_string()))
+}
+
+/// Address an instruction refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Label(String, i32),
+    Addr(u32),
+}
+
+impl Target {
+    fn resolve(&self, labels: &HashMap<String, u32>) -> EncodeResult<u32> {
+        match self {
+            Self::Label(label, offset) => {
+                Ok(find_label(labels, label)?.wrapping_add_signed(*offset))
+            }
+            Self::Addr(addr) => Ok(*addr),
+        }
+    }
+}
+
+/// Machine word whose label references aren't resolved yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Word {
+    Fixed(u32),
+    /// Branch to a label, relative to the next instruction
+    Branch(u32, String),
+    /// Jump to a label in the same 256MB region
+    Jump(u32, String),
+    /// Upper half of an address. Rounded up if `adjust` is set, since
+    /// the instruction using the lower half sign extends it.
+    High {
+        word: u32,
+        target: Target,
+        adjust: bool,
+    },
+    /// Lower half of an address
+    Low(u32, Target),
+}
+
+impl Word {
+    /// Fills in the label addresses for a word placed at `pc`
+    pub fn resolve(&self, pc: u32, labels: &HashMap<String, u32>) -> EncodeResult<u32> {
+        match self {
+            Self::Fixed(word) => Ok(*word),
+            Self::Branch(word, label) => {
+                let offset = (find_label(labels, label)? as i64 - pc as i64 - 4) / 4;
+                if offset < i16::MIN as i64 || offset > i16::MAX as i64 {
+                    return Err(EncodeError::OutOfRange(label.clone()));
+                }
+                Ok(word | (offset as u32 & 0xffff))
+            }
+            Self::Jump(word, label) => {
+                let addr = find_label(labels, label)?;
+                if (addr ^ pc.wrapping_add(4)) & 0xf000_0000 != 0 {
+                    return Err(EncodeError::OutOfRange(label.clone()));
+                }
+                Ok(word | ((addr >> 2) & 0x03ff_ffff))
+            }
+            Self::High {
+                word,
+                target,
+                adjust,
+            } => {
+                let addr = target.resolve(labels)?;
+                let addr = if *adjust {
+                    addr.wrapping_add(0x8000)
+                } else {
+                    addr
+                };
+                Ok(word | (addr >> 16))
+            }
+            Self::Low(word, target) => Ok(word | (target.resolve(labels)? & 0xffff)),
+        }
+    }
+
+    /// Branches and jumps, which have a delay slot on real hardware
+    pub fn is_transfer(&self) -> bool {
+        match self {
+            Self::Branch(..) | Self::Jump(..) => true,
+            // jr and jalr
+            Self::Fixed(word) => word >> 26 == SPECIAL && matches!(word & 0x3f, 0x08 | 0x09),
+            _ => false,
+        }
+    }
+}
+
+fn r_type(rs: u32, rt: u32, rd: u32, shamt: u32, funct: u32) -> u32 {
+    (SPECIAL << 26) | (rs << 21) | (rt << 16) | (rd << 11) | (shamt << 6) | funct
+}
+
+fn i_type(op: u32, rs: u32, rt: u32, imm: u32) -> u32 {
+    (op << 26) | (rs << 21) | (rt << 16) | (imm & 0xffff)
+}
+
+fn fr_type(fmt: u32, ft: u32, fs: u32, fd: u32, funct: u32) -> u32 {
+    (COP1 << 26) | (fmt << 21) | (ft << 16) | (fs << 11) | (fd << 6) | funct
+}
+
+fn parse_float_reg(s: &str) -> Option<u32> {
+    let index = s.trim().strip_prefix("$f")?;
+    index.parse().ok().filter(|r| *r < 32)
+}
+
+/// Parses a decimal or hex integer that fits in 32 bits, signed or not
+fn parse_int(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse().ok()?,
+        None => return None,
+    };
+    let value = if negative { -value } else { value };
+    (i32::MIN as i64..=u32::MAX as i64)
+        .contains(&value)
+        .then_some(value)
+}
+
+fn is_label(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Splits a leading `label:` off a line of assembly
+pub fn split_label(line: &str) -> (Option<&str>, &str) {
+    let code = line.split('#').next().unwrap_or("");
+    match code.split_once(':') {
+        Some((label, rest)) if is_label(label.trim()) => (Some(label.trim()), rest),
+        _ => (None, line),
+    }
+}
+
+fn fits_i16(value: i64) -> bool {
+    (i16::MIN as i64..=i16::MAX as i64).contains(&value)
+}
+
+/// Memory operand such as `label+4($t0)`
+struct Address {
+    target: Option<Target>,
+    offset: i64,
+    base: Option<u32>,
+}
+
+impl Address {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (outer, base) = match s.find('(') {
+            Some(open) => {
+                let inner = s[open + 1..].strip_suffix(')')?;
+                (s[..open].trim(), Some(register_number(inner)?))
+            }
+            None => (s, None),
+        };
+        if outer.is_empty() {
+            return Some(Self {
+                target: None,
+                offset: 0,
+                base,
+            });
+        }
+        if let Some(offset) = parse_int(outer) {
+            return Some(Self {
+                target: None,
+                offset,
+                base,
+            });
+        }
+        let (label, offset) = match outer.find(['+', '-']) {
+            Some(sign) => (outer[..sign].trim(), parse_int(&outer[sign..])?),
+            None => (outer, 0),
+        };
+        let offset = i32::try_from(offset).ok()?;
+        is_label(label).then(|| Self {
+            target: Some(Target::Label(label.to_string(), offset)),
+            offset: 0,
+            base,
+        })
+    }
+
+    /// Base register and offset, if the address can be used directly
+    /// by a load or store
+    fn direct(&self) -> Option<(u32, u32)> {
+        match self.target {
+            None if fits_i16(self.offset) => Some((self.base.unwrap_or(ZERO), self.offset as u32)),
+            _ => None,
+        }
+    }
+
+    /// Absolute part of the address that has to be built in a register
+    fn target(&self) -> Target {
+        match &self.target {
+            Some(target) => target.clone(),
+            None => Target::Addr(self.offset as u32),
+        }
+    }
+}
+
+/// Operands of an instruction, split at the commas
+struct Operands<'a> {
+    text: &'a str,
+    ops: Vec<&'a str>,
+}
+
+impl<'a> Operands<'a> {
+    fn invalid(&self) -> EncodeError {
+        EncodeError::InvalidOperands(self.text.to_string())
+    }
+
+    fn count(&self, count: usize) -> EncodeResult<()> {
+        if self.ops.len() == count {
+            Ok(())
+        } else {
+            Err(self.invalid())
+        }
+    }
+
+    fn get(&self, i: usize) -> EncodeResult<&'a str> {
+        self.ops.get(i).copied().ok_or_else(|| self.invalid())
+    }
+
+    fn reg(&self, i: usize) -> EncodeResult<u32> {
+        register_number(self.get(i)?).ok_or_else(|| self.invalid())
+    }
+
+    fn float_reg(&self, i: usize) -> EncodeResult<u32> {
+        parse_float_reg(self.get(i)?).ok_or_else(|| self.invalid())
+    }
+
+    /// Immediate that fits in `bits` bits, as signed or unsigned
+    fn imm(&self, i: usize, bits: u32, signed: bool) -> EncodeResult<u32> {
+        let value = parse_int(self.get(i)?).ok_or_else(|| self.invalid())?;
+        let fits = match signed {
+            true => (-(1 << (bits - 1))..1 << (bits - 1)).contains(&value),
+            false => (0..1 << bits).contains(&value),
+        };
+        if fits {
+            Ok(value as u32)
+        } else {
+            Err(self.invalid())
+        }
+    }
+
+    fn int(&self, i: usize) -> EncodeResult<u32> {
+        Ok(parse_int(self.get(i)?).ok_or_else(|| self.invalid())? as u32)
+    }
+
+    fn label(&self, i: usize) -> EncodeResult<String> {
+        let label = self.get(i)?.trim();
+        if is_label(label) {
+            Ok(label.to_string())
+        } else {
+            Err(self.invalid())
+        }
+    }
+
+    fn addr(&self, i: usize) -> EncodeResult<Address> {
+        Address::parse(self.get(i)?).ok_or_else(|| self.invalid())
+    }
+}
+
+/// Words of one instruction, which may take several for pseudo
+/// instructions. `$at` is used as a scratch register like MARS does.
+struct Encoder<'a> {
+    ops: Operands<'a>,
+    words: Vec<Word>,
+}
+
+impl Encoder<'_> {
+    fn push(&mut self, word: u32) {
+        self.words.push(Word::Fixed(word));
+    }
+
+    fn load_const(&mut self, rd: u32, value: u32) {
+        if fits_i16(value as i32 as i64) {
+            self.push(i_type(0x09, ZERO, rd, value));
+        } else if value <= 0xffff {
+            self.push(i_type(0x0d, ZERO, rd, value));
+        } else {
+            self.push(i_type(0x0f, ZERO, AT, value >> 16));
+            self.push(i_type(0x0d, AT, rd, value));
+        }
+    }
+
+    /// Register operand, or an immediate loaded into `$at`
+    fn reg_or_at(&mut self, i: usize) -> EncodeResult<u32> {
+        match self.ops.reg(i) {
+            Ok(reg) => Ok(reg),
+            Err(err) => match parse_int(self.ops.get(i)?) {
+                Some(value) => {
+                    self.load_const(AT, value as u32);
+                    Ok(AT)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    fn branch(&mut self, word: u32, label_index: usize) -> EncodeResult<()> {
+        let label = self.ops.label(label_index)?;
+        self.words.push(Word::Branch(word, label));
+        Ok(())
+    }
+
+    /// Three register instruction, also accepting an immediate last
+    fn alu(&mut self, funct: u32) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let (rd, rs) = (self.ops.reg(0)?, self.ops.reg(1)?);
+        let rt = self.reg_or_at(2)?;
+        self.push(r_type(rs, rt, rd, 0, funct));
+        Ok(())
+    }
+
+    /// Immediate instruction, falling back to the register form through
+    /// `$at` when the value doesn't fit
+    fn alu_imm(&mut self, op: u32, signed: bool, funct: u32) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let (rt, rs) = (self.ops.reg(0)?, self.ops.reg(1)?);
+        match self.ops.imm(2, 16, signed) {
+            Ok(imm) => self.push(i_type(op, rs, rt, imm)),
+            Err(_) => {
+                self.load_const(AT, self.ops.int(2)?);
+                self.push(r_type(rs, AT, rt, 0, funct));
+            }
+        }
+        Ok(())
+    }
+
+    fn shift(&mut self, funct: u32) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let (rd, rt) = (self.ops.reg(0)?, self.ops.reg(1)?);
+        self.push(r_type(ZERO, rt, rd, self.ops.imm(2, 5, false)?, funct));
+        Ok(())
+    }
+
+    fn shift_var(&mut self, funct: u32) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let (rd, rt, rs) = (self.ops.reg(0)?, self.ops.reg(1)?, self.ops.reg(2)?);
+        self.push(r_type(rs, rt, rd, 0, funct));
+        Ok(())
+    }
+
+    /// Load or store, going through `$at` for labels and wide offsets
+    fn memory(&mut self, op: u32, float: bool) -> EncodeResult<()> {
+        self.ops.count(2)?;
+        let rt = match float {
+            true => self.ops.float_reg(0)?,
+            false => self.ops.reg(0)?,
+        };
+        let addr = self.ops.addr(1)?;
+        match addr.direct() {
+            Some((base, offset)) => self.push(i_type(op, base, rt, offset)),
+            None => {
+                let target = addr.target();
+                self.words.push(Word::High {
+                    word: i_type(0x0f, ZERO, AT, 0),
+                    target: target.clone(),
+                    adjust: true,
+                });
+                if let Some(base) = addr.base {
+                    self.push(r_type(AT, base, AT, 0, 0x21));
+                }
+                self.words.push(Word::Low(i_type(op, AT, rt, 0), target));
+            }
+        }
+        Ok(())
+    }
+
+    fn load_addr(&mut self) -> EncodeResult<()> {
+        self.ops.count(2)?;
+        let rd = self.ops.reg(0)?;
+        let addr = self.ops.addr(1)?;
+        match addr.direct() {
+            Some((base, offset)) => self.push(i_type(0x09, base, rd, offset)),
+            None => {
+                let target = addr.target();
+                self.words.push(Word::High {
+                    word: i_type(0x0f, ZERO, AT, 0),
+                    target: target.clone(),
+                    adjust: false,
+                });
+                self.words.push(Word::Low(i_type(0x0d, AT, rd, 0), target));
+                if let Some(base) = addr.base {
+                    self.push(r_type(rd, base, rd, 0, 0x21));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares two registers with `slt`/`sltu` into `$at` and branches
+    /// on the result. `swap` compares the operands the other way round,
+    /// `on_set` branches when the comparison holds.
+    fn compare_branch(&mut self, funct: u32, swap: bool, on_set: bool) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let rs = self.ops.reg(0)?;
+        let rt = self.reg_or_at(1)?;
+        let (rs, rt) = if swap { (rt, rs) } else { (rs, rt) };
+        self.push(r_type(rs, rt, AT, 0, funct));
+        let op = if on_set { 0x05 } else { 0x04 };
+        self.branch(i_type(op, AT, ZERO, 0), 2)
+    }
+
+    /// Sets a register to the result of `slt`/`sltu`, optionally
+    /// swapping the operands or inverting the result
+    fn compare_set(&mut self, funct: u32, swap: bool, invert: bool) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let (rd, rs) = (self.ops.reg(0)?, self.ops.reg(1)?);
+        let rt = self.reg_or_at(2)?;
+        let (rs, rt) = if swap { (rt, rs) } else { (rs, rt) };
+        self.push(r_type(rs, rt, rd, 0, funct));
+        if invert {
+            self.push(i_type(0x0e, rd, rd, 1));
+        }
+        Ok(())
+    }
+
+    /// `div`/`divu` with two operands, or the three operand pseudo
+    /// instruction that moves the quotient or remainder to a register
+    fn divide(&mut self, funct: u32, result_funct: u32) -> EncodeResult<()> {
+        match self.ops.ops.len() {
+            2 if result_funct == 0x12 => {
+                self.push(r_type(self.ops.reg(0)?, self.ops.reg(1)?, 0, 0, funct));
+            }
+            3 => {
+                let (rd, rs) = (self.ops.reg(0)?, self.ops.reg(1)?);
+                let rt = self.reg_or_at(2)?;
+                self.push(r_type(rs, rt, 0, 0, funct));
+                self.push(r_type(0, 0, rd, 0, result_funct));
+            }
+            _ => return Err(self.ops.invalid()),
+        }
+        Ok(())
+    }
+
+    fn float3(&mut self, funct: u32) -> EncodeResult<()> {
+        self.ops.count(3)?;
+        let (fd, fs, ft) = (
+            self.ops.float_reg(0)?,
+            self.ops.float_reg(1)?,
+            self.ops.float_reg(2)?,
+        );
+        self.push(fr_type(FMT_S, ft, fs, fd, funct));
+        Ok(())
+    }
+
+    fn float2(&mut self, fmt: u32, funct: u32) -> EncodeResult<()> {
+        self.ops.count(2)?;
+        let (fd, fs) = (self.ops.float_reg(0)?, self.ops.float_reg(1)?);
+        self.push(fr_type(fmt, 0, fs, fd, funct));
+        Ok(())
+    }
+
+    fn float_compare(&mut self, funct: u32) -> EncodeResult<()> {
+        self.ops.count(2)?;
+        let (fs, ft) = (self.ops.float_reg(0)?, self.ops.float_reg(1)?);
+        self.push(fr_type(FMT_S, ft, fs, 0, funct));
+        Ok(())
+    }
+
+    fn float_move(&mut self, rs: u32) -> EncodeResult<()> {
+        self.ops.count(2)?;
+        let (rt, fs) = (self.ops.reg(0)?, self.ops.float_reg(1)?);
+        self.push(fr_type(rs, rt, fs, 0, 0));
+        Ok(())
+    }
+
+    fn encode(&mut self, name: &str) -> EncodeResult<()> {
+        let ops = &self.ops;
+        match name {
+            "add" => self.alu(0x20)?,
+            "addu" => self.alu(0x21)?,
+            "sub" => self.alu(0x22)?,
+            "subu" => self.alu(0x23)?,
+            "and" => self.alu(0x24)?,
+            "or" => self.alu(0x25)?,
+            "xor" => self.alu(0x26)?,
+            "nor" => self.alu(0x27)?,
+            "slt" => self.alu(0x2a)?,
+            "sltu" => self.alu(0x2b)?,
+            "addi" => self.alu_imm(0x08, true, 0x20)?,
+            "addiu" => self.alu_imm(0x09, true, 0x21)?,
+            "slti" => self.alu_imm(0x0a, true, 0x2a)?,
+            "sltiu" => self.alu_imm(0x0b, true, 0x2b)?,
+            "andi" => self.alu_imm(0x0c, false, 0x24)?,
+            "ori" => self.alu_imm(0x0d, false, 0x25)?,
+            "xori" => self.alu_imm(0x0e, false, 0x26)?,
+            "subi" | "subiu" => {
+                ops.count(3)?;
+                let (rd, rs, value) = (ops.reg(0)?, ops.reg(1)?, ops.int(2)?);
+                self.load_const(AT, value);
+                let funct = if name == "subi" { 0x22 } else { 0x23 };
+                self.push(r_type(rs, AT, rd, 0, funct));
+            }
+            "lui" => {
+                ops.count(2)?;
+                let (rt, imm) = (ops.reg(0)?, ops.imm(1, 16, false)?);
+                self.push(i_type(0x0f, ZERO, rt, imm));
+            }
+            "sll" => self.shift(0x00)?,
+            "srl" => self.shift(0x02)?,
+            "sra" => self.shift(0x03)?,
+            "sllv" => self.shift_var(0x04)?,
+            "srlv" => self.shift_var(0x06)?,
+            "srav" => self.shift_var(0x07)?,
+
+            "mult" | "multu" => {
+                ops.count(2)?;
+                let funct = if name == "mult" { 0x18 } else { 0x19 };
+                self.push(r_type(ops.reg(0)?, ops.reg(1)?, 0, 0, funct));
+            }
+            "div" => self.divide(0x1a, 0x12)?,
+            "divu" => self.divide(0x1b, 0x12)?,
+            "rem" => self.divide(0x1a, 0x10)?,
+            "remu" => self.divide(0x1b, 0x10)?,
+            "mul" => {
+                ops.count(3)?;
+                let (rd, rs) = (ops.reg(0)?, ops.reg(1)?);
+                let rt = self.reg_or_at(2)?;
+                self.push((SPECIAL2 << 26) | (rs << 21) | (rt << 16) | (rd << 11) | 0x02);
+            }
+            "mfhi" | "mflo" => {
+                ops.count(1)?;
+                let funct = if name == "mfhi" { 0x10 } else { 0x12 };
+                self.push(r_type(0, 0, ops.reg(0)?, 0, funct));
+            }
+            "mthi" | "mtlo" => {
+                ops.count(1)?;
+                let funct = if name == "mthi" { 0x11 } else { 0x13 };
+                self.push(r_type(ops.reg(0)?, 0, 0, 0, funct));
+            }
+
+            "lb" => self.memory(0x20, false)?,
+            "lh" => self.memory(0x21, false)?,
+            "lw" => self.memory(0x23, false)?,
+            "lbu" => self.memory(0x24, false)?,
+            "lhu" => self.memory(0x25, false)?,
+            "sb" => self.memory(0x28, false)?,
+            "sh" => self.memory(0x29, false)?,
+            "sw" => self.memory(0x2b, false)?,
+            "lwc1" | "l.s" => self.memory(0x31, true)?,
+            "swc1" | "s.s" => self.memory(0x39, true)?,
+
+            "j" | "jal" => {
+                ops.count(1)?;
+                let op = if name == "j" { 0x02 } else { 0x03 };
+                self.words.push(Word::Jump(op << 26, ops.label(0)?));
+            }
+            "jr" => {
+                ops.count(1)?;
+                self.push(r_type(ops.reg(0)?, 0, 0, 0, 0x08));
+            }
+            "jalr" => match ops.ops.len() {
+                1 => self.push(r_type(ops.reg(0)?, 0, RA, 0, 0x09)),
+                2 => self.push(r_type(ops.reg(1)?, 0, ops.reg(0)?, 0, 0x09)),
+                _ => return Err(ops.invalid()),
+            },
+            "beq" | "bne" => {
+                ops.count(3)?;
+                let rs = ops.reg(0)?;
+                let rt = self.reg_or_at(1)?;
+                let op = if name == "beq" { 0x04 } else { 0x05 };
+                self.branch(i_type(op, rs, rt, 0), 2)?;
+            }
+            "blez" | "bgtz" | "bltz" | "bgez" => {
+                ops.count(2)?;
+                let rs = ops.reg(0)?;
+                let word = match name {
+                    "blez" => i_type(0x06, rs, 0, 0),
+                    "bgtz" => i_type(0x07, rs, 0, 0),
+                    "bltz" => i_type(REGIMM, rs, 0, 0),
+                    _ => i_type(REGIMM, rs, 1, 0),
+                };
+                self.branch(word, 1)?;
+            }
+            "b" => {
+                ops.count(1)?;
+                self.branch(i_type(0x04, ZERO, ZERO, 0), 0)?;
+            }
+            "beqz" | "bnez" => {
+                ops.count(2)?;
+                let op = if name == "beqz" { 0x04 } else { 0x05 };
+                self.branch(i_type(op, ops.reg(0)?, ZERO, 0), 1)?;
+            }
+            "blt" => self.compare_branch(0x2a, false, true)?,
+            "bltu" => self.compare_branch(0x2b, false, true)?,
+            "bge" => self.compare_branch(0x2a, false, false)?,
+            "bgeu" => self.compare_branch(0x2b, false, false)?,
+            "bgt" => self.compare_branch(0x2a, true, true)?,
+            "bgtu" => self.compare_branch(0x2b, true, true)?,
+            "ble" => self.compare_branch(0x2a, true, false)?,
+            "bleu" => self.compare_branch(0x2b, true, false)?,
+
+            "seq" | "sne" => {
+                ops.count(3)?;
+                let (rd, rs) = (ops.reg(0)?, ops.reg(1)?);
+                let rt = self.reg_or_at(2)?;
+                self.push(r_type(rs, rt, rd, 0, 0x26));
+                match name {
+                    "seq" => self.push(i_type(0x0b, rd, rd, 1)),
+                    _ => self.push(r_type(ZERO, rd, rd, 0, 0x2b)),
+                }
+            }
+            "sgt" => self.compare_set(0x2a, true, false)?,
+            "sgtu" => self.compare_set(0x2b, true, false)?,
+            "sge" => self.compare_set(0x2a, false, true)?,
+            "sgeu" => self.compare_set(0x2b, false, true)?,
+            "sle" => self.compare_set(0x2a, true, true)?,
+            "sleu" => self.compare_set(0x2b, true, true)?,
+
+            "nop" => {
+                ops.count(0)?;
+                self.push(0);
+            }
+            "move" => {
+                ops.count(2)?;
+                self.push(r_type(ZERO, ops.reg(1)?, ops.reg(0)?, 0, 0x21));
+            }
+            "not" => {
+                ops.count(2)?;
+                self.push(r_type(ops.reg(1)?, ZERO, ops.reg(0)?, 0, 0x27));
+            }
+            "neg" | "negu" => {
+                ops.count(2)?;
+                let funct = if name == "neg" { 0x22 } else { 0x23 };
+                self.push(r_type(ZERO, ops.reg(1)?, ops.reg(0)?, 0, funct));
+            }
+            "abs" => {
+                ops.count(2)?;
+                let (rd, rs) = (ops.reg(0)?, ops.reg(1)?);
+                self.push(r_type(ZERO, rs, AT, 31, 0x03));
+                self.push(r_type(AT, rs, rd, 0, 0x26));
+                self.push(r_type(rd, AT, rd, 0, 0x23));
+            }
+            "li" => {
+                ops.count(2)?;
+                let (rd, value) = (ops.reg(0)?, ops.int(1)?);
+                self.load_const(rd, value);
+            }
+            "la" => self.load_addr()?,
+            "syscall" => {
+                ops.count(0)?;
+                self.push(0x0000_000c);
+            }
+            "break" => {
+                ops.count(0)?;
+                self.push(0x0000_000d);
+            }
+
+            "add.s" => self.float3(0x00)?,
+            "sub.s" => self.float3(0x01)?,
+            "mul.s" => self.float3(0x02)?,
+            "div.s" => self.float3(0x03)?,
+            "sqrt.s" => self.float2(FMT_S, 0x04)?,
+            "abs.s" => self.float2(FMT_S, 0x05)?,
+            "mov.s" => self.float2(FMT_S, 0x06)?,
+            "neg.s" => self.float2(FMT_S, 0x07)?,
+            "cvt.s.w" => self.float2(FMT_W, 0x20)?,
+            "cvt.w.s" => self.float2(FMT_S, 0x24)?,
+            "c.eq.s" => self.float_compare(0x32)?,
+            "c.lt.s" => self.float_compare(0x3c)?,
+            "c.le.s" => self.float_compare(0x3e)?,
+            "mfc1" => self.float_move(0x00)?,
+            "mtc1" => self.float_move(0x04)?,
+            "bc1f" | "bc1t" => {
+                ops.count(1)?;
+                let rt = if name == "bc1f" { 0 } else { 1 };
+                self.branch(i_type(COP1, 0x08, rt, 0), 0)?;
+            }
+            // Raw words placed between instructions
+            ".word" => {
+                for i in 0..ops.ops.len() {
+                    let word = self.ops.int(i)?;
+                    self.push(word);
+                }
+            }
+            _ => return Err(EncodeError::UnknownInstruction(self.ops.text.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// Encodes one line of assembly into the words it assembles to.
+/// Comments and blank lines give no words.
+pub fn encode_instruction(line: &str) -> EncodeResult<Vec<Word>> {
+    let text = line.split('#').next().unwrap_or("").trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (name, operands) = match text.split_once(char::is_whitespace) {
+        Some((name, operands)) => (name, operands.trim()),
+        None => (text, ""),
+    };
+    let ops = match operands {
+        "" => Vec::new(),
+        operands => operands.split(',').map(str::trim).collect(),
+    };
+    let mut encoder = Encoder {
+        ops: Operands { text, ops },
+        words: Vec::new(),
+    };
+    encoder.encode(&name.to_ascii_lowercase())?;
+    Ok(encoder.words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes instructions with no label references
+    fn encode(line: &str) -> Vec<u32> {
+        encode_instruction(line)
+            .unwrap()
+            .iter()
+            .map(|w| w.resolve(0x0040_0000, &HashMap::new()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn native_instructions() {
+        #[rustfmt::skip]
+        let table = [
+            ("add $t0, $t1, $t2", 0x012a_4020),
+            ("addu $t0, $t1, $t2", 0x012a_4021),
+            ("sub $t0, $t1, $t2", 0x012a_4022),
+            ("subu $t0, $t1, $t2", 0x012a_4023),
+            ("and $t0, $t1, $t2", 0x012a_4024),
+            ("or $t0, $t1, $t2", 0x012a_4025),
+            ("xor $t0, $t1, $t2", 0x012a_4026),
+            ("nor $t0, $t1, $t2", 0x012a_4027),
+            ("slt $t0, $t1, $t2", 0x012a_402a),
+            ("sltu $t0, $t1, $t2", 0x012a_402b),
+            ("addi $sp, $sp, -8", 0x23bd_fff8),
+            ("addiu $t0, $t1, 100", 0x2528_0064),
+            ("slti $t0, $t1, -1", 0x2928_ffff),
+            ("sltiu $t0, $t1, 1", 0x2d28_0001),
+            ("andi $t0, $t1, 255", 0x3128_00ff),
+            ("ori $t0, $zero, 5", 0x3408_0005),
+            ("xori $t0, $t0, 1", 0x3908_0001),
+            ("lui $at, 0x1001", 0x3c01_1001),
+            ("sll $t0, $t0, 2", 0x0008_4080),
+            ("srl $t0, $t1, 3", 0x0009_40c2),
+            ("sra $t0, $t1, 3", 0x0009_40c3),
+            ("sllv $t0, $t1, $t2", 0x0149_4004),
+            ("srav $t0, $t1, $t2", 0x0149_4007),
+            ("mult $t0, $t1", 0x0109_0018),
+            ("multu $t0, $t1", 0x0109_0019),
+            ("div $t0, $t1", 0x0109_001a),
+            ("divu $t0, $t1", 0x0109_001b),
+            ("mul $t0, $t1, $t2", 0x712a_4002),
+            ("mfhi $t0", 0x0000_4010),
+            ("mflo $t0", 0x0000_4012),
+            ("lb $t0, 0($a0)", 0x8088_0000),
+            ("lbu $t0, 1($a0)", 0x9088_0001),
+            ("lh $t0, 2($a0)", 0x8488_0002),
+            ("lhu $t0, 2($a0)", 0x9488_0002),
+            ("lw $t0, 4($sp)", 0x8fa8_0004),
+            ("sb $t0, -1($a0)", 0xa088_ffff),
+            ("sh $t0, 2($a0)", 0xa488_0002),
+            ("sw $ra, 0($sp)", 0xafbf_0000),
+            ("sw $t0, ($a0)", 0xac88_0000),
+            ("lwc1 $f4, 8($sp)", 0xc7a4_0008),
+            ("swc1 $f4, 8($sp)", 0xe7a4_0008),
+            ("jr $ra", 0x03e0_0008),
+            ("jalr $t0", 0x0100_f809),
+            ("syscall", 0x0000_000c),
+            ("nop", 0x0000_0000),
+            ("add.s $f4, $f5, $f6", 0x4606_2900),
+            ("sub.s $f4, $f5, $f6", 0x4606_2901),
+            ("mul.s $f4, $f5, $f6", 0x4606_2902),
+            ("div.s $f4, $f5, $f6", 0x4606_2903),
+            ("mov.s $f4, $f5", 0x4600_2906),
+            ("neg.s $f4, $f5", 0x4600_2907),
+            ("cvt.s.w $f4, $f5", 0x4680_2920),
+            ("cvt.w.s $f4, $f5", 0x4600_2924),
+            ("c.eq.s $f4, $f5", 0x4605_2032),
+            ("c.lt.s $f4, $f5", 0x4605_203c),
+            ("c.le.s $f4, $f5", 0x4605_203e),
+            ("mfc1 $t0, $f4", 0x4408_2000),
+            ("mtc1 $t0, $f4", 0x4488_2000),
+        ];
+        for (line, word) in table {
+            assert_eq!(encode(line), vec![word], "{}", line);
+        }
+    }
+
+    #[test]
+    fn pseudo_instructions() {
+        #[rustfmt::skip]
+        let table: [(&str, &[u32]); 13] = [
+            ("move $t0, $t1", &[0x0009_4021]),
+            ("li $t0, -1", &[0x2408_ffff]),
+            ("li $t0, 40000", &[0x3408_9c40]),
+            ("li $t0, 0x12345678", &[0x3c01_1234, 0x3428_5678]),
+            ("seq $t0, $t1, $t2", &[0x012a_4026, 0x2d08_0001]),
+            ("sne $t0, $t1, $t2", &[0x012a_4026, 0x0008_402b]),
+            ("sgt $t0, $t1, $t2", &[0x0149_402a]),
+            ("sge $t0, $t1, $t2", &[0x012a_402a, 0x3908_0001]),
+            ("sle $t0, $t1, $t2", &[0x0149_402a, 0x3908_0001]),
+            ("addi $t0, $t1, 70000", &[0x3c01_0001, 0x3421_1170, 0x0121_4020]),
+            ("lw $t0, 0x10010004($t1)", &[0x3c01_1001, 0x0029_0821, 0x8c28_0004]),
+            (".word 5, -1  # raw data", &[0x0000_0005, 0xffff_ffff]),
+            ("# only a comment", &[]),
+        ];
+        for (line, words) in table {
+            assert_eq!(encode(line), words, "{}", line);
+        }
+    }
+
+    #[test]
+    fn label_references() {
+        let labels = HashMap::from([
+            ("main".to_string(), 0x0040_0000),
+            ("loop".to_string(), 0x0040_0010),
+            ("data".to_string(), 0x1001_8004),
+        ]);
+        let resolve = |line: &str, pc: u32| -> Vec<u32> {
+            let words = encode_instruction(line).unwrap();
+            (words.iter().enumerate())
+                .map(|(i, w)| w.resolve(pc + 4 * i as u32, &labels).unwrap())
+                .collect()
+        };
+        assert_eq!(resolve("jal main", 0x0040_0020), [0x0c10_0000]);
+        assert_eq!(resolve("j loop", 0x0040_0000), [0x0810_0004]);
+        assert_eq!(resolve("b loop", 0x0040_0010), [0x1000_ffff]);
+        assert_eq!(resolve("beq $t0, $zero, loop", 0x0040_0000), [0x1100_0003]);
+        assert_eq!(resolve("bc1t loop", 0x0040_0000), [0x4501_0003]);
+        assert_eq!(resolve("bc1f loop", 0x0040_0000), [0x4500_0003]);
+        assert_eq!(
+            resolve("bltu $t0, $t1, loop", 0x0040_0000),
+            [0x0109_082b, 0x1420_0002]
+        );
+        assert_eq!(
+            resolve("bge $t0, $t1, loop", 0x0040_0000),
+            [0x0109_082a, 0x1020_0002]
+        );
+        // Lower half is negative when sign extended, so the upper half
+        // is rounded up for loads but not for `la`
+        assert_eq!(
+            resolve("lw $t0, data", 0x0040_0000),
+            [0x3c01_1002, 0x8c28_8004]
+        );
+        assert_eq!(
+            resolve("la $t0, data+4", 0x0040_0000),
+            [0x3c01_1001, 0x3428_8008]
+        );
+        assert_eq!(
+            resolve("l.s $f0, data($t1)", 0x0040_0000),
+            [0x3c01_1002, 0x0029_0821, 0xc420_8004]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            encode_instruction("frob $t0"),
+            Err(EncodeError::UnknownInstruction("frob $t0".to_string()))
+        );
+        assert!(matches!(
+            encode_instruction("sll $t0, $t1, 32"),
+            Err(EncodeError::InvalidOperands(_))
+        ));
+        assert!(matches!(
+            encode_instruction("add $t0, $t1"),
+            Err(EncodeError::InvalidOperands(_))
+        ));
+        let far = HashMap::from([("far".to_string(), 0x0050_0000)]);
+        let branch = &encode_instruction("b far").unwrap()[0];
+        assert_eq!(
+            branch.resolve(0x0040_0000, &far),
+            Err(EncodeError::OutOfRange("far".to_string()))
+        );
+        assert_eq!(
+            branch.resolve(0x0040_0000, &HashMap::new()),
+            Err(EncodeError::UndefinedLabel("far".to_string()))
+        );
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+
+use crate::encode::{encode_instruction, split_label, EncodeError, EncodeResult, Word};
+use crate::{DataDef, DataDirective, MipsBuilder, WORD_CONSTS_LBL};
+
+/// Start of the text segment in the MARS default memory layout
+pub const TEXT_BASE: u32 = 0x0040_0000;
+/// Start of the data segment in the MARS default memory layout
+pub const DATA_BASE: u32 = 0x1001_0000;
+const ENTRY_LABEL: &str = "main";
+
+/// Machine an image is encoded for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Machine {
+    /// MARS simulator, little-endian without branch delay slots
+    Mars,
+    /// Big-endian MIPS32 hardware, which runs the instruction after
+    /// every branch or jump
+    Mips32,
+}
+
+impl Machine {
+    fn big_endian(self) -> bool {
+        self == Self::Mips32
+    }
+
+    fn delay_slots(self) -> bool {
+        self == Self::Mips32
+    }
+
+    fn word_bytes(self, word: u32) -> [u8; 4] {
+        if self.big_endian() {
+            word.to_be_bytes()
+        } else {
+            word.to_le_bytes()
+        }
+    }
+}
+
+/// MARS memory dump formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Four bytes per word, in the machine's byte order
+    Binary,
+    /// One word per line as 8 hex digits
+    HexText,
+}
+
+/// Encoded program with its text and data segments
+pub struct Image {
+    pub machine: Machine,
+    pub text: Vec<u32>,
+    pub data: Vec<u8>,
+    pub entry: u32,
+}
+
+fn define_label(labels: &mut HashMap<String, u32>, label: &str, addr: u32) -> EncodeResult<()> {
+    match labels.insert(label.to_string(), addr) {
+        Some(_) => Err(EncodeError::DuplicateLabel(label.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Data segment bytes, with label words filled in once every label
+/// has an address
+struct DataSegment {
+    machine: Machine,
+    bytes: Vec<u8>,
+    label_words: Vec<(usize, String)>,
+}
+
+impl DataSegment {
+    fn addr(&self) -> u32 {
+        DATA_BASE + self.bytes.len() as u32
+    }
+
+    fn align(&mut self, size: usize) {
+        while !self.bytes.len().is_multiple_of(size) {
+            self.bytes.push(0);
+        }
+    }
+
+    fn push_half(&mut self, half: u16) {
+        if self.machine.big_endian() {
+            self.bytes.extend(half.to_be_bytes());
+        } else {
+            self.bytes.extend(half.to_le_bytes());
+        }
+    }
+
+    fn push_word(&mut self, word: u32) {
+        self.bytes.extend(self.machine.word_bytes(word));
+    }
+
+    fn push_dir(&mut self, dir: &DataDirective) {
+        match dir {
+            DataDirective::Word(words) => words.iter().for_each(|w| self.push_word(*w)),
+            DataDirective::WordLen { len, default } => {
+                (0..*len).for_each(|_| self.push_word(*default))
+            }
+            DataDirective::Float(floats) => floats.iter().for_each(|f| self.push_word(f.to_bits())),
+            DataDirective::FloatLen { len, default } => {
+                (0..*len).for_each(|_| self.push_word(default.to_bits()))
+            }
+            DataDirective::Labels(labels) => {
+                for label in labels {
+                    self.label_words.push((self.bytes.len(), label.clone()));
+                    self.push_word(0);
+                }
+            }
+            DataDirective::Half(halves) => halves.iter().for_each(|h| self.push_half(*h)),
+            DataDirective::HalfLen { len, default } => {
+                (0..*len).for_each(|_| self.push_half(*default))
+            }
+            DataDirective::Byte(bytes) => self.bytes.extend(bytes),
+            DataDirective::ByteLen { len, default } => {
+                self.bytes.extend(std::iter::repeat_n(*default, *len))
+            }
+            DataDirective::Asciiz(txt) => {
+                self.bytes.extend(txt);
+                self.bytes.push(0);
+            }
+        }
+    }
+
+    /// Lays out a data def. Like MARS, the label points past the padding
+    /// that aligns the first directive.
+    fn push_def(&mut self, def: &DataDef, labels: &mut HashMap<String, u32>) -> EncodeResult<()> {
+        let mut labeled = false;
+        for dir in &def.vals {
+            self.align(dir.alignment());
+            if !labeled {
+                define_label(labels, &def.name, self.addr())?;
+                labeled = true;
+            }
+            self.push_dir(dir);
+        }
+        if !labeled {
+            define_label(labels, &def.name, self.addr())?;
+        }
+        Ok(())
+    }
+}
+
+impl DataDirective {
+    fn alignment(&self) -> usize {
+        match self {
+            Self::Word(_)
+            | Self::WordLen { .. }
+            | Self::Float(_)
+            | Self::FloatLen { .. }
+            | Self::Labels(_) => 4,
+            Self::Half(_) | Self::HalfLen { .. } => 2,
+            Self::Byte(_) | Self::ByteLen { .. } | Self::Asciiz(_) => 1,
+        }
+    }
+}
+
+impl MipsBuilder {
+    /// Encodes the program into machine code placed at the MARS default
+    /// addresses. Execution starts at `main`, or the start of the text
+    /// segment if there's none.
+    pub fn encode(&self, machine: Machine) -> EncodeResult<Image> {
+        let mut labels = HashMap::new();
+
+        let mut words = Vec::new();
+        for block in &self.blocks {
+            let addr = TEXT_BASE + 4 * words.len() as u32;
+            define_label(&mut labels, &block.label, addr)?;
+            for line in block.instructions.iter().flat_map(|i| i.lines()) {
+                // Inline asm can define its own labels
+                let (label, line) = split_label(line);
+                if let Some(label) = label {
+                    define_label(&mut labels, label, TEXT_BASE + 4 * words.len() as u32)?;
+                }
+                for word in encode_instruction(line)? {
+                    let transfer = word.is_transfer();
+                    words.push(word);
+                    if transfer && machine.delay_slots() {
+                        words.push(Word::Fixed(0));
+                    }
+                }
+            }
+        }
+
+        let mut data = DataSegment {
+            machine,
+            bytes: Vec::new(),
+            label_words: Vec::new(),
+        };
+        if !self.word_consts.is_empty() {
+            let mut word_bank = DataDef::new(WORD_CONSTS_LBL);
+            word_bank.add_dir(self.word_consts.clone());
+            data.push_def(&word_bank, &mut labels)?;
+        }
+        for def in &self.data_vars {
+            data.push_def(def, &mut labels)?;
+        }
+        for (offset, label) in &data.label_words {
+            let addr = labels
+                .get(label)
+                .ok_or_else(|| EncodeError::UndefinedLabel(label.clone()))?;
+            data.bytes[*offset..*offset + 4].copy_from_slice(&machine.word_bytes(*addr));
+        }
+
+        let text = (words.iter().enumerate())
+            .map(|(i, word)| word.resolve(TEXT_BASE + 4 * i as u32, &labels))
+            .collect::<EncodeResult<Vec<_>>>()?;
+        Ok(Image {
+            machine,
+            text,
+            data: data.bytes,
+            entry: labels.get(ENTRY_LABEL).copied().unwrap_or(TEXT_BASE),
+        })
+    }
+}
+
+impl Image {
+    fn text_bytes(&self) -> Vec<u8> {
+        let bytes = self.text.iter().map(|w| self.machine.word_bytes(*w));
+        bytes.flatten().collect()
+    }
+
+    /// Data segment padded to whole words
+    fn data_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.data.clone();
+        bytes.resize(self.data.len().next_multiple_of(4), 0);
+        bytes
+    }
+
+    fn dump(&self, bytes: &[u8], format: DumpFormat) -> Vec<u8> {
+        match format {
+            DumpFormat::Binary => bytes.to_vec(),
+            DumpFormat::HexText => {
+                let mut text = String::new();
+                for word in bytes.chunks(4) {
+                    let word = word.try_into().unwrap();
+                    let word = if self.machine.big_endian() {
+                        u32::from_be_bytes(word)
+                    } else {
+                        u32::from_le_bytes(word)
+                    };
+                    text.push_str(&format!("{:08x}\n", word));
+                }
+                text.into_bytes()
+            }
+        }
+    }
+
+    /// Text segment as saved by the MARS "Dump Memory" tool
+    pub fn text_dump(&self, format: DumpFormat) -> Vec<u8> {
+        self.dump(&self.text_bytes(), format)
+    }
+
+    /// Data segment as saved by the MARS "Dump Memory" tool
+    pub fn data_dump(&self, format: DumpFormat) -> Vec<u8> {
+        self.dump(&self.data_bytes(), format)
+    }
+
+    /// Minimal ELF32 executable loading both segments, with section
+    /// headers so the usual tools can disassemble it. Syscalls keep
+    /// their MARS service numbers, so the program can be inspected but
+    /// won't run under a MIPS Linux kernel.
+    pub fn elf(&self) -> Vec<u8> {
+        const PAGE: usize = 0x1000;
+        const HEADER_SIZE: usize = 52;
+        const PROGRAM_HEADER_SIZE: usize = 32;
+        const SECTION_HEADER_SIZE: usize = 40;
+        const SECTION_NAMES: &[u8] = b"\0.text\0.data\0.shstrtab\0";
+
+        let text = self.text_bytes();
+        let data = self.data_bytes();
+        // Segments start on pages so their file offsets match their
+        // addresses modulo the page size
+        let text_offset = PAGE;
+        let data_offset = (text_offset + text.len()).next_multiple_of(PAGE);
+        let names_offset = data_offset + data.len();
+        let sections_offset = (names_offset + SECTION_NAMES.len()).next_multiple_of(4);
+
+        let mut elf = Elf {
+            big_endian: self.machine.big_endian(),
+            bytes: Vec::new(),
+        };
+        // Identification: 32-bit, byte order, current version
+        elf.bytes.extend(b"\x7fELF\x01");
+        elf.bytes.push(if elf.big_endian { 2 } else { 1 });
+        elf.bytes.push(1);
+        elf.bytes.resize(16, 0);
+        elf.half(2); // Executable
+        elf.half(8); // MIPS
+        elf.word(1);
+        elf.word(self.entry);
+        elf.word(HEADER_SIZE as u32);
+        elf.word(sections_offset as u32);
+        elf.word(0x5000_1000); // MIPS32, O32 ABI
+        elf.half(HEADER_SIZE as u16);
+        elf.half(PROGRAM_HEADER_SIZE as u16);
+        elf.half(2);
+        elf.half(SECTION_HEADER_SIZE as u16);
+        elf.half(4);
+        elf.half(3);
+
+        // Loadable segments: readable and executable text, readable and
+        // writable data
+        for (offset, addr, size, flags) in [
+            (text_offset, TEXT_BASE, text.len(), 5),
+            (data_offset, DATA_BASE, data.len(), 6),
+        ] {
+            elf.word(1);
+            elf.word(offset as u32);
+            elf.word(addr);
+            elf.word(addr);
+            elf.word(size as u32);
+            elf.word(size as u32);
+            elf.word(flags);
+            elf.word(PAGE as u32);
+        }
+
+        elf.bytes.resize(text_offset, 0);
+        elf.bytes.extend(&text);
+        elf.bytes.resize(data_offset, 0);
+        elf.bytes.extend(&data);
+        elf.bytes.extend(SECTION_NAMES);
+        elf.bytes.resize(sections_offset, 0);
+
+        // Null section, then .text, .data and the section name table
+        elf.bytes.extend([0; SECTION_HEADER_SIZE]);
+        let sections = [
+            (1, 1, 6, TEXT_BASE, text_offset, text.len(), 4),
+            (7, 1, 3, DATA_BASE, data_offset, data.len(), 4),
+            (13, 3, 0, 0, names_offset, SECTION_NAMES.len(), 1),
+        ];
+        for (name, kind, flags, addr, offset, size, align) in sections {
+            elf.word(name);
+            elf.word(kind);
+            elf.word(flags);
+            elf.word(addr);
+            elf.word(offset as u32);
+            elf.word(size as u32);
+            elf.word(0);
+            elf.word(0);
+            elf.word(align);
+            elf.word(0);
+        }
+        elf.bytes
+    }
+}
+
+/// ELF file contents in the image's byte order
+struct Elf {
+    big_endian: bool,
+    bytes: Vec<u8>,
+}
+
+impl Elf {
+    fn half(&mut self, half: u16) {
+        if self.big_endian {
+            self.bytes.extend(half.to_be_bytes());
+        } else {
+            self.bytes.extend(half.to_le_bytes());
+        }
+    }
+
+    fn word(&mut self, word: u32) {
+        if self.big_endian {
+            self.bytes.extend(word.to_be_bytes());
+        } else {
+            self.bytes.extend(word.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Register;
+
+    fn program() -> MipsBuilder {
+        let mut b = MipsBuilder::new();
+        let mut bytes = DataDef::new("bytes");
+        bytes.add_dir(vec![1u8, 2, 3]);
+        b.add_def(bytes);
+        let mut table = DataDef::new("table");
+        table.add_dir(DataDirective::Labels(vec!["main".to_string()]));
+        b.add_def(table);
+        b.new_block("main");
+        b.load_word(Register::T0, "table");
+        b.jump_register(Register::T0);
+        b
+    }
+
+    #[test]
+    fn data_layout() {
+        let image = program().encode(Machine::Mars).unwrap();
+        assert_eq!(image.entry, TEXT_BASE);
+        // Label word aligned past the bytes, holding the address of main
+        assert_eq!(image.data, [1, 2, 3, 0, 0x00, 0x00, 0x40, 0x00]);
+        assert_eq!(image.text, [0x3c01_1001, 0x8c28_0004, 0x0100_0008]);
+        assert_eq!(
+            image.data_dump(DumpFormat::HexText),
+            b"00030201\n00400000\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn asciiz_bytes() {
+        let mut b = MipsBuilder::new();
+        let mut text = DataDef::new("text");
+        text.add_dir(DataDirective::Asciiz(vec![b'a', 0x80, 0xff]));
+        b.add_def(text);
+        b.new_block("main");
+        let image = b.encode(Machine::Mars).unwrap();
+        assert_eq!(image.data, [b'a', 0x80, 0xff, 0]);
+    }
+
+    #[test]
+    fn delay_slots() {
+        let image = program().encode(Machine::Mips32).unwrap();
+        assert_eq!(image.data[4..], [0x00, 0x40, 0x00, 0x00]);
+        assert_eq!(image.text, [0x3c01_1001, 0x8c28_0004, 0x0100_0008, 0]);
+        let elf = image.elf();
+        assert_eq!(elf[..6], *b"\x7fELF\x01\x02");
+        assert_eq!(elf[0x1000..0x1004], [0x3c, 0x01, 0x10, 0x01]);
+    }
+}
@@ -1,26 +1,40 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::ops::Add;
 
+mod encode;
+mod image;
+mod mnemonics;
 mod registers;
+pub use encode::*;
+pub use image::*;
+pub use mnemonics::*;
 pub use registers::*;
 
 const FMT_ITEMS_PER_LINE: usize = 10;
-const WORD_CONSTS_LBL: &'static str = "WORD_CONST";
+pub const WORD_CONSTS_LBL: &str = "WORD_CONST";
+
+/// Row and column in the source program that an instruction came from
+pub type Origin = (usize, usize);
 
 pub struct MipsBlock {
     label: String,
     instructions: Vec<String>,
+    origins: Vec<Option<Origin>>,
 }
 
 impl MipsBlock {
-    fn append(&self, s: &mut String) {
+    fn append(&self, s: &mut String, lines: &mut Vec<Option<Origin>>) {
         s.push_str(&self.label);
         s.push_str(":\n");
-        for instr in &self.instructions {
+        lines.push(None);
+        for (instr, origin) in self.instructions.iter().zip(&self.origins) {
             s.push('\t');
             s.push_str(instr);
             s.push('\n');
+            let line_count = instr.matches('\n').count() + 1;
+            lines.extend(std::iter::repeat_n(*origin, line_count));
         }
     }
 }
@@ -53,8 +67,10 @@ pub enum MipsAddress<'a> {
 }
 
 /// Add or subtract to mips address offset
-impl<'a> MipsAddress<'a> {
-    pub fn add(self, offset: i32) -> Self {
+impl Add<i32> for MipsAddress<'_> {
+    type Output = Self;
+
+    fn add(self, offset: i32) -> Self {
         match self {
             Self::Addr(u) => Self::Addr(u.wrapping_add_signed(offset)),
             Self::RegisterAddr { register, addr } => Self::RegisterAddr {
@@ -151,12 +167,28 @@ fn write_len_directive(s: &mut String, directive: &str, len: usize, default: imp
 
 pub enum DataDirective {
     Word(Vec<u32>),
-    WordLen { len: usize, default: u32 },
-    Asciiz(String),
+    WordLen {
+        len: usize,
+        default: u32,
+    },
+    Asciiz(Vec<u8>),
+    Half(Vec<u16>),
+    HalfLen {
+        len: usize,
+        default: u16,
+    },
     Byte(Vec<u8>),
-    ByteLen { len: usize, default: u8 },
+    ByteLen {
+        len: usize,
+        default: u8,
+    },
     Float(Vec<f32>),
-    FloatLen { len: usize, default: f32 },
+    FloatLen {
+        len: usize,
+        default: f32,
+    },
+    /// Words holding the addresses of labels
+    Labels(Vec<String>),
 }
 
 impl From<Vec<u32>> for DataDirective {
@@ -183,6 +215,21 @@ impl From<i32> for DataDirective {
     }
 }
 
+impl From<Vec<u16>> for DataDirective {
+    fn from(value: Vec<u16>) -> Self {
+        Self::Half(value)
+    }
+}
+
+impl From<u16> for DataDirective {
+    fn from(value: u16) -> Self {
+        Self::HalfLen {
+            len: 1,
+            default: value,
+        }
+    }
+}
+
 impl From<Vec<u8>> for DataDirective {
     fn from(value: Vec<u8>) -> Self {
         Self::Byte(value)
@@ -215,8 +262,29 @@ impl From<Vec<f32>> for DataDirective {
 
 impl From<String> for DataDirective {
     fn from(value: String) -> Self {
-        Self::Asciiz(value)
+        Self::Asciiz(value.into_bytes())
+    }
+}
+
+/// Quotes and escapes a string for an .asciiz directive. Returns None if
+/// the string has a byte MARS has no escape sequence for
+fn escape_asciiz(txt: &[u8]) -> Option<String> {
+    let mut escaped = String::with_capacity(txt.len() + 2);
+    escaped.push('"');
+    for &c in txt {
+        match c {
+            b'\n' => escaped.push_str("\\n"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\0' => escaped.push_str("\\0"),
+            b'\\' => escaped.push_str("\\\\"),
+            b'"' => escaped.push_str("\\\""),
+            b' '..=b'~' => escaped.push(c as char),
+            _ => return None,
+        }
     }
+    escaped.push('"');
+    Some(escaped)
 }
 
 impl DataDirective {
@@ -224,15 +292,25 @@ impl DataDirective {
         match self {
             Self::Word(u) => write_group_directive(s, ".word", u),
             Self::WordLen { len, default } => write_len_directive(s, ".word", *len, default),
-            Self::Asciiz(txt) => {
-                s.push_str(".asciiz ");
-                s.push_str(txt);
-                s.push('\n');
-            }
+            Self::Asciiz(txt) => match escape_asciiz(txt) {
+                Some(escaped) => {
+                    s.push_str(".asciiz ");
+                    s.push_str(&escaped);
+                    s.push('\n');
+                }
+                None => {
+                    let mut bytes = txt.clone();
+                    bytes.push(0);
+                    write_group_directive(s, ".byte", &bytes);
+                }
+            },
+            Self::Half(h) => write_group_directive(s, ".half", h),
+            Self::HalfLen { len, default } => write_len_directive(s, ".half", *len, default),
             Self::Byte(b) => write_group_directive(s, ".byte", b),
             Self::ByteLen { len, default } => write_len_directive(s, ".byte", *len, default),
             Self::Float(f) => write_group_directive(s, ".float", f),
             Self::FloatLen { len, default } => write_len_directive(s, ".float", *len, default),
+            Self::Labels(labels) => write_group_directive(s, ".word", labels),
         }
     }
 }
@@ -269,6 +347,8 @@ pub struct MipsBuilder {
     word_consts: Vec<u32>,
     data_vars: Vec<DataDef>,
     blocks: Vec<MipsBlock>,
+    globals: Vec<String>,
+    origin: Option<Origin>,
 }
 
 impl MipsBuilder {
@@ -281,14 +361,27 @@ impl MipsBuilder {
         self.blocks.push(MipsBlock {
             label,
             instructions: Vec::new(),
+            origins: Vec::new(),
         });
     }
 
     pub fn instr(&mut self, text: String) {
         if let Some(block) = self.blocks.last_mut() {
             block.instructions.push(text);
+            block.origins.push(self.origin);
         }
     }
+
+    /// Adds a comment line to the current block
+    pub fn comment(&mut self, text: &str) {
+        self.instr(format!("# {}", text));
+    }
+
+    /// Sets the source location of the instructions added from now on,
+    /// returning the previous one
+    pub fn set_origin(&mut self, origin: Option<Origin>) -> Option<Origin> {
+        std::mem::replace(&mut self.origin, origin)
+    }
     fn instr2(&mut self, t: &str, a: impl Into<GenericRegister>, b: impl Into<GenericRegister>) {
         self.instr(format!("{} {}, {}", t, a.into(), b.into()));
     }
@@ -336,9 +429,21 @@ impl MipsBuilder {
     pub fn load_byte<'a>(&mut self, dest: Register, loc: impl Into<MipsAddress<'a>>) {
         self.addr_instr("lb", dest, loc.into());
     }
+    pub fn load_byte_unsigned<'a>(&mut self, dest: Register, loc: impl Into<MipsAddress<'a>>) {
+        self.addr_instr("lbu", dest, loc.into());
+    }
     pub fn save_byte<'a>(&mut self, source: Register, loc: impl Into<MipsAddress<'a>>) {
         self.addr_instr("sb", source, loc.into());
     }
+    pub fn load_half<'a>(&mut self, dest: Register, loc: impl Into<MipsAddress<'a>>) {
+        self.addr_instr("lh", dest, loc.into());
+    }
+    pub fn load_half_unsigned<'a>(&mut self, dest: Register, loc: impl Into<MipsAddress<'a>>) {
+        self.addr_instr("lhu", dest, loc.into());
+    }
+    pub fn save_half<'a>(&mut self, source: Register, loc: impl Into<MipsAddress<'a>>) {
+        self.addr_instr("sh", source, loc.into());
+    }
     pub fn load_f32<'a>(&mut self, dest: FloatRegister, loc: impl Into<MipsAddress<'a>>) {
         self.addr_instr("l.s", dest, loc.into());
     }
@@ -401,10 +506,27 @@ impl MipsBuilder {
         self.instr2("div", source1, source2);
         self.move_from_hi(dest);
     }
+    pub fn div_u32(&mut self, dest: Register, source1: Register, source2: Register) {
+        self.instr2("divu", source1, source2);
+        self.move_from_lo(dest);
+    }
+    pub fn mod_u32(&mut self, dest: Register, source1: Register, source2: Register) {
+        self.instr2("divu", source1, source2);
+        self.move_from_hi(dest);
+    }
 
     pub fn shift_logical_left(&mut self, dest: Register, source: Register, value: u32) {
         self.instr2_const("sll", dest, source, value);
     }
+    pub fn shift_arith_right(&mut self, dest: Register, source: Register, value: u32) {
+        self.instr2_const("sra", dest, source, value);
+    }
+    pub fn and_const(&mut self, dest: Register, source: Register, val: u16) {
+        self.instr2_const("andi", dest, source, val);
+    }
+    pub fn or_const(&mut self, dest: Register, source: Register, val: u16) {
+        self.instr2_const("ori", dest, source, val);
+    }
     pub fn add_const_i32(&mut self, dest: Register, source: Register, val: i32) {
         self.instr2_const("addi", dest, source, val);
     }
@@ -434,6 +556,18 @@ impl MipsBuilder {
     pub fn or_i32(&mut self, dest: Register, source1: Register, source2: Register) {
         self.instr3("or", dest, source1, source2);
     }
+    pub fn xor_i32(&mut self, dest: Register, source1: Register, source2: Register) {
+        self.instr3("xor", dest, source1, source2);
+    }
+    pub fn shift_left(&mut self, dest: Register, source: Register, amount: Register) {
+        self.instr3("sllv", dest, source, amount);
+    }
+    pub fn shift_right_i32(&mut self, dest: Register, source: Register, amount: Register) {
+        self.instr3("srav", dest, source, amount);
+    }
+    pub fn shift_right_u32(&mut self, dest: Register, source: Register, amount: Register) {
+        self.instr3("srlv", dest, source, amount);
+    }
 
     pub fn jump_register(&mut self, dest: Register) {
         self.instr(format!("jr {}", dest));
@@ -441,6 +575,9 @@ impl MipsBuilder {
     pub fn jump_and_link(&mut self, fn_name: &str) {
         self.instr(format!("jal {}", fn_name));
     }
+    pub fn jump_and_link_register(&mut self, target: Register) {
+        self.instr(format!("jalr {}", target));
+    }
 
     pub fn branch_eq(&mut self, reg1: Register, reg2: Register, lbl: &str) {
         self.branch_instr("beq", reg1, reg2, lbl);
@@ -448,6 +585,15 @@ impl MipsBuilder {
     pub fn branch_not_eq(&mut self, reg1: Register, reg2: Register, lbl: &str) {
         self.branch_instr("bne", reg1, reg2, lbl);
     }
+    pub fn branch_lt(&mut self, reg1: Register, reg2: Register, lbl: &str) {
+        self.branch_instr("blt", reg1, reg2, lbl);
+    }
+    pub fn branch_lt_u32(&mut self, reg1: Register, reg2: Register, lbl: &str) {
+        self.branch_instr("bltu", reg1, reg2, lbl);
+    }
+    pub fn branch_ge_u32(&mut self, reg1: Register, reg2: Register, lbl: &str) {
+        self.branch_instr("bgeu", reg1, reg2, lbl);
+    }
     pub fn branch_eq_zero(&mut self, reg1: Register, lbl: &str) {
         self.branch_eq(reg1, Register::Zero, lbl);
     }
@@ -510,13 +656,28 @@ impl MipsBuilder {
     pub fn add_def(&mut self, d: DataDef) {
         self.data_vars.push(d);
     }
+    /// Marks a label as visible to other assembled files
+    pub fn add_global(&mut self, label: impl Into<String>) {
+        self.globals.push(label.into());
+    }
     pub fn add_syscall(&mut self, id: u8) {
         self.const_word(id as u32, Register::V0);
         self.instr("syscall".to_string());
     }
 
     pub fn codegen(self) -> String {
+        self.codegen_with_origins().0
+    }
+
+    /// Generates the assembly along with the source location of every
+    /// line of it
+    pub fn codegen_with_origins(self) -> (String, Vec<Option<Origin>>) {
         let mut buf = String::new();
+        for global in &self.globals {
+            buf.push_str("\t.globl ");
+            buf.push_str(global);
+            buf.push('\n');
+        }
         buf.push_str("\t.data\n");
 
         // Write word constants
@@ -534,10 +695,11 @@ impl MipsBuilder {
         buf.push_str("\n\t.text\n");
 
         // Write instructions
+        let mut lines = vec![None; buf.matches('\n').count()];
         for block in self.blocks {
-            block.append(&mut buf);
+            block.append(&mut buf, &mut lines);
         }
 
-        buf
+        (buf, lines)
     }
 }
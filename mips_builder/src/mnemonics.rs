@@ -0,0 +1,35 @@
+/// Instructions and pseudo instructions accepted by MARS
+#[rustfmt::skip]
+static MNEMONICS: &[&str] = &[
+    // Arithmetic and logic
+    "add", "addu", "addi", "addiu", "sub", "subu", "subi", "subiu", "and", "andi", "or", "ori",
+    "xor", "xori", "nor", "not", "neg", "negu", "abs", "sll", "srl", "sra", "sllv", "srlv", "srav",
+    "rol", "ror", "slt", "sltu", "slti", "sltiu", "seq", "sne", "sge", "sgeu", "sgt", "sgtu", "sle",
+    "sleu", "lui", "li", "la", "move", "clo", "clz", "movn", "movz", "movf", "movt", "nop",
+    // Multiplication and division
+    "mult", "multu", "mul", "mulu", "mulo", "mulou", "div", "divu", "rem", "remu", "madd", "maddu",
+    "msub", "msubu", "mfhi", "mflo", "mthi", "mtlo",
+    // Memory
+    "lb", "lbu", "lh", "lhu", "lw", "lwl", "lwr", "ll", "sb", "sh", "sw", "swl", "swr", "sc", "ulh",
+    "ulhu", "ulw", "ush", "usw", "ld", "sd",
+    // Branches and jumps
+    "b", "beq", "bne", "beqz", "bnez", "bgez", "bgezal", "bgtz", "blez", "bltz", "bltzal", "bge",
+    "bgeu", "bgt", "bgtu", "ble", "bleu", "blt", "bltu", "bal", "j", "jal", "jr", "jalr",
+    // Traps and system
+    "syscall", "break", "eret", "teq", "teqi", "tne", "tnei", "tge", "tgeu", "tgei", "tgeiu", "tlt",
+    "tltu", "tlti", "tltiu",
+    // Coprocessor 1
+    "add.s", "add.d", "sub.s", "sub.d", "mul.s", "mul.d", "div.s", "div.d", "abs.s", "abs.d",
+    "neg.s", "neg.d", "mov.s", "mov.d", "sqrt.s", "sqrt.d", "cvt.s.w", "cvt.s.d", "cvt.d.w",
+    "cvt.d.s", "cvt.w.s", "cvt.w.d", "ceil.w.s", "ceil.w.d", "floor.w.s", "floor.w.d", "round.w.s",
+    "round.w.d", "trunc.w.s", "trunc.w.d", "c.eq.s", "c.eq.d", "c.lt.s", "c.lt.d", "c.le.s",
+    "c.le.d", "bc1t", "bc1f", "movf.s", "movf.d", "movt.s", "movt.d", "movn.s", "movn.d", "movz.s",
+    "movz.d", "mfc1", "mtc1", "mfc1.d", "mtc1.d", "lwc1", "swc1", "ldc1", "sdc1", "l.s", "l.d",
+    "s.s", "s.d",
+];
+
+/// Checks if an instruction name is known to the assembler
+pub fn is_mnemonic(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    MNEMONICS.contains(&name.as_str())
+}
@@ -189,3 +189,20 @@ impl std::fmt::Display for GenericRegister {
         }
     }
 }
+
+/// General purpose registers by number
+#[rustfmt::skip]
+static REGISTER_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp",
+    "ra",
+];
+
+/// Number of an integer register written as `$name` or `$N`
+pub fn register_number(s: &str) -> Option<u32> {
+    let name = s.trim().strip_prefix('$')?;
+    match REGISTER_NAMES.iter().position(|r| *r == name) {
+        Some(index) => Some(index as u32),
+        None => name.parse().ok().filter(|r| *r < 32),
+    }
+}